@@ -0,0 +1,253 @@
+//! Decoders for Bubblegum and account compression program accounts
+
+use anchor_lang::AccountDeserialize;
+pub use mpl_bubblegum::state::TreeConfig;
+use solana_sdk::pubkey::Pubkey;
+use spl_account_compression::state::CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1;
+
+use crate::{
+    tree::{empty_root, NODE_SIZE},
+    Error, Result,
+};
+
+/// Decode the data of a Bubblegum tree configuration account
+///
+/// # Errors
+/// This function fails if the data is not a valid tree configuration.
+pub fn decode_tree_config(data: &[u8]) -> Result<TreeConfig> {
+    TreeConfig::try_deserialize(&mut &*data).map_err(|e| Error::InvalidAccountData {
+        kind: "tree config",
+        reason: e.to_string(),
+    })
+}
+
+/// The decoded state of a concurrent Merkle tree account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MerkleTreeAccount {
+    /// The depth of the tree
+    pub max_depth: u32,
+    /// The number of concurrent changes the tree can buffer
+    pub max_buffer_size: u32,
+    /// The authority allowed to modify the tree
+    pub authority: Pubkey,
+    /// The slot the tree was initialized in
+    pub creation_slot: u64,
+    /// The number of changes applied to the tree
+    pub sequence_number: u64,
+    /// The current root of the tree
+    pub root: [u8; 32],
+    /// The index of the rightmost leaf appended to the tree
+    pub rightmost_index: u32,
+    /// The number of cached upper levels stored after the tree
+    pub canopy_depth: u32,
+}
+
+impl MerkleTreeAccount {
+    /// The number of leaves the tree can hold
+    #[inline]
+    #[must_use]
+    pub fn capacity(&self) -> u64 { 1_u64.checked_shl(self.max_depth).unwrap_or(u64::MAX) }
+
+    /// Whether every leaf in the tree is empty, i.e. whether the account
+    /// compression program will allow it to be closed
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool { self.root == empty_root(self.max_depth) }
+}
+
+/// Account type tag of an initialized concurrent Merkle tree
+const ACCOUNT_TYPE_TREE: u8 = 1;
+/// Size of a change log entry, excluding its path
+pub(crate) const CHANGE_LOG_SIZE: usize = 32 + 4 + 4;
+/// Size of the sequence number, active index and buffer size fields
+pub(crate) const TREE_PREFIX_SIZE: usize = 8 * 3;
+
+/// Compute the size of a concurrent Merkle tree's body, excluding the header
+/// and canopy
+///
+/// Returns `None` if the size overflows a `usize`.
+#[must_use]
+pub(crate) fn tree_body_size(max_depth: usize, max_buffer_size: usize) -> Option<usize> {
+    let path = NODE_SIZE.checked_mul(max_depth)?;
+    let change_logs = CHANGE_LOG_SIZE
+        .checked_add(path)?
+        .checked_mul(max_buffer_size)?;
+
+    TREE_PREFIX_SIZE
+        .checked_add(change_logs)?
+        .checked_add(path)?
+        .checked_add(32 + 4 + 4)
+}
+
+pub(crate) fn read<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N]> {
+    offset
+        .checked_add(N)
+        .and_then(|end| data.get(offset..end))
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| Error::InvalidAccountData {
+            kind: "Merkle tree",
+            reason: "Account data is too short".into(),
+        })
+}
+
+/// Decode the data of a concurrent Merkle tree account
+///
+/// # Errors
+/// This function fails if the data is not an initialized Merkle tree.
+pub fn decode_merkle_tree(data: &[u8]) -> Result<MerkleTreeAccount> {
+    let invalid = |reason: &str| Error::InvalidAccountData {
+        kind: "Merkle tree",
+        reason: reason.into(),
+    };
+
+    let [account_type, version] = read(data, 0)?;
+    if account_type != ACCOUNT_TYPE_TREE {
+        return Err(invalid("Account is not an initialized tree"));
+    }
+    if version != 0 {
+        return Err(invalid("Unsupported tree header version"));
+    }
+
+    let max_buffer_size = u32::from_le_bytes(read(data, 2)?);
+    let max_depth = u32::from_le_bytes(read(data, 6)?);
+    let authority = Pubkey::new_from_array(read(data, 10)?);
+    let creation_slot = u64::from_le_bytes(read(data, 42)?);
+
+    let depth = max_depth as usize;
+    let buffer = max_buffer_size as usize;
+    let body = data
+        .get(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1..)
+        .ok_or_else(|| invalid("Account data is too short"))?;
+    let body_size =
+        tree_body_size(depth, buffer).ok_or_else(|| invalid("Tree dimensions out of range"))?;
+    if body.len() < body_size {
+        return Err(invalid("Account data is too short"));
+    }
+
+    let sequence_number = u64::from_le_bytes(read(body, 0)?);
+    let active_index = u64::from_le_bytes(read(body, 8)?);
+    let active_index =
+        usize::try_from(active_index).map_err(|_| invalid("Active index out of range"))?;
+    if active_index >= buffer {
+        return Err(invalid("Active index out of range"));
+    }
+
+    let path = NODE_SIZE * depth;
+    let root = read(
+        body,
+        TREE_PREFIX_SIZE + active_index * (CHANGE_LOG_SIZE + path),
+    )?;
+    let rightmost = TREE_PREFIX_SIZE + buffer * (CHANGE_LOG_SIZE + path);
+    let rightmost_index = u32::from_le_bytes(read(body, rightmost + path + 32)?);
+
+    // The canopy stores every level of the tree below the root, i.e.
+    // 2^(c + 1) - 2 nodes for a canopy depth of c
+    let canopy_nodes = (body.len() - body_size) / NODE_SIZE;
+    let canopy_depth = (canopy_nodes + 2).trailing_zeros().saturating_sub(1);
+    if (2 << canopy_depth) - 2 != canopy_nodes {
+        return Err(invalid("Invalid canopy size"));
+    }
+
+    Ok(MerkleTreeAccount {
+        max_depth,
+        max_buffer_size,
+        authority,
+        creation_slot,
+        sequence_number,
+        root,
+        rightmost_index,
+        canopy_depth,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem::size_of;
+
+    use spl_account_compression::ConcurrentMerkleTree;
+
+    use super::*;
+    use crate::tree::tree_size;
+
+    #[test]
+    fn body_size_matches_upstream() {
+        assert_eq!(
+            tree_body_size(14, 64),
+            Some(size_of::<ConcurrentMerkleTree<14, 64>>())
+        );
+        assert_eq!(
+            tree_body_size(30, 2048),
+            Some(size_of::<ConcurrentMerkleTree<30, 2048>>())
+        );
+        assert_eq!(tree_body_size(usize::MAX, 1), None);
+        assert_eq!(tree_body_size(1, usize::MAX), None);
+    }
+
+    #[test]
+    fn empty_root_matches_upstream() {
+        let mut tree = ConcurrentMerkleTree::<14, 64>::new();
+        tree.initialize().unwrap();
+
+        assert_eq!(empty_root(14), tree.get_root());
+    }
+
+    #[test]
+    fn decodes_tree() {
+        let authority = Pubkey::new_unique();
+        let size = tree_size(3, 8, 2).unwrap();
+        let mut data = vec![0_u8; usize::try_from(size).unwrap()];
+
+        data[0] = ACCOUNT_TYPE_TREE;
+        data[2..6].copy_from_slice(&8_u32.to_le_bytes());
+        data[6..10].copy_from_slice(&3_u32.to_le_bytes());
+        data[10..42].copy_from_slice(authority.as_ref());
+        data[42..50].copy_from_slice(&1234_u64.to_le_bytes());
+
+        let body = CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1;
+        data[body..body + 8].copy_from_slice(&5_u64.to_le_bytes());
+        data[body + 8..body + 16].copy_from_slice(&1_u64.to_le_bytes());
+        let root = body + TREE_PREFIX_SIZE + CHANGE_LOG_SIZE + 32 * 3;
+        data[root..root + 32].copy_from_slice(&[7; 32]);
+
+        let tree = decode_merkle_tree(&data).unwrap();
+        assert_eq!(tree, MerkleTreeAccount {
+            max_depth: 3,
+            max_buffer_size: 8,
+            authority,
+            creation_slot: 1234,
+            sequence_number: 5,
+            root: [7; 32],
+            rightmost_index: 0,
+            canopy_depth: 2,
+        });
+        assert_eq!(tree.capacity(), 8);
+        assert!(!tree.is_empty());
+        assert!(MerkleTreeAccount {
+            root: empty_root(3),
+            ..tree
+        }
+        .is_empty());
+
+        assert!(decode_merkle_tree(&data[..100]).is_err());
+        data[0] = 0;
+        assert!(decode_merkle_tree(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_tree() {
+        let mut data = vec![0_u8; CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1];
+        data[0] = ACCOUNT_TYPE_TREE;
+        data[2..6].copy_from_slice(&8_u32.to_le_bytes());
+        data[6..10].copy_from_slice(&3_u32.to_le_bytes());
+
+        assert!(decode_merkle_tree(&[]).is_err());
+        assert!(decode_merkle_tree(&data[..50]).is_err());
+        assert!(decode_merkle_tree(&data[..CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1 - 1]).is_err());
+        assert!(decode_merkle_tree(&data).is_err());
+
+        data[2..6].copy_from_slice(&u32::MAX.to_le_bytes());
+        data[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+        data.resize(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1 + 64, 0);
+        assert!(decode_merkle_tree(&data).is_err());
+    }
+}
@@ -0,0 +1,651 @@
+//! An in-process bank for running the Bubblegum, account compression, and
+//! noop programs without a cluster
+//!
+//! Rather than loading program binaries into a full runtime, the programs are
+//! compiled natively and invoked through their entrypoints.  Cross-program
+//! invocations, PDA signing, and the rent and clock sysvars are provided by
+//! installing custom syscall stubs, and the few system program instructions
+//! the programs rely on are implemented here.  [`TestBank::process`] does not
+//! check signatures; an instruction account marked as a signer simply has to
+//! be one of the signers passed to it.  [`BankChain`] wraps a bank in a
+//! [`ChainClient`], so that anything written against the trait, including the
+//! `arborist` commands, can be exercised end-to-end.
+//!
+//! `ConcurrentMerkleTree` is not `#[repr(C)]`, so its native layout differs
+//! from the on-chain one.  The bank stores tree accounts in the on-chain
+//! layout, converting them to and from the native layout around each
+//! instruction, so that decoders see the same bytes they would on a cluster.
+//!
+//! This module is only available with the `test-utils` feature.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    mem::offset_of,
+    sync::{Mutex, Once, PoisonError},
+};
+
+use async_trait::async_trait;
+use solana_client::client_error::Result as ClientResult;
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    bpf_loader,
+    clock::{Clock, Slot},
+    commitment_config::CommitmentConfig,
+    entrypoint::{ProgramResult, SUCCESS},
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    message::v0::LoadedAddresses,
+    program_error::ProgramError,
+    program_stubs::{self, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    system_instruction::SystemInstruction,
+    system_program,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+use spl_account_compression::{state::CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1, ConcurrentMerkleTree};
+
+use crate::chain::{
+    ChainClient, ConfirmedTransaction, SignatureInfo, SimulationConfig, SimulationResult,
+};
+
+/// Slot reported by the clock sysvar
+pub const SLOT: u64 = 42;
+
+thread_local! {
+    /// Programs currently executing, used to derive PDA signers for CPIs
+    static CALL_STACK: RefCell<Vec<Pubkey>> = const { RefCell::new(vec![]) };
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let caller = CALL_STACK.with(|s| *s.borrow().last().unwrap());
+        let pdas = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+
+        let infos = instruction
+            .accounts
+            .iter()
+            .map(|meta| {
+                let info = account_infos
+                    .iter()
+                    .find(|a| *a.key == meta.pubkey)
+                    .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+                if meta.is_signer && !info.is_signer && !pdas.contains(&meta.pubkey) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+
+                let mut info = info.clone();
+                info.is_signer = meta.is_signer;
+                info.is_writable = meta.is_writable;
+
+                Ok(info)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        dispatch(&instruction.program_id, &infos, &instruction.data)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock {
+            slot: SLOT,
+            ..Clock::default()
+        };
+        unsafe { std::ptr::write_unaligned(var_addr.cast::<Clock>(), clock) };
+
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { std::ptr::write_unaligned(var_addr.cast::<Rent>(), Rent::default()) };
+
+        SUCCESS
+    }
+}
+
+fn dispatch(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    CALL_STACK.with(|s| s.borrow_mut().push(*program_id));
+
+    let res = if *program_id == mpl_bubblegum::ID {
+        mpl_bubblegum::entry(program_id, accounts, data)
+    } else if *program_id == spl_account_compression::ID {
+        spl_account_compression::entry(program_id, accounts, data)
+    } else if *program_id == spl_noop::ID {
+        spl_noop::noop(program_id, accounts, data)
+    } else if *program_id == system_program::ID {
+        system(accounts, data)
+    } else {
+        Err(ProgramError::IncorrectProgramId)
+    };
+
+    CALL_STACK.with(|s| s.borrow_mut().pop());
+    res
+}
+
+/// The subset of the system program used by the programs under test
+fn system(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let transfer = |from: &AccountInfo, to: &AccountInfo, lamports: u64| {
+        if !from.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let from_lamports = from.lamports();
+        **from.lamports.borrow_mut() = from_lamports
+            .checked_sub(lamports)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **to.lamports.borrow_mut() += lamports;
+
+        Ok(())
+    };
+
+    match bincode::deserialize(data).map_err(|_| ProgramError::InvalidInstructionData)? {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => {
+            let [from, to, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            if !to.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if to.lamports() != 0 || !to.data_is_empty() || *to.owner != system_program::ID {
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+
+            transfer(from, to, lamports)?;
+            let space = usize::try_from(space).map_err(|_| ProgramError::InvalidArgument)?;
+            *to.data.borrow_mut() = vec![0; space].leak();
+            to.assign(&owner);
+
+            Ok(())
+        },
+        SystemInstruction::Transfer { lamports } => {
+            let [from, to, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            transfer(from, to, lamports)
+        },
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+/// Offset and length of each field of a concurrent Merkle tree in its native
+/// layout, in declaration order
+fn native_fields(depth: u32, buffer_size: u32) -> [(usize, usize); 5] {
+    macro_rules! fields {
+        ($depth:literal, $buf:literal) => {{
+            type Tree = ConcurrentMerkleTree<$depth, $buf>;
+
+            [
+                (offset_of!(Tree, sequence_number), 8),
+                (offset_of!(Tree, active_index), 8),
+                (offset_of!(Tree, buffer_size), 8),
+                (offset_of!(Tree, change_logs), $buf * (32 * $depth + 40)),
+                (offset_of!(Tree, rightmost_proof), 32 * $depth + 40),
+            ]
+        }};
+    }
+
+    match (depth, buffer_size) {
+        (3, 8) => fields!(3, 8),
+        (5, 8) => fields!(5, 8),
+        (14, 64) => fields!(14, 64),
+        _ => panic!("Unsupported tree size {depth}/{buffer_size} in test bank"),
+    }
+}
+
+/// Convert a concurrent Merkle tree account between its on-chain and native
+/// layouts
+fn relayout(data: &mut [u8], to_native: bool) {
+    const HEADER: usize = CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1;
+
+    // Only initialized trees have a meaningful layout
+    if data.len() < HEADER || data[0] != 1 {
+        return;
+    }
+
+    let read = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+    let native = native_fields(read(6), read(2));
+    let body = &mut data[HEADER..];
+    let src = body.to_vec();
+    let mut chain_offset = 0;
+
+    for (native_offset, len) in native {
+        let (from, to) = if to_native {
+            (chain_offset, native_offset)
+        } else {
+            (native_offset, chain_offset)
+        };
+
+        body[to..to + len].copy_from_slice(&src[from..from + len]);
+        chain_offset += len;
+    }
+}
+
+/// An in-memory set of accounts that instructions are executed against
+#[derive(Debug)]
+pub struct TestBank {
+    accounts: HashMap<Pubkey, Account>,
+}
+
+impl Default for TestBank {
+    fn default() -> Self { Self::new() }
+}
+
+impl TestBank {
+    /// Construct a bank containing the Bubblegum, account compression, noop,
+    /// and system programs
+    #[must_use]
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            program_stubs::set_syscall_stubs(Box::new(Stubs));
+        });
+
+        let accounts = [
+            mpl_bubblegum::ID,
+            spl_account_compression::ID,
+            spl_noop::ID,
+            system_program::ID,
+        ]
+        .into_iter()
+        .map(|id| {
+            (id, Account {
+                lamports: 1,
+                data: vec![],
+                owner: bpf_loader::ID,
+                executable: true,
+                rent_epoch: 0,
+            })
+        })
+        .collect();
+
+        Self { accounts }
+    }
+
+    /// Add lamports to a system account, creating it if necessary
+    pub fn fund(&mut self, address: &Pubkey, lamports: u64) {
+        self.accounts
+            .entry(*address)
+            .or_insert_with(|| Account::new(0, 0, &system_program::ID))
+            .lamports += lamports;
+    }
+
+    /// Get an account, if it exists
+    #[must_use]
+    pub fn account(&self, address: &Pubkey) -> Option<&Account> { self.accounts.get(address) }
+
+    /// Get the lamport balance of an account
+    #[must_use]
+    pub fn balance(&self, address: &Pubkey) -> u64 {
+        self.account(address).map_or(0, |a| a.lamports)
+    }
+
+    /// Execute a set of instructions atomically, as if they were a single
+    /// transaction signed by the given signers
+    ///
+    /// # Errors
+    /// This function fails if an instruction fails, in which case no accounts
+    /// are modified.
+    pub fn process(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> ProgramResult {
+        let signers = signers.iter().map(|s| s.pubkey()).collect::<Vec<_>>();
+        let mut accounts = self.accounts.clone();
+
+        for ix in instructions {
+            execute(&mut accounts, ix, &signers)?;
+        }
+
+        self.accounts = accounts;
+        Ok(())
+    }
+
+    /// Verify and execute a signed transaction
+    ///
+    /// # Errors
+    /// This function fails if the transaction is malformed, a signature is
+    /// invalid, or an instruction fails.
+    pub fn process_transaction(
+        &mut self,
+        txn: &VersionedTransaction,
+    ) -> Result<(), TransactionError> {
+        txn.sanitize(true)?;
+        if !txn.verify_with_results().into_iter().all(|v| v) {
+            return Err(TransactionError::SignatureFailure);
+        }
+
+        let message = &txn.message;
+        let keys = message.static_account_keys();
+        let signers = &keys[..usize::from(message.header().num_required_signatures)];
+        let mut accounts = self.accounts.clone();
+
+        for (i, ix) in message.instructions().iter().enumerate() {
+            let ix = Instruction {
+                program_id: keys[usize::from(ix.program_id_index)],
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|&a| {
+                        let a = usize::from(a);
+
+                        AccountMeta {
+                            pubkey: keys[a],
+                            is_signer: message.is_signer(a),
+                            is_writable: message.is_maybe_writable(a),
+                        }
+                    })
+                    .collect(),
+                data: ix.data.clone(),
+            };
+
+            execute(&mut accounts, &ix, signers).map_err(|e| {
+                TransactionError::InstructionError(
+                    i.try_into().unwrap_or_else(|_| unreachable!()),
+                    InstructionError::from(u64::from(e)),
+                )
+            })?;
+        }
+
+        self.accounts = accounts;
+        Ok(())
+    }
+}
+
+/// Execute a single top-level instruction, writing back any modified accounts
+fn execute(
+    accounts: &mut HashMap<Pubkey, Account>,
+    ix: &Instruction,
+    signers: &[Pubkey],
+) -> ProgramResult {
+    // Every unique account gets one set of buffers shared by all of its
+    // AccountInfos, so that CPIs observe each other's changes
+    let mut unique: HashMap<Pubkey, AccountInfo<'static>> = HashMap::new();
+
+    let infos = ix
+        .accounts
+        .iter()
+        .map(
+            |&AccountMeta {
+                 pubkey,
+                 is_signer,
+                 is_writable,
+             }| {
+                if is_signer && !signers.contains(&pubkey) {
+                    return Err(ProgramError::MissingRequiredSignature);
+                }
+
+                let base = unique.entry(pubkey).or_insert_with(|| {
+                    let Account {
+                        lamports,
+                        mut data,
+                        owner,
+                        executable,
+                        rent_epoch,
+                    } = accounts
+                        .get(&pubkey)
+                        .cloned()
+                        .unwrap_or_else(|| Account::new(0, 0, &system_program::ID));
+
+                    if owner == spl_account_compression::ID {
+                        relayout(&mut data, true);
+                    }
+
+                    AccountInfo::new(
+                        Box::leak(Box::new(pubkey)),
+                        false,
+                        false,
+                        Box::leak(Box::new(lamports)),
+                        data.leak(),
+                        Box::leak(Box::new(owner)),
+                        executable,
+                        rent_epoch,
+                    )
+                });
+
+                let mut info = base.clone();
+                info.is_signer = is_signer;
+                info.is_writable = is_writable;
+
+                Ok(info)
+            },
+        )
+        .collect::<Result<Vec<_>, _>>()?;
+
+    dispatch(&ix.program_id, &infos, &ix.data)?;
+
+    for (pubkey, info) in unique {
+        let writable = ix
+            .accounts
+            .iter()
+            .any(|m| m.pubkey == pubkey && m.is_writable);
+        let lamports = info.lamports();
+
+        if !writable {
+            continue;
+        }
+
+        if lamports == 0 {
+            accounts.remove(&pubkey);
+        } else {
+            let mut data = info.data.borrow().to_vec();
+            if *info.owner == spl_account_compression::ID {
+                relayout(&mut data, false);
+            }
+
+            accounts.insert(pubkey, Account {
+                lamports,
+                data,
+                owner: *info.owner,
+                executable: info.executable,
+                rent_epoch: info.rent_epoch,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct State {
+    bank: TestBank,
+    transactions: Vec<ConfirmedTransaction>,
+}
+
+/// A [`ChainClient`] that executes transactions against a [`TestBank`]
+#[derive(Debug)]
+pub struct BankChain(Mutex<State>);
+
+impl BankChain {
+    /// Wrap a bank, executing every transaction sent against it
+    #[must_use]
+    pub fn new(bank: TestBank) -> Self {
+        Self(Mutex::new(State {
+            bank,
+            transactions: vec![],
+        }))
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Run a function against the underlying bank
+    pub fn with_bank<T>(&self, f: impl FnOnce(&mut TestBank) -> T) -> T {
+        f(&mut self.state().bank)
+    }
+
+    fn find(&self, signature: &Signature) -> Option<ConfirmedTransaction> {
+        self.state()
+            .transactions
+            .iter()
+            .find(|t| t.transaction.signatures[0] == *signature)
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl ChainClient for BankChain {
+    fn commitment(&self) -> CommitmentConfig { CommitmentConfig::confirmed() }
+
+    async fn latest_blockhash(&self) -> ClientResult<(Hash, u64)> {
+        Ok((Hash::default(), u64::MAX))
+    }
+
+    async fn is_blockhash_valid(&self, _: &Hash) -> ClientResult<bool> { Ok(true) }
+
+    async fn block_height(&self) -> ClientResult<u64> { Ok(SLOT) }
+
+    async fn slot(&self, _: CommitmentConfig) -> ClientResult<Slot> { Ok(SLOT) }
+
+    async fn genesis_hash(&self) -> ClientResult<Hash> { Ok(Hash::default()) }
+
+    async fn minimum_balance_for_rent_exemption(&self, size: usize) -> ClientResult<u64> {
+        Ok(Rent::default().minimum_balance(size))
+    }
+
+    async fn balance(&self, address: &Pubkey) -> ClientResult<u64> {
+        Ok(self.state().bank.balance(address))
+    }
+
+    async fn account(&self, address: &Pubkey) -> ClientResult<Option<Account>> {
+        Ok(self.state().bank.account(address).cloned())
+    }
+
+    async fn multiple_accounts(&self, addresses: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        let state = self.state();
+
+        Ok(addresses
+            .iter()
+            .map(|a| state.bank.account(a).cloned())
+            .collect())
+    }
+
+    async fn recent_prioritization_fees(&self, _: &[Pubkey]) -> ClientResult<Vec<u64>> {
+        Ok(vec![])
+    }
+
+    async fn send(
+        &self,
+        txn: &VersionedTransaction,
+        skip_preflight: bool,
+    ) -> ClientResult<Signature> {
+        let mut state = self.state();
+        let mut bank = TestBank {
+            accounts: state.bank.accounts.clone(),
+        };
+        let res = bank.process_transaction(txn);
+
+        // A failing transaction is only processed if preflight is skipped
+        match res {
+            Ok(()) => state.bank = bank,
+            Err(e) if !skip_preflight => return Err(e.into()),
+            Err(_) => (),
+        }
+
+        state.transactions.push(ConfirmedTransaction {
+            slot: SLOT,
+            block_time: None,
+            transaction: txn.clone(),
+            err: res.err(),
+            logs: vec![],
+            inner_instructions: vec![],
+            loaded_addresses: LoadedAddresses::default(),
+        });
+
+        Ok(txn.signatures[0])
+    }
+
+    async fn simulate(
+        &self,
+        txn: &VersionedTransaction,
+        config: SimulationConfig,
+    ) -> ClientResult<SimulationResult> {
+        let mut bank = TestBank {
+            accounts: self.state().bank.accounts.clone(),
+        };
+        let err = bank.process_transaction(txn).err();
+
+        Ok(SimulationResult {
+            err,
+            accounts: config
+                .accounts
+                .iter()
+                .map(|a| bank.account(a).cloned())
+                .collect(),
+            ..SimulationResult::default()
+        })
+    }
+
+    async fn signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> ClientResult<Vec<Option<TransactionStatus>>> {
+        Ok(signatures
+            .iter()
+            .map(|s| {
+                self.find(s).map(|t| TransactionStatus {
+                    slot: t.slot,
+                    confirmations: None,
+                    status: t.err.clone().map_or(Ok(()), Err),
+                    err: t.err,
+                    confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+                })
+            })
+            .collect())
+    }
+
+    async fn signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        limit: usize,
+    ) -> ClientResult<Vec<SignatureInfo>> {
+        Ok(self
+            .state()
+            .transactions
+            .iter()
+            .rev()
+            .filter(|t| {
+                t.transaction
+                    .message
+                    .static_account_keys()
+                    .contains(address)
+            })
+            .skip_while(|t| before.is_some_and(|b| b != t.transaction.signatures[0]))
+            .skip(usize::from(before.is_some()))
+            .take(limit)
+            .map(|t| SignatureInfo {
+                signature: t.transaction.signatures[0],
+                slot: t.slot,
+                err: t.err.clone(),
+                block_time: t.block_time,
+            })
+            .collect())
+    }
+
+    async fn transaction(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<Option<ConfirmedTransaction>> {
+        Ok(self.find(signature))
+    }
+}
@@ -0,0 +1,489 @@
+use std::{collections::VecDeque, fs::File, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use arborist::{
+    accounts::decode_tree_config,
+    chain::ChainClient,
+    client::{SolanaClient, TransactionOutcome},
+    hash::asset_id,
+    instructions,
+    tree::tree_config_address,
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    bubblegum::{self, check_creator_shares, metadata_args},
+    cli::{CreateTree, DelegateTree, MintBatch, MultisigArgs, TreeArg},
+    output::{ErrorCode, Output, ResultExt},
+    project::Project,
+    signer::CommandSigners,
+};
+
+/// A row of the input CSV for `mint-batch`
+#[derive(Debug, Deserialize)]
+struct BatchItem {
+    name: String,
+    uri: String,
+    #[serde(default)]
+    symbol: String,
+    #[serde(default)]
+    recipient: Option<String>,
+    #[serde(default)]
+    delegate: Option<String>,
+    #[serde(default)]
+    seller_fee_basis_points: u16,
+}
+
+fn parse_address(row: usize, column: &str, s: Option<String>) -> Result<Option<Pubkey>> {
+    s.filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse()
+                .with_context(|| format!("Invalid {column} {s:?} in row {row}"))
+                .code(ErrorCode::InvalidArgument)
+        })
+        .transpose()
+}
+
+fn read_items(path: &Path) -> Result<Vec<BatchItem>> {
+    csv::Reader::from_path(path)
+        .and_then(|mut r| r.deserialize().collect())
+        .with_context(|| format!("Error reading items from {}", path.display()))
+        .code(ErrorCode::InvalidArgument)
+}
+
+/// Shape of the trees to create once the listed trees are full
+struct TreeTemplate {
+    depth: u8,
+    buffer_size: u16,
+    canopy_depth: u8,
+    delegate: Option<Pubkey>,
+    name: Option<String>,
+}
+
+/// Mint capacity of the tree currently being minted into
+#[derive(Debug, Clone, Copy)]
+struct ActiveTree {
+    merkle_tree: Pubkey,
+    num_minted: u64,
+    capacity: u64,
+}
+
+/// A tree created when the pool ran out of capacity
+#[derive(Debug, Serialize)]
+pub struct CreatedTree {
+    merkle_tree: String,
+    name: Option<String>,
+    tree_delegate: Option<String>,
+}
+
+/// The trees available to a batch mint, in the order they are used
+struct TreePool {
+    trees: VecDeque<Pubkey>,
+    template: Option<TreeTemplate>,
+    active: Option<ActiveTree>,
+    created: Vec<CreatedTree>,
+}
+
+impl TreePool {
+    /// Get a tree with room for another mint, moving on to the next listed
+    /// tree or creating a new one from the template if the active tree is
+    /// full
+    async fn next<C: ChainClient>(
+        &mut self,
+        client: &SolanaClient<C>,
+        signers: &CommandSigners,
+        project: &mut Project,
+    ) -> Result<&mut ActiveTree> {
+        loop {
+            if self.active.is_some_and(|t| t.num_minted < t.capacity) {
+                return Ok(self.active.as_mut().unwrap_or_else(|| unreachable!()));
+            }
+
+            self.active = Some(if let Some(merkle_tree) = self.trees.pop_front() {
+                Self::read(client, merkle_tree).await?
+            } else if let Some(template) = &self.template {
+                let (tree, created) = Self::create(client, signers, project, template).await?;
+                self.created.push(created);
+                tree
+            } else {
+                return Err(anyhow!("All trees in the pool are full"))
+                    .code(ErrorCode::InvalidArgument);
+            });
+        }
+    }
+
+    async fn read<C: ChainClient>(
+        client: &SolanaClient<C>,
+        merkle_tree: Pubkey,
+    ) -> Result<ActiveTree> {
+        let address = tree_config_address(&merkle_tree);
+        let account = client
+            .account(&address)
+            .await
+            .with_context(|| format!("Error getting tree config for {merkle_tree}"))
+            .code(ErrorCode::Rpc)?
+            .with_context(|| format!("Tree {merkle_tree} has no Bubblegum tree config"))
+            .code(ErrorCode::InvalidArgument)?;
+        let config = decode_tree_config(&account.data)
+            .with_context(|| format!("Error decoding tree config for {merkle_tree}"))
+            .code(ErrorCode::InvalidArgument)?;
+
+        Ok(ActiveTree {
+            merkle_tree,
+            num_minted: config.num_minted,
+            capacity: config.total_mint_capacity,
+        })
+    }
+
+    async fn create<C: ChainClient>(
+        client: &SolanaClient<C>,
+        signers: &CommandSigners,
+        project: &mut Project,
+        template: &TreeTemplate,
+    ) -> Result<(ActiveTree, CreatedTree)> {
+        let &TreeTemplate {
+            depth,
+            buffer_size,
+            canopy_depth,
+            delegate,
+            ref name,
+        } = template;
+
+        let name = name.as_ref().map(|prefix| {
+            let mut i = 1;
+            while project.has_tree(&format!("{prefix}-{i}")) {
+                i += 1;
+            }
+
+            format!("{prefix}-{i}")
+        });
+        let res = bubblegum::create_tree(client, signers, project, CreateTree {
+            depth,
+            buffer_size,
+            canopy_depth,
+            name: name.clone(),
+        })
+        .await
+        .context("Error creating a new tree for the pool")?;
+        let merkle_tree: Pubkey = res.merkle_tree().parse().unwrap_or_else(|_| unreachable!());
+
+        // Later mints depend on the tree existing
+        if !matches!(res.transaction(), TransactionOutcome::Confirmed { .. }) {
+            return Err(anyhow!(
+                "Trees can only be created for the pool when sending transactions"
+            ))
+            .code(ErrorCode::InvalidArgument);
+        }
+
+        if let Some(new_tree_delegate) = delegate {
+            bubblegum::delegate_tree(client, signers, project, DelegateTree {
+                merkle_tree: TreeArg::Address(merkle_tree),
+                tree_authority: None,
+                new_tree_delegate,
+                multisig: MultisigArgs::default(),
+            })
+            .await
+            .with_context(|| format!("Error delegating new tree {merkle_tree}"))?;
+        }
+
+        Ok((
+            ActiveTree {
+                merkle_tree,
+                num_minted: 0,
+                capacity: 1 << depth,
+            },
+            CreatedTree {
+                merkle_tree: merkle_tree.to_string(),
+                name,
+                tree_delegate: delegate.map(|d| d.to_string()),
+            },
+        ))
+    }
+}
+
+/// Where a batch item was minted
+#[derive(Debug, Serialize)]
+pub struct MintedItem {
+    row: usize,
+    name: String,
+    merkle_tree: String,
+    leaf_index: u64,
+    asset_id: String,
+    status: &'static str,
+    signature: Option<String>,
+}
+
+/// Result of the `mint-batch` command
+#[derive(Debug, Serialize)]
+pub struct MintBatchResult {
+    created_trees: Vec<CreatedTree>,
+    items: Vec<MintedItem>,
+}
+
+impl Output for MintBatchResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        for CreatedTree {
+            merkle_tree,
+            name,
+            tree_delegate,
+        } in &self.created_trees
+        {
+            write!(out, "Created tree {merkle_tree}")?;
+            if let Some(name) = name {
+                write!(out, " ({name})")?;
+            }
+            if let Some(delegate) = tree_delegate {
+                write!(out, ", delegated to {delegate}")?;
+            }
+            writeln!(out)?;
+        }
+
+        for MintedItem {
+            row,
+            name,
+            merkle_tree,
+            leaf_index,
+            asset_id,
+            status,
+            signature,
+        } in &self.items
+        {
+            write!(
+                out,
+                "Row {row} ({name:?}): tree {merkle_tree}, leaf {leaf_index}, asset {asset_id}"
+            )?;
+            match signature {
+                Some(s) => writeln!(out, ", signature {s}")?,
+                None => writeln!(out, " ({status})")?,
+            }
+        }
+
+        writeln!(out, "Minted {} asset(s)", self.items.len())
+    }
+
+    fn write_csv(&self, out: &mut dyn std::io::Write) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(out);
+
+        for item in &self.items {
+            csv.serialize(item)
+                .context("Error serializing minted items to CSV")?;
+        }
+
+        csv.flush().context("Error writing CSV output")
+    }
+}
+
+async fn mint_item<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    tree: &mut ActiveTree,
+    row: usize,
+    item: BatchItem,
+    creators: &[(Pubkey, u8)],
+    immutable: bool,
+) -> Result<MintedItem> {
+    let BatchItem {
+        name,
+        uri,
+        symbol,
+        recipient,
+        delegate,
+        seller_fee_basis_points,
+    } = item;
+    let payer = signers.payer();
+    let leaf_owner =
+        parse_address(row, "recipient", recipient)?.unwrap_or_else(|| signers.leaf_owner());
+    let leaf_delegate = parse_address(row, "delegate", delegate)?.unwrap_or(leaf_owner);
+    let metadata = metadata_args(
+        name.clone(),
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        creators,
+        immutable,
+        &payer,
+    );
+
+    let transaction = client
+        .send_transaction(
+            &[instructions::mint_v1(
+                &tree.merkle_tree,
+                &leaf_owner,
+                &leaf_delegate,
+                &payer,
+                &signers.authority(),
+                metadata,
+            )],
+            Some(&payer),
+            &signers.with(&[]),
+        )
+        .await
+        .with_context(|| format!("Error minting row {row}"))?;
+
+    // Assets are numbered by the mint count of their tree
+    let nonce = tree.num_minted;
+    tree.num_minted += 1;
+
+    let (status, signature) = match transaction {
+        TransactionOutcome::Confirmed { signature } => ("confirmed", Some(signature)),
+        TransactionOutcome::Simulated(_) => ("simulated", None),
+        TransactionOutcome::SignOnly(_) => ("sign-only", None),
+    };
+
+    Ok(MintedItem {
+        row,
+        name,
+        merkle_tree: tree.merkle_tree.to_string(),
+        leaf_index: nonce,
+        asset_id: asset_id(&tree.merkle_tree, nonce).to_string(),
+        status,
+        signature,
+    })
+}
+
+pub async fn mint_batch<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &mut Project,
+    args: MintBatch,
+) -> Result<MintBatchResult> {
+    let MintBatch {
+        items,
+        trees,
+        new_depth,
+        new_buffer_size,
+        new_canopy_depth,
+        new_tree_delegate,
+        new_tree_name,
+        creators,
+        immutable,
+        results,
+    } = args;
+
+    check_creator_shares(&creators)?;
+    let items = read_items(&items)?;
+    let mut pool = TreePool {
+        trees: project.trees(&trees)?.into(),
+        template: new_depth
+            .zip(new_buffer_size)
+            .map(|(depth, buffer_size)| TreeTemplate {
+                depth,
+                buffer_size,
+                canopy_depth: new_canopy_depth,
+                delegate: new_tree_delegate,
+                name: new_tree_name,
+            }),
+        active: None,
+        created: vec![],
+    };
+    let mut results = results
+        .map(|path| {
+            File::create(&path)
+                .map(csv::Writer::from_writer)
+                .with_context(|| format!("Error creating results file {}", path.display()))
+                .code(ErrorCode::Io)
+        })
+        .transpose()?;
+
+    let mut minted = vec![];
+    for (i, item) in items.into_iter().enumerate() {
+        let tree = pool.next(client, signers, project).await?;
+        let item = mint_item(client, signers, tree, i + 1, item, &creators, immutable).await?;
+
+        // Record each item as it lands, so an interrupted batch can be resumed
+        if let Some(results) = &mut results {
+            results
+                .serialize(&item)
+                .and_then(|()| results.flush().map_err(Into::into))
+                .context("Error writing results file")
+                .code(ErrorCode::Io)?;
+        }
+
+        minted.push(item);
+    }
+
+    Ok(MintBatchResult {
+        created_trees: pool.created,
+        items: minted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use arborist::{accounts::TreeConfig, chain::MockChain};
+    use solana_sdk::{signature::Keypair, signer::Signer};
+
+    use super::*;
+    use crate::test_support::{block_on, command_signers, config_account, TempDir};
+
+    #[test]
+    fn rolls_over_to_new_trees() {
+        let keypair = Keypair::new();
+        let authority = keypair.pubkey();
+        let dir = TempDir::new("batch");
+        let items = dir.path().join("items.csv");
+        std::fs::write(
+            &items,
+            "name,uri,recipient\nOak,https://example.com/oak.json,\nElm,https://example.com/elm.json,\nAsh,https://example.com/ash.json,\n",
+        )
+        .unwrap();
+
+        let signers = command_signers(&keypair);
+        let mut project = dir.project();
+
+        // The first tree has room for one more asset, and the second is full
+        let config = |num_minted| TreeConfig {
+            tree_creator: authority,
+            tree_delegate: authority,
+            total_mint_capacity: 8,
+            num_minted,
+            is_public: false,
+        };
+        let (partial, full) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let chain = MockChain::new();
+        chain.set_account(tree_config_address(&partial), config_account(1, &config(7)));
+        chain.set_account(tree_config_address(&full), config_account(1, &config(8)));
+        let client = SolanaClient::new(chain, false);
+
+        let delegate = Pubkey::new_unique();
+        let res = block_on(mint_batch(&client, &signers, &mut project, MintBatch {
+            items,
+            trees: vec![TreeArg::Address(partial), TreeArg::Address(full)],
+            new_depth: Some(3),
+            new_buffer_size: Some(8),
+            new_canopy_depth: 0,
+            new_tree_delegate: Some(delegate),
+            new_tree_name: Some("drop".into()),
+            creators: vec![],
+            immutable: false,
+            results: Some(dir.path().join("results.csv")),
+        }))
+        .unwrap();
+        let results = std::fs::read_to_string(dir.path().join("results.csv"));
+
+        assert_eq!(res.created_trees.len(), 1);
+        let created = &res.created_trees[0];
+        assert_eq!(created.name.as_deref(), Some("drop-1"));
+        assert_eq!(created.tree_delegate, Some(delegate.to_string()));
+
+        let placed = res
+            .items
+            .iter()
+            .map(|i| (i.name.as_str(), i.merkle_tree.clone(), i.leaf_index))
+            .collect::<Vec<_>>();
+        assert_eq!(placed, vec![
+            ("Oak", partial.to_string(), 7),
+            ("Elm", created.merkle_tree.clone(), 0),
+            ("Ash", created.merkle_tree.clone(), 1),
+        ]);
+        assert_eq!(
+            res.items[1].asset_id,
+            asset_id(&created.merkle_tree.parse().unwrap(), 0).to_string()
+        );
+
+        // Mint, create, delegate, and two more mints
+        assert_eq!(client.sent().len(), 5);
+        assert_eq!(results.unwrap().lines().count(), 4);
+    }
+}
@@ -0,0 +1,462 @@
+use anyhow::{anyhow, Context, Result};
+use arborist::{
+    chain::ChainClient,
+    client::{SolanaClient, TransactionOutcome},
+    instructions,
+    tree::{tree_config_address, tree_size},
+    Creator, MetadataArgs, TokenProgramVersion, TokenStandard,
+};
+use serde::Serialize;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{
+    cli::{CreateTree, DelegateTree, Mint, SetDecompressible},
+    multisig::{self, AdminOutcome, Multisig},
+    output::{ErrorCode, Output, ResultExt},
+    project::{NamedTree, Project},
+    signer::CommandSigners,
+};
+
+/// Result of the `create-tree` command
+#[derive(Debug, Serialize)]
+pub struct CreateTreeResult {
+    #[serde(flatten)]
+    transaction: TransactionOutcome,
+    merkle_tree: String,
+    tree_config: String,
+}
+
+impl CreateTreeResult {
+    /// Address of the new tree
+    #[inline]
+    #[must_use]
+    pub fn merkle_tree(&self) -> &str { &self.merkle_tree }
+
+    /// Outcome of the transaction creating the tree
+    #[inline]
+    #[must_use]
+    pub fn transaction(&self) -> &TransactionOutcome { &self.transaction }
+}
+
+impl Output for CreateTreeResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        self.transaction.write_text(out)?;
+        writeln!(out, "Merkle tree: {}", self.merkle_tree)?;
+        writeln!(out, "Tree config: {}", self.tree_config)
+    }
+}
+
+pub async fn create_tree<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &mut Project,
+    args: CreateTree,
+) -> Result<CreateTreeResult> {
+    let CreateTree {
+        depth,
+        buffer_size,
+        canopy_depth,
+        name,
+    } = args;
+
+    let payer = signers.payer();
+    let tree_creator = signers.authority();
+    let tree = Keypair::new();
+    let tree_pubkey = tree.try_pubkey().unwrap_or_else(|_| unreachable!());
+
+    let tree_authority = tree_config_address(&tree_pubkey);
+
+    let size = tree_size(depth, buffer_size, canopy_depth).code(ErrorCode::InvalidArgument)?;
+    let rent = client
+        .rent_exemption(size.try_into().unwrap_or_else(|_| unreachable!()))
+        .await
+        .context("Error getting rent exemption balance for new tree")?;
+
+    let transaction = client
+        .send_transaction(
+            &[
+                instructions::create_tree_account(&payer, &tree_pubkey, rent, size),
+                instructions::create_tree(
+                    &tree_pubkey,
+                    &payer,
+                    &tree_creator,
+                    depth.into(),
+                    buffer_size.into(),
+                    None, // TODO: why is this undocumented
+                ),
+            ],
+            Some(&payer),
+            &signers.with(&[&tree]),
+        )
+        .await?;
+
+    // Only register trees that actually exist
+    if let (Some(name), TransactionOutcome::Confirmed { .. }) = (name, &transaction) {
+        project
+            .register_tree(name, NamedTree {
+                address: tree_pubkey.to_string(),
+                depth: Some(depth),
+                buffer_size: Some(buffer_size),
+                canopy_depth: Some(canopy_depth),
+            })
+            .context("Error registering new tree")?;
+    }
+
+    Ok(CreateTreeResult {
+        transaction,
+        merkle_tree: tree_pubkey.to_string(),
+        tree_config: tree_authority.to_string(),
+    })
+}
+
+pub async fn delegate_tree<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: DelegateTree,
+) -> Result<AdminOutcome> {
+    let DelegateTree {
+        merkle_tree,
+        tree_authority,
+        new_tree_delegate,
+        multisig,
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+    let multisig = Multisig::from_args(multisig);
+    let tree_creator = multisig
+        .as_ref()
+        .map_or_else(|| signers.authority(), Multisig::vault);
+
+    let mut ix = instructions::set_tree_delegate(&merkle_tree, &tree_creator, &new_tree_delegate);
+    if let Some(tree_authority) = tree_authority {
+        ix.accounts[0].pubkey = tree_authority;
+    }
+
+    multisig::send_or_propose(client, signers, multisig.as_ref(), &[ix]).await
+}
+
+pub async fn set_decompressible<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: SetDecompressible,
+) -> Result<AdminOutcome> {
+    let SetDecompressible {
+        merkle_tree,
+        state,
+        multisig,
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+    let multisig = Multisig::from_args(multisig);
+    let tree_creator = multisig
+        .as_ref()
+        .map_or_else(|| signers.authority(), Multisig::vault);
+
+    multisig::send_or_propose(client, signers, multisig.as_ref(), &[
+        instructions::set_decompressible_state(&merkle_tree, &tree_creator, state.into()),
+    ])
+    .await
+}
+
+/// Check that creator royalty shares add up to 100, if any creators are
+/// given
+pub fn check_creator_shares(creators: &[(Pubkey, u8)]) -> Result<()> {
+    if !creators.is_empty() && creators.iter().map(|&(_, s)| u16::from(s)).sum::<u16>() != 100 {
+        return Err(anyhow!("Creator shares must add up to 100")).code(ErrorCode::InvalidArgument);
+    }
+
+    Ok(())
+}
+
+/// Build the metadata for a new asset.  Creators are verified if they are the
+/// payer.
+pub fn metadata_args(
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    creators: &[(Pubkey, u8)],
+    immutable: bool,
+    payer: &Pubkey,
+) -> MetadataArgs {
+    MetadataArgs {
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        primary_sale_happened: false,
+        is_mutable: !immutable,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: None,
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators: creators
+            .iter()
+            .map(|&(address, share)| Creator {
+                address,
+                verified: address == *payer,
+                share,
+            })
+            .collect(),
+    }
+}
+
+pub async fn mint<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: Mint,
+) -> Result<AdminOutcome> {
+    let Mint {
+        merkle_tree,
+        recipient,
+        delegate,
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        creators,
+        immutable,
+        multisig,
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+    let multisig = Multisig::from_args(multisig);
+
+    // A vault transaction can only be signed by the vault, so it must also
+    // pay for the mint
+    let (payer, tree_delegate) = multisig.as_ref().map_or_else(
+        || (signers.payer(), signers.authority()),
+        |m| (m.vault(), m.vault()),
+    );
+    let leaf_owner = recipient.unwrap_or_else(|| signers.leaf_owner());
+    check_creator_shares(&creators)?;
+
+    let metadata = metadata_args(
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        &creators,
+        immutable,
+        &payer,
+    );
+
+    multisig::send_or_propose(client, signers, multisig.as_ref(), &[
+        instructions::mint_v1(
+            &merkle_tree,
+            &leaf_owner,
+            &delegate.unwrap_or(leaf_owner),
+            &payer,
+            &tree_delegate,
+            metadata,
+        ),
+    ])
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use arborist::{
+        accounts::{decode_merkle_tree, decode_tree_config, MerkleTreeAccount, TreeConfig},
+        bank::{BankChain, TestBank},
+        tree::empty_root,
+    };
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    use super::*;
+    use crate::{
+        cli::{DecompressibleState, MultisigArgs, TreeArg},
+        test_support::{block_on, command_signers, TempDir},
+    };
+
+    /// A bank containing a funded payer that is also the authority, and a
+    /// depth-3 tree created with `create-tree` and registered as `oak`
+    struct Fixture {
+        keypair: Keypair,
+        signers: CommandSigners,
+        project: Project,
+        client: SolanaClient<BankChain>,
+        merkle_tree: Pubkey,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let keypair = Keypair::new();
+            let signers = command_signers(&keypair);
+            let dir = TempDir::new("bubblegum");
+            let mut project = dir.project();
+
+            let mut bank = TestBank::new();
+            bank.fund(&keypair.pubkey(), 10_000_000_000);
+            let client = SolanaClient::new(BankChain::new(bank), false);
+
+            let created = block_on(create_tree(&client, &signers, &mut project, CreateTree {
+                depth: 3,
+                buffer_size: 8,
+                canopy_depth: 0,
+                name: Some("oak".into()),
+            }));
+            let created = created.unwrap();
+
+            assert!(matches!(
+                created.transaction(),
+                TransactionOutcome::Confirmed { .. }
+            ));
+            let merkle_tree = created.merkle_tree().parse().unwrap();
+            assert_eq!(dir.project().tree(&oak()).unwrap(), merkle_tree);
+
+            Self {
+                keypair,
+                signers,
+                project,
+                client,
+                merkle_tree,
+            }
+        }
+
+        fn fund(&self, keypair: &Keypair) -> CommandSigners {
+            self.client.with_bank(|b| b.fund(&keypair.pubkey(), 1_000_000_000));
+
+            command_signers(keypair)
+        }
+
+        fn tree(&self) -> MerkleTreeAccount {
+            self.client.with_bank(|b| {
+                decode_merkle_tree(&b.account(&self.merkle_tree).unwrap().data).unwrap()
+            })
+        }
+
+        fn config(&self) -> TreeConfig {
+            let address = tree_config_address(&self.merkle_tree);
+
+            self.client
+                .with_bank(|b| decode_tree_config(&b.account(&address).unwrap().data).unwrap())
+        }
+    }
+
+    fn oak() -> TreeArg { TreeArg::Name("oak".into()) }
+
+    fn mint_args(recipient: Pubkey, creators: Vec<(Pubkey, u8)>) -> Mint {
+        Mint {
+            merkle_tree: oak(),
+            recipient: Some(recipient),
+            delegate: None,
+            name: "Acorn".into(),
+            symbol: "OAK".into(),
+            uri: "https://example.com/acorn.json".into(),
+            seller_fee_basis_points: 500,
+            creators,
+            immutable: false,
+            multisig: MultisigArgs::default(),
+        }
+    }
+
+    #[test]
+    fn creates_tree() {
+        let f = Fixture::new();
+        let creator = f.keypair.pubkey();
+
+        let tree = f.tree();
+        assert_eq!(tree.max_depth, 3);
+        assert_eq!(tree.max_buffer_size, 8);
+        assert_eq!(tree.authority, tree_config_address(&f.merkle_tree));
+        assert_eq!(tree.root, empty_root(3));
+
+        let config = f.config();
+        assert_eq!(config.tree_creator, creator);
+        assert_eq!(config.tree_delegate, creator);
+        assert_eq!(config.total_mint_capacity, 8);
+    }
+
+    #[test]
+    fn mints_and_delegates() {
+        let f = Fixture::new();
+        let creator = f.keypair.pubkey();
+        let recipient = Pubkey::new_unique();
+
+        let minted = block_on(mint(
+            &f.client,
+            &f.signers,
+            &f.project,
+            mint_args(recipient, vec![(creator, 100)]),
+        ))
+        .unwrap();
+        assert!(matches!(
+            minted,
+            AdminOutcome::Transaction(TransactionOutcome::Confirmed { .. })
+        ));
+        assert_eq!(f.config().num_minted, 1);
+        assert_eq!(f.tree().rightmost_index, 1);
+
+        let delegate = Keypair::new();
+        let delegate_args = || DelegateTree {
+            merkle_tree: oak(),
+            tree_authority: None,
+            new_tree_delegate: delegate.pubkey(),
+            multisig: MultisigArgs::default(),
+        };
+        block_on(delegate_tree(&f.client, &f.signers, &f.project, delegate_args())).unwrap();
+        assert_eq!(f.config().tree_delegate, delegate.pubkey());
+
+        // The new delegate may mint, but only the creator may delegate
+        let delegate_signers = f.fund(&delegate);
+        block_on(mint(
+            &f.client,
+            &delegate_signers,
+            &f.project,
+            mint_args(recipient, vec![]),
+        ))
+        .unwrap();
+        assert_eq!(f.config().num_minted, 2);
+        block_on(delegate_tree(
+            &f.client,
+            &delegate_signers,
+            &f.project,
+            delegate_args(),
+        ))
+        .unwrap_err();
+
+        let stranger = f.fund(&Keypair::new());
+        block_on(mint(
+            &f.client,
+            &stranger,
+            &f.project,
+            mint_args(recipient, vec![]),
+        ))
+        .unwrap_err();
+        assert_eq!(f.config().num_minted, 2);
+    }
+
+    #[test]
+    fn set_decompressible_reaches_program() {
+        let f = Fixture::new();
+
+        // Bubblegum 0.9.2 predates the set_decompressible_state instruction,
+        // so the program rejects it as unknown once it reaches the bank
+        let err = block_on(set_decompressible(
+            &f.client,
+            &f.signers,
+            &f.project,
+            SetDecompressible {
+                merkle_tree: oak(),
+                state: DecompressibleState::Enabled,
+                multisig: MultisigArgs::default(),
+            },
+        ))
+        .unwrap_err();
+
+        let Some(arborist::Error::TransactionFailed { source, .. }) = err.downcast_ref() else {
+            panic!("Unexpected error: {err:?}");
+        };
+        assert_eq!(
+            source.get_transaction_error(),
+            Some(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(
+                    anchor_lang::error::ErrorCode::InstructionFallbackNotFound.into()
+                ),
+            )),
+        );
+    }
+}
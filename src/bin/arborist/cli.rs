@@ -0,0 +1,1086 @@
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use arborist::client::{ComputeBudget, LookupTables, PriorityFee, SendConfig};
+use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash, pubkey::Pubkey};
+
+use crate::{output::OutputFormat, signer};
+
+trait ArgExt {
+    fn default_solana_config(self) -> Self;
+}
+
+impl ArgExt for clap::Arg {
+    fn default_solana_config(self) -> Self {
+        if let Some(ref f) = *solana_cli_config::CONFIG_FILE {
+            self.required(false).default_value(f.as_str())
+        } else {
+            self.required(true)
+        }
+    }
+}
+
+#[derive(clap::Parser)]
+#[command(author, version, about)]
+pub struct Opts {
+    /// Path to an existing Solana CLI configuration file
+    #[arg(short = 'C', long, default_solana_config(), global = true)]
+    pub solana_config: String,
+
+    /// Path to the Arborist project configuration file
+    #[arg(
+        long,
+        env = "ARBORIST_CONFIG",
+        default_value = crate::project::DEFAULT_PATH,
+        global = true
+    )]
+    pub project_config: PathBuf,
+
+    /// Profile from the project configuration to use for default options
+    #[arg(long, env = "ARBORIST_PROFILE", global = true)]
+    pub profile: Option<String>,
+
+    /// Override the default RPC endpoint
+    #[arg(
+        short = 'u',
+        long = "url",
+        value_name = "URL_OR_MONIKER",
+        global = true
+    )]
+    pub rpc_url: Option<String>,
+
+    /// Timeout for RPC requests
+    #[arg(long, default_value_t = 90, global = true)]
+    pub rpc_timeout: u64,
+
+    /// Override the default RPC commitment level
+    #[arg(long = "commitment", global = true)]
+    pub rpc_commitment: Option<CommitmentConfig>,
+
+    /// URL of a Digital Asset Standard (DAS) API endpoint, defaults to the
+    /// RPC endpoint
+    #[arg(long, env = "ARBORIST_DAS_URL", global = true)]
+    pub das_url: Option<String>,
+
+    /// URL of the RPC node's pubsub websocket endpoint, derived from the RPC
+    /// URL by default
+    #[arg(long, env = "ARBORIST_WS_URL", global = true)]
+    pub ws_url: Option<String>,
+
+    /// Override the default keypair path
+    #[arg(short, long, global = true)]
+    pub keypair: Option<String>,
+
+    #[command(flatten)]
+    pub signer: signer::SignerArgs,
+
+    #[command(flatten)]
+    pub roles: SignerRoleArgs,
+
+    #[command(flatten)]
+    pub compute_budget: ComputeBudgetArgs,
+
+    #[command(flatten)]
+    pub send: SendArgs,
+
+    #[command(flatten)]
+    pub lookup_tables: LookupTableArgs,
+
+    #[command(flatten)]
+    pub nonce: NonceArgs,
+
+    /// Format for printing command results and errors
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub output: OutputFormat,
+
+    #[command(subcommand)]
+    pub subcmd: Subcommand,
+}
+
+#[derive(Default, clap::Args)]
+pub struct SignerRoleArgs {
+    /// Signer paying transaction fees and rent, defaults to --keypair
+    #[arg(long, value_name = "SIGNER", global = true)]
+    pub payer: Option<String>,
+
+    /// Signer acting as the authority of the tree, lookup table, or nonce
+    /// account being managed, defaults to --keypair
+    #[arg(long, value_name = "SIGNER", global = true)]
+    pub authority: Option<String>,
+
+    /// Signer owning the leaves being operated on, defaults to --authority
+    #[arg(long, value_name = "SIGNER", global = true)]
+    pub leaf_owner: Option<String>,
+
+    /// Additional signer, e.g. a `PUBKEY=SIGNATURE` presigner; may be
+    /// specified multiple times
+    #[arg(long = "signer", value_name = "SIGNER", global = true)]
+    pub signers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, clap::Args)]
+pub struct ComputeBudgetArgs {
+    /// Priority fee to pay for each transaction, in micro-lamports per
+    /// compute unit
+    #[arg(long, value_name = "MICROLAMPORTS", global = true)]
+    pub priority_fee: Option<u64>,
+
+    /// Select the priority fee for each transaction automatically from the
+    /// recent fees paid for its writable accounts
+    #[arg(long, conflicts_with = "priority_fee", global = true)]
+    pub auto_priority_fee: bool,
+
+    /// Percentile of recent fees to pay when using --auto-priority-fee
+    #[arg(
+        long,
+        value_name = "PERCENTILE",
+        default_value_t = 75,
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        global = true
+    )]
+    pub priority_fee_percentile: u8,
+
+    /// Upper bound on the fee selected by --auto-priority-fee, in
+    /// micro-lamports per compute unit
+    #[arg(
+        long,
+        value_name = "MICROLAMPORTS",
+        requires = "auto_priority_fee",
+        global = true
+    )]
+    pub max_priority_fee: Option<u64>,
+
+    /// Maximum number of compute units each transaction may consume
+    #[arg(long, value_name = "UNITS", global = true)]
+    pub compute_unit_limit: Option<u32>,
+}
+
+impl From<ComputeBudgetArgs> for ComputeBudget {
+    fn from(args: ComputeBudgetArgs) -> Self {
+        let ComputeBudgetArgs {
+            priority_fee,
+            auto_priority_fee,
+            priority_fee_percentile,
+            max_priority_fee,
+            compute_unit_limit,
+        } = args;
+
+        Self {
+            priority_fee: match (priority_fee, auto_priority_fee) {
+                (Some(f), _) => PriorityFee::Fixed(f),
+                (None, true) => PriorityFee::Auto {
+                    percentile: priority_fee_percentile,
+                    max: max_priority_fee,
+                },
+                (None, false) => PriorityFee::None,
+            },
+            unit_limit: compute_unit_limit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::Args)]
+pub struct SendArgs {
+    /// Simulate transactions instead of sending them, printing their logs,
+    /// compute usage, and account changes
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Skip the RPC node's preflight check when sending transactions
+    #[arg(long, global = true)]
+    pub skip_preflight: bool,
+
+    /// Number of times to re-sign a transaction with a new blockhash if it
+    /// expires before being processed
+    #[arg(long, value_name = "COUNT", default_value_t = 2, global = true)]
+    pub blockhash_retries: usize,
+
+    /// Interval in milliseconds at which unconfirmed transactions are
+    /// rebroadcast
+    #[arg(long, value_name = "MILLIS", default_value_t = 2000, global = true)]
+    pub rebroadcast_interval: u64,
+
+    /// Sign transactions without sending them, printing each transaction and
+    /// its signatures for use with `submit`
+    #[arg(long, conflicts_with = "dry_run", global = true)]
+    pub sign_only: bool,
+
+    /// Blockhash to sign transactions with instead of fetching the latest
+    /// blockhash from the cluster
+    #[arg(long, value_name = "HASH", requires = "sign_only", global = true)]
+    pub blockhash: Option<Hash>,
+}
+
+impl From<SendArgs> for SendConfig {
+    fn from(args: SendArgs) -> Self {
+        let SendArgs {
+            dry_run,
+            skip_preflight,
+            blockhash_retries,
+            rebroadcast_interval,
+            sign_only,
+            blockhash,
+        } = args;
+
+        Self {
+            dry_run,
+            skip_preflight,
+            blockhash_retries,
+            rebroadcast_interval: Duration::from_millis(rebroadcast_interval),
+            sign_only,
+            blockhash,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub struct NonceArgs {
+    /// Use the blockhash stored in this durable nonce account instead of a
+    /// recent blockhash, advancing the nonce with each transaction
+    #[arg(long, value_name = "ACCOUNT", global = true)]
+    pub nonce: Option<Pubkey>,
+
+    /// Signer authorized to advance the nonce account, defaults to the
+    /// current signer
+    #[arg(long, value_name = "SIGNER", requires = "nonce", global = true)]
+    pub nonce_authority: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct LookupTableArgs {
+    /// Send versioned transactions using the given address lookup table; may
+    /// be specified multiple times
+    #[arg(long = "lookup-table", value_name = "ADDRESS", global = true)]
+    pub lookup_tables: Vec<Pubkey>,
+
+    /// Send versioned transactions using a lookup table owned by the fee
+    /// payer, creating it or adding Merkle tree and program accounts to it as
+    /// needed
+    #[arg(long, conflicts_with = "lookup_tables", global = true)]
+    pub auto_lookup_table: bool,
+}
+
+impl From<LookupTableArgs> for LookupTables {
+    fn from(args: LookupTableArgs) -> Self {
+        let LookupTableArgs {
+            lookup_tables,
+            auto_lookup_table,
+        } = args;
+
+        if auto_lookup_table {
+            Self::Auto
+        } else if lookup_tables.is_empty() {
+            Self::None
+        } else {
+            Self::Fixed(lookup_tables)
+        }
+    }
+}
+
+#[derive(clap::Subcommand)]
+pub enum Subcommand {
+    /// Create a new Merkle tree and tree configuration
+    CreateTree(CreateTree),
+
+    /// Delegate a Merkle tree to a new tree authority
+    DelegateTree(DelegateTree),
+
+    /// Allow or disallow decompressing the assets in a Merkle tree
+    SetDecompressible(SetDecompressible),
+
+    /// Mint a compressed NFT into a Merkle tree
+    Mint(Mint),
+
+    /// Mint a list of compressed NFTs, moving on to the next tree in a pool
+    /// as each one fills up
+    MintBatch(MintBatch),
+
+    /// Close empty Merkle trees and reclaim their rent
+    CloseTree(CloseTree),
+
+    /// Operate on concurrent Merkle trees directly through the account
+    /// compression program, without Bubblegum
+    #[command(subcommand)]
+    Compression(Compression),
+
+    /// Stream live updates to a Merkle tree and its configuration
+    WatchTree(WatchTree),
+
+    /// Serve Prometheus metrics on the capacity of a set of trees and send
+    /// alerts when they run low
+    Monitor(Monitor),
+
+    /// Decode the Bubblegum and account compression instructions in a
+    /// transaction and the events they logged
+    DecodeTx(DecodeTx),
+
+    /// Print a chronological log of the Bubblegum actions on a tree or a
+    /// single asset
+    History(History),
+
+    /// Save a point-in-time snapshot of a Merkle tree, its configuration and
+    /// its leaves to a file
+    ExportTree(ExportTree),
+
+    /// Verify and print a snapshot written by export-tree, without
+    /// connecting to the cluster
+    InspectSnapshot(InspectSnapshot),
+
+    /// List assets matching a set of filters using the DAS API
+    ListAssets(ListAssets),
+
+    /// Manage address lookup tables
+    #[command(subcommand)]
+    Lut(Lut),
+
+    /// Add signatures from each --signer to a transaction created with
+    /// --sign-only and send it
+    Submit(Submit),
+
+    /// Manage durable nonce accounts
+    #[command(subcommand)]
+    Nonce(Nonce),
+
+    /// Manage passphrase-encrypted keypair files
+    #[command(subcommand)]
+    Keystore(Keystore),
+
+    /// View or edit the project configuration file
+    #[command(subcommand)]
+    Config(Config),
+}
+
+/// A Merkle tree given by its address or by a name registered in the project
+/// configuration
+#[derive(Debug, Clone)]
+pub enum TreeArg {
+    Address(Pubkey),
+    Name(String),
+}
+
+impl FromStr for TreeArg {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Pubkey::from_str(s).map_or_else(|_| Self::Name(s.to_owned()), Self::Address))
+    }
+}
+
+#[derive(clap::Args)]
+pub struct CreateTree {
+    /// Depth (log2 capacity) of the tree
+    #[arg(short, long)]
+    pub depth: u8,
+
+    /// Buffer size (i.e. concurrency limit) for the tree
+    #[arg(short, long = "buffer")]
+    pub buffer_size: u16,
+
+    /// Cached tree (canopy) depth
+    #[arg(short, long = "canopy", default_value_t = 0)]
+    pub canopy_depth: u8,
+
+    /// Register the new tree under this name in the project configuration
+    #[arg(long)]
+    pub name: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct DelegateTree {
+    /// Address or name of the Merkle tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// Address of the tree configuration PDA, derived from the tree if not
+    /// given
+    #[arg(short = 'c', long = "config")]
+    pub tree_authority: Option<Pubkey>,
+
+    // TODO: this needs to be a signer
+    // /// The creator of the Merkle tree, defaults to the current signer
+    // #[arg(short = 'O', long = "owner")]
+    // pub tree_owner: Option<Pubkey>,
+    /// The new delegate over the Merkle tree
+    #[arg(short = 'd', long = "delegate")]
+    pub new_tree_delegate: Pubkey,
+
+    #[command(flatten)]
+    pub multisig: MultisigArgs,
+}
+
+#[derive(clap::Args)]
+pub struct SetDecompressible {
+    /// Address or name of the Merkle tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// Whether assets in the tree may be decompressed
+    #[arg(value_enum)]
+    pub state: DecompressibleState,
+
+    #[command(flatten)]
+    pub multisig: MultisigArgs,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum DecompressibleState {
+    Enabled,
+    Disabled,
+}
+
+impl From<DecompressibleState> for arborist::instructions::DecompressibleState {
+    fn from(state: DecompressibleState) -> Self {
+        match state {
+            DecompressibleState::Enabled => Self::Enabled,
+            DecompressibleState::Disabled => Self::Disabled,
+        }
+    }
+}
+
+#[derive(clap::Args)]
+pub struct Mint {
+    /// Address or name of the Merkle tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// Owner of the new asset, defaults to the --leaf-owner signer
+    #[arg(long)]
+    pub recipient: Option<Pubkey>,
+
+    /// Delegate of the new asset, defaults to its owner
+    #[arg(long)]
+    pub delegate: Option<Pubkey>,
+
+    /// Name of the asset
+    #[arg(long)]
+    pub name: String,
+
+    /// Symbol of the asset
+    #[arg(long, default_value = "")]
+    pub symbol: String,
+
+    /// URI of the asset's JSON metadata
+    #[arg(long)]
+    pub uri: String,
+
+    /// Royalty paid to creators on secondary sales, in basis points
+    #[arg(long, default_value_t = 0)]
+    pub seller_fee_basis_points: u16,
+
+    /// A creator and their percentage share of royalties, as ADDRESS:SHARE;
+    /// may be specified multiple times.  Creators are verified if they are
+    /// the fee payer.
+    #[arg(long = "creator", value_name = "ADDRESS:SHARE", value_parser = parse_creator)]
+    pub creators: Vec<(Pubkey, u8)>,
+
+    /// Prevent the asset's metadata from being updated
+    #[arg(long)]
+    pub immutable: bool,
+
+    #[command(flatten)]
+    pub multisig: MultisigArgs,
+}
+
+#[derive(clap::Args)]
+#[command(group(
+    clap::ArgGroup::new("pool")
+        .required(true)
+        .multiple(true)
+        .args(["trees", "new_depth"]),
+))]
+pub struct MintBatch {
+    /// CSV file listing the assets to mint, with `name` and `uri` columns and
+    /// optional `symbol`, `recipient`, `delegate`, and
+    /// `seller_fee_basis_points` columns.  Recipients default to the
+    /// --leaf-owner signer.
+    pub items: PathBuf,
+
+    /// Address or name of a tree to mint into; may be specified multiple
+    /// times.  Trees are used in order, moving on to the next when one
+    /// reaches its mint capacity.
+    #[arg(short = 't', long = "tree")]
+    pub trees: Vec<TreeArg>,
+
+    /// Depth of the trees to create once the given trees are full
+    #[arg(long, requires = "new_buffer_size")]
+    pub new_depth: Option<u8>,
+
+    /// Buffer size of the trees to create once the given trees are full
+    #[arg(long = "new-buffer", requires = "new_depth")]
+    pub new_buffer_size: Option<u16>,
+
+    /// Canopy depth of the trees to create once the given trees are full
+    #[arg(long = "new-canopy", default_value_t = 0, requires = "new_depth")]
+    pub new_canopy_depth: u8,
+
+    /// Delegate each new tree to this address after creating it
+    #[arg(long, requires = "new_depth")]
+    pub new_tree_delegate: Option<Pubkey>,
+
+    /// Register each new tree in the project configuration as NAME-1, NAME-2,
+    /// and so on, skipping names already in use
+    #[arg(long, value_name = "NAME", requires = "new_depth")]
+    pub new_tree_name: Option<String>,
+
+    /// A creator and their percentage share of royalties for every asset, as
+    /// ADDRESS:SHARE; may be specified multiple times.  Creators are verified
+    /// if they are the fee payer.
+    #[arg(long = "creator", value_name = "ADDRESS:SHARE", value_parser = parse_creator)]
+    pub creators: Vec<(Pubkey, u8)>,
+
+    /// Prevent the assets' metadata from being updated
+    #[arg(long)]
+    pub immutable: bool,
+
+    /// Append the tree, leaf index, and asset ID of each item to this CSV
+    /// file as soon as it is minted
+    #[arg(long)]
+    pub results: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct CloseTree {
+    /// Addresses or names of the trees to close.  Trees that cannot be closed
+    /// are skipped and reported, and a failure to close one tree does not
+    /// stop the rest from being closed.
+    #[arg(required = true)]
+    pub trees: Vec<TreeArg>,
+
+    /// Account to receive the reclaimed rent, defaults to the fee payer
+    #[arg(long)]
+    pub recipient: Option<Pubkey>,
+}
+
+fn parse_creator(s: &str) -> Result<(Pubkey, u8), String> {
+    let (address, share) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected ADDRESS:SHARE, found {s:?}"))?;
+    let address = address
+        .parse()
+        .map_err(|e| format!("invalid address {address:?}: {e}"))?;
+    let share = share
+        .parse()
+        .map_err(|e| format!("invalid share {share:?}: {e}"))?;
+
+    Ok((address, share))
+}
+
+#[derive(Default, clap::Args)]
+pub struct MultisigArgs {
+    /// Propose the instructions to this Squads multisig instead of sending
+    /// them, using its vault as the authority.  The --authority signer must
+    /// be a member of the multisig.
+    #[arg(long, value_name = "ADDRESS")]
+    pub multisig: Option<Pubkey>,
+
+    /// Index of the multisig vault to use as the authority
+    #[arg(long, default_value_t = 0, requires = "multisig")]
+    pub vault_index: u8,
+
+    /// Index of the new multisig transaction, defaults to the next index of
+    /// the multisig.  Required to propose without contacting the cluster.
+    #[arg(long, requires = "multisig")]
+    pub transaction_index: Option<u64>,
+
+    /// Memo to attach to the multisig transaction
+    #[arg(long, requires = "multisig")]
+    pub memo: Option<String>,
+}
+
+/// Parse a 32-byte tree node given as hex (with or without a `0x` prefix) or
+/// base58
+fn parse_node(s: &str) -> Result<[u8; 32], String> {
+    let hex = s.strip_prefix("0x").unwrap_or(s);
+    let bytes = if hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        (0..64)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    } else {
+        bs58::decode(s)
+            .into_vec()
+            .map_err(|e| format!("expected 32 bytes of hex or base58: {e}"))?
+    };
+
+    bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| format!("expected 32 bytes, found {}", b.len()))
+}
+
+#[derive(clap::Subcommand)]
+pub enum Compression {
+    /// Create an empty tree owned by the --authority signer
+    Create(CompressionCreate),
+
+    /// Print the state of a tree
+    Show(CompressionShow),
+
+    /// Append leaves to the rightmost positions of a tree
+    Append(CompressionAppend),
+
+    /// Replace the leaf at an index
+    Replace(CompressionReplace),
+
+    /// Verify that a leaf is present at an index
+    Verify(CompressionVerify),
+
+    /// Transfer write access to a tree to a new authority
+    TransferAuthority(CompressionTransferAuthority),
+
+    /// Close a tree with no leaves and reclaim its rent
+    Close(CompressionClose),
+}
+
+#[derive(clap::Args)]
+pub struct CompressionCreate {
+    /// Depth (log2 capacity) of the tree
+    #[arg(short, long)]
+    pub depth: u8,
+
+    /// Buffer size (i.e. concurrency limit) for the tree
+    #[arg(short, long = "buffer")]
+    pub buffer_size: u16,
+
+    /// Cached tree (canopy) depth
+    #[arg(short, long = "canopy", default_value_t = 0)]
+    pub canopy_depth: u8,
+
+    /// Register the new tree under this name in the project configuration
+    #[arg(long)]
+    pub name: Option<String>,
+}
+
+#[derive(clap::Args)]
+pub struct CompressionShow {
+    /// Address or name of the tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+}
+
+#[derive(clap::Args)]
+pub struct CompressionAppend {
+    /// Address or name of the tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// Leaves to append in order, as 32 bytes of hex or base58
+    #[arg(required = true, value_parser = parse_node)]
+    pub leaves: Vec<[u8; 32]>,
+}
+
+#[derive(clap::Args)]
+pub struct LeafProof {
+    /// Index of the leaf
+    #[arg(short, long)]
+    pub index: u32,
+
+    /// Proof nodes for the leaf, from the leaf level up, excluding any
+    /// levels stored in the tree's canopy
+    #[arg(long, value_delimiter = ',', value_parser = parse_node)]
+    pub proof: Vec<[u8; 32]>,
+
+    /// Root the proof was generated against, defaults to the tree's current
+    /// root.  Required to sign without contacting the cluster.
+    #[arg(long, value_parser = parse_node)]
+    pub root: Option<[u8; 32]>,
+}
+
+#[derive(clap::Args)]
+pub struct CompressionReplace {
+    /// Address or name of the tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// Current value of the leaf
+    #[arg(long, value_parser = parse_node)]
+    pub previous_leaf: [u8; 32],
+
+    /// New value of the leaf
+    #[arg(long, value_parser = parse_node)]
+    pub new_leaf: [u8; 32],
+
+    #[command(flatten)]
+    pub proof: LeafProof,
+}
+
+#[derive(clap::Args)]
+pub struct CompressionVerify {
+    /// Address or name of the tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// Expected value of the leaf
+    #[arg(long, value_parser = parse_node)]
+    pub leaf: [u8; 32],
+
+    #[command(flatten)]
+    pub proof: LeafProof,
+}
+
+#[derive(clap::Args)]
+pub struct CompressionTransferAuthority {
+    /// Address or name of the tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// New authority of the tree
+    #[arg(long)]
+    pub new_authority: Pubkey,
+}
+
+#[derive(clap::Args)]
+pub struct CompressionClose {
+    /// Address or name of the tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// Account to receive the reclaimed rent, defaults to the fee payer
+    #[arg(long)]
+    pub recipient: Option<Pubkey>,
+}
+
+#[derive(clap::Args)]
+pub struct WatchTree {
+    /// Address or name of the tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+}
+
+#[derive(clap::Args)]
+pub struct Monitor {
+    /// Path to the monitor configuration file
+    #[arg(long, default_value = "monitor.toml")]
+    pub config: PathBuf,
+}
+
+#[derive(clap::Args)]
+pub struct DecodeTx {
+    /// Signature of the transaction to fetch, or the path to a JSON file
+    /// containing a `getTransaction` response with base58 or base64 encoding
+    #[arg(value_name = "SIGNATURE|FILE")]
+    pub transaction: String,
+}
+
+#[derive(clap::Args)]
+pub struct History {
+    /// Address or name of the tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// Only show actions on this asset, giving its full provenance
+    #[arg(long)]
+    pub asset: Option<Pubkey>,
+
+    /// Only show actions processed in or after this slot
+    #[arg(long)]
+    pub since: Option<u64>,
+}
+
+#[derive(clap::Args)]
+pub struct ExportTree {
+    /// Address or name of the tree
+    #[arg(short = 't', long = "tree")]
+    pub merkle_tree: TreeArg,
+
+    /// Path to write the snapshot to
+    #[arg(short, long)]
+    pub out: PathBuf,
+
+    /// Don't fetch the tree's leaves from the DAS API
+    #[arg(long)]
+    pub no_leaves: bool,
+}
+
+#[derive(clap::Args)]
+pub struct InspectSnapshot {
+    /// Path to the snapshot file
+    pub file: PathBuf,
+
+    /// List every leaf stored in the snapshot
+    #[arg(long)]
+    pub leaves: bool,
+}
+
+#[derive(clap::Args)]
+#[command(group(
+    clap::ArgGroup::new("filter")
+        .required(true)
+        .multiple(true)
+        .args(["owner", "tree", "collection", "creator"]),
+))]
+pub struct ListAssets {
+    /// Only list assets owned by this wallet
+    #[arg(long)]
+    pub owner: Option<Pubkey>,
+
+    /// Only list assets minted into this Merkle tree, given by address or
+    /// name
+    #[arg(short = 't', long)]
+    pub tree: Option<TreeArg>,
+
+    /// Only list assets belonging to this collection mint
+    #[arg(long)]
+    pub collection: Option<Pubkey>,
+
+    /// Only list assets with this creator
+    #[arg(long)]
+    pub creator: Option<Pubkey>,
+
+    /// Include uncompressed assets in the results
+    #[arg(long)]
+    pub include_uncompressed: bool,
+
+    /// Page of results to fetch, starting from 1
+    #[arg(long, default_value_t = 1)]
+    pub page: u32,
+
+    /// Number of results per page
+    #[arg(long, default_value_t = 100)]
+    pub limit: u32,
+
+    /// Fetch all pages of results starting from the given page
+    #[arg(long)]
+    pub all: bool,
+
+    /// Field to sort results by
+    #[arg(long, value_enum)]
+    pub sort: Option<ListAssetsSort>,
+
+    /// Direction to sort results in
+    #[arg(long, value_enum, default_value_t = SortDirection::Asc, requires = "sort")]
+    pub direction: SortDirection,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ListAssetsSort {
+    Created,
+    Updated,
+    RecentAction,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Lut {
+    /// Create a new address lookup table owned by the --authority signer
+    Create(LutCreate),
+
+    /// Add addresses to an address lookup table
+    Extend(LutExtend),
+
+    /// Deactivate an address lookup table so that it can later be closed
+    Deactivate(LutDeactivate),
+
+    /// Close a deactivated address lookup table and reclaim its rent
+    Close(LutClose),
+
+    /// Print the contents of an address lookup table
+    Show(LutShow),
+}
+
+#[derive(clap::Args)]
+pub struct LutAddresses {
+    /// Add the Bubblegum accounts used by this Merkle tree (given by address
+    /// or name), including its tree configuration and the programs it
+    /// invokes; may be specified multiple times
+    #[arg(short = 't', long = "tree")]
+    pub trees: Vec<TreeArg>,
+
+    /// Additional addresses to add
+    pub addresses: Vec<Pubkey>,
+}
+
+#[derive(clap::Args)]
+pub struct LutCreate {
+    #[command(flatten)]
+    pub addresses: LutAddresses,
+}
+
+#[derive(clap::Args)]
+pub struct LutExtend {
+    /// Address of the lookup table
+    #[arg(short = 'l', long = "table")]
+    pub table: Pubkey,
+
+    #[command(flatten)]
+    pub addresses: LutAddresses,
+}
+
+#[derive(clap::Args)]
+pub struct LutDeactivate {
+    /// Address of the lookup table
+    pub table: Pubkey,
+}
+
+#[derive(clap::Args)]
+pub struct LutClose {
+    /// Address of the lookup table
+    pub table: Pubkey,
+
+    /// Account to receive the reclaimed rent, defaults to the fee payer
+    #[arg(long)]
+    pub recipient: Option<Pubkey>,
+}
+
+#[derive(clap::Args)]
+pub struct LutShow {
+    /// Address of the lookup table
+    pub table: Pubkey,
+}
+
+#[derive(clap::Args)]
+pub struct Submit {
+    /// Serialized transaction, encoded as base58 or base64.  Signatures are
+    /// added from each --signer, e.g. `--signer PUBKEY=SIGNATURE`.
+    pub transaction: String,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Nonce {
+    /// Create a new durable nonce account funded by the fee payer
+    Create(NonceCreate),
+
+    /// Print the state of a durable nonce account
+    Show(NonceShow),
+
+    /// Advance a durable nonce account, invalidating any transactions signed
+    /// with its current value
+    Advance(NonceAdvance),
+
+    /// Withdraw lamports from a durable nonce account
+    Withdraw(NonceWithdraw),
+}
+
+#[derive(clap::Args)]
+pub struct NonceCreate {
+    /// Authority of the new nonce account, defaults to the --authority signer
+    #[arg(long = "new-authority", value_name = "PUBKEY")]
+    pub authority: Option<Pubkey>,
+
+    /// Lamports to fund the new account with, defaults to the minimum balance
+    /// for rent exemption
+    #[arg(long)]
+    pub lamports: Option<u64>,
+}
+
+#[derive(clap::Args)]
+pub struct NonceShow {
+    /// Address of the nonce account
+    pub account: Pubkey,
+}
+
+#[derive(clap::Args)]
+pub struct NonceAdvance {
+    /// Address of the nonce account
+    pub account: Pubkey,
+}
+
+#[derive(clap::Args)]
+pub struct NonceWithdraw {
+    /// Address of the nonce account
+    pub account: Pubkey,
+
+    /// Lamports to withdraw; withdrawing the full balance closes the account
+    pub lamports: u64,
+
+    /// Account to receive the withdrawn lamports, defaults to the fee payer
+    #[arg(long)]
+    pub recipient: Option<Pubkey>,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Keystore {
+    /// Generate a new keypair and write it to an encrypted keystore
+    New(KeystoreNew),
+
+    /// Encrypt an existing keypair into a keystore
+    Import(KeystoreImport),
+
+    /// Decrypt a keystore into a plaintext keypair file
+    Export(KeystoreExport),
+}
+
+#[derive(clap::Args)]
+pub struct KeystoreNew {
+    /// Path to write the keystore to
+    #[arg(long)]
+    pub outfile: PathBuf,
+
+    /// Overwrite the output file if it exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(clap::Args)]
+pub struct KeystoreImport {
+    /// Signer source of the keypair to encrypt, e.g. a keypair file or
+    /// `prompt:`
+    pub keypair: String,
+
+    /// Path to write the keystore to
+    #[arg(long)]
+    pub outfile: PathBuf,
+
+    /// Overwrite the output file if it exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(clap::Args)]
+pub struct KeystoreExport {
+    /// Path of the keystore to decrypt
+    pub keystore: String,
+
+    /// Path to write the plaintext keypair file to
+    #[arg(long)]
+    pub outfile: PathBuf,
+
+    /// Overwrite the output file if it exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Config {
+    /// Print a value from the project configuration, e.g. `profiles.devnet.url`
+    Get(ConfigGet),
+
+    /// Set a value in the project configuration, creating the file if needed
+    Set(ConfigSet),
+
+    /// Print every value in the project configuration
+    List,
+}
+
+#[derive(clap::Args)]
+pub struct ConfigGet {
+    /// Dotted path of the value, e.g. `trees.drop-2024.address`
+    pub key: String,
+}
+
+#[derive(clap::Args)]
+pub struct ConfigSet {
+    /// Dotted path of the value, e.g. `profiles.devnet.priority_fee`
+    pub key: String,
+
+    /// New value, parsed as a TOML value if possible and otherwise as a
+    /// string
+    pub value: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn verify_cli() {
+        Opts::command().debug_assert();
+    }
+}
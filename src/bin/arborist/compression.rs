@@ -0,0 +1,592 @@
+use anyhow::{anyhow, Context, Result};
+use arborist::{
+    accounts::{decode_merkle_tree, MerkleTreeAccount},
+    chain::ChainClient,
+    client::{SolanaClient, TransactionOutcome},
+    compression::{self, Node},
+    instructions::create_tree_account,
+    tree::{tree_config_address, tree_size},
+};
+use serde::Serialize;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{
+    cli::{
+        CloseTree, CompressionAppend, CompressionClose, CompressionCreate, CompressionReplace,
+        CompressionShow, CompressionTransferAuthority, CompressionVerify, LeafProof,
+    },
+    output::{ErrorCode, Output, ResultExt, TransactionsResult},
+    project::{NamedTree, Project},
+    signer::CommandSigners,
+};
+
+/// Maximum number of leaves appended by a single transaction, chosen to keep
+/// transactions within the default compute budget
+const MAX_APPEND_LEAVES: usize = 8;
+
+/// Result of the `compression create` command
+#[derive(Debug, Serialize)]
+pub struct CreateResult {
+    #[serde(flatten)]
+    transaction: TransactionOutcome,
+    merkle_tree: String,
+}
+
+impl Output for CreateResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        self.transaction.write_text(out)?;
+        writeln!(out, "Merkle tree: {}", self.merkle_tree)
+    }
+}
+
+/// Result of the `compression show` command
+#[derive(Debug, Serialize)]
+pub struct ShowResult {
+    merkle_tree: String,
+    authority: String,
+    max_depth: u32,
+    max_buffer_size: u32,
+    canopy_depth: u32,
+    creation_slot: u64,
+    sequence_number: u64,
+    rightmost_index: u32,
+    root: String,
+}
+
+impl Output for ShowResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        writeln!(out, "Merkle tree: {}", self.merkle_tree)?;
+        writeln!(out, "Authority: {}", self.authority)?;
+        writeln!(out, "Max depth: {}", self.max_depth)?;
+        writeln!(out, "Max buffer size: {}", self.max_buffer_size)?;
+        writeln!(out, "Canopy depth: {}", self.canopy_depth)?;
+        writeln!(out, "Creation slot: {}", self.creation_slot)?;
+        writeln!(out, "Sequence number: {}", self.sequence_number)?;
+        writeln!(out, "Rightmost index: {}", self.rightmost_index)?;
+        writeln!(out, "Root: {}", self.root)
+    }
+}
+
+/// Outcome of closing a single tree with `close-tree`
+#[derive(Debug, Serialize)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+enum CloseOutcome {
+    /// The tree was closed
+    Closed {
+        transaction: TransactionOutcome,
+        lamports: u64,
+    },
+    /// The tree cannot be closed
+    Skipped { reason: String },
+    /// Reading or closing the tree failed
+    Failed { code: ErrorCode, error: String },
+}
+
+impl CloseOutcome {
+    fn failed(err: &anyhow::Error) -> Self {
+        Self::Failed {
+            code: ErrorCode::of(err),
+            error: format!("{err:#}"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ClosedTree {
+    merkle_tree: String,
+    #[serde(flatten)]
+    outcome: CloseOutcome,
+}
+
+/// Result of the `close-tree` command
+#[derive(Debug, Serialize)]
+pub struct CloseTreesResult {
+    trees: Vec<ClosedTree>,
+    lamports_reclaimed: u64,
+}
+
+impl Output for CloseTreesResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        for ClosedTree {
+            merkle_tree,
+            outcome,
+        } in &self.trees
+        {
+            match outcome {
+                CloseOutcome::Closed {
+                    transaction,
+                    lamports,
+                } => {
+                    writeln!(out, "{merkle_tree}: closed, reclaiming {lamports} lamports")?;
+                    transaction.write_text(out)?;
+                },
+                CloseOutcome::Skipped { reason } => {
+                    writeln!(out, "{merkle_tree}: skipped: {reason}")?;
+                },
+                CloseOutcome::Failed { error, .. } => {
+                    writeln!(out, "{merkle_tree}: failed: {error}")?;
+                },
+            }
+        }
+
+        writeln!(out, "Total reclaimed: {} lamports", self.lamports_reclaimed)
+    }
+}
+
+impl CloseTreesResult {
+    /// Fail if any tree could not be read or closed, once the full report
+    /// has been printed
+    ///
+    /// # Errors
+    /// This function fails if the outcome of any tree is
+    /// [`CloseOutcome::Failed`], using the error code of the first failure.
+    pub fn check(&self) -> Result<()> {
+        let mut failed = self.trees.iter().filter_map(|t| match t.outcome {
+            CloseOutcome::Failed { code, .. } => Some(code),
+            _ => None,
+        });
+
+        let Some(code) = failed.next() else {
+            return Ok(());
+        };
+
+        Err(anyhow!(
+            "Failed to close {} of {} tree(s)",
+            failed.count() + 1,
+            self.trees.len()
+        ))
+        .code(code)
+    }
+}
+
+async fn fetch_tree<C: ChainClient>(
+    client: &SolanaClient<C>,
+    merkle_tree: &Pubkey,
+) -> Result<MerkleTreeAccount> {
+    let account = client
+        .account(merkle_tree)
+        .await
+        .with_context(|| format!("Error getting Merkle tree {merkle_tree}"))
+        .code(ErrorCode::Rpc)?
+        .with_context(|| format!("Merkle tree {merkle_tree} does not exist"))
+        .code(ErrorCode::InvalidArgument)?;
+
+    decode_merkle_tree(&account.data)
+        .with_context(|| format!("Error decoding Merkle tree {merkle_tree}"))
+        .code(ErrorCode::InvalidArgument)
+}
+
+/// Resolve the root a proof was generated against, defaulting to the current
+/// root of the tree
+async fn proof_root<C: ChainClient>(
+    client: &SolanaClient<C>,
+    merkle_tree: &Pubkey,
+    root: Option<Node>,
+) -> Result<Node> {
+    if let Some(root) = root {
+        return Ok(root);
+    }
+
+    Ok(fetch_tree(client, merkle_tree)
+        .await
+        .context("Error getting current tree root (pass --root to skip this)")?
+        .root)
+}
+
+async fn send<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    ix: Instruction,
+) -> Result<TransactionOutcome> {
+    let payer = signers.payer();
+
+    client
+        .send_transaction(&[ix], Some(&payer), &signers.with(&[]))
+        .await
+        .map_err(Into::into)
+}
+
+pub async fn create<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &mut Project,
+    args: CompressionCreate,
+) -> Result<CreateResult> {
+    let CompressionCreate {
+        depth,
+        buffer_size,
+        canopy_depth,
+        name,
+    } = args;
+
+    let payer = signers.payer();
+    let tree = Keypair::new();
+    let tree_pubkey = tree.try_pubkey().unwrap_or_else(|_| unreachable!());
+
+    let size = tree_size(depth, buffer_size, canopy_depth).code(ErrorCode::InvalidArgument)?;
+    let rent = client
+        .rent_exemption(size.try_into().unwrap_or_else(|_| unreachable!()))
+        .await
+        .context("Error getting rent exemption balance for new tree")?;
+
+    let transaction = client
+        .send_transaction(
+            &[
+                create_tree_account(&payer, &tree_pubkey, rent, size),
+                compression::init_empty_merkle_tree(
+                    &tree_pubkey,
+                    &signers.authority(),
+                    depth.into(),
+                    buffer_size.into(),
+                ),
+            ],
+            Some(&payer),
+            &signers.with(&[&tree]),
+        )
+        .await?;
+
+    // Only register trees that actually exist
+    if let (Some(name), TransactionOutcome::Confirmed { .. }) = (name, &transaction) {
+        project
+            .register_tree(name, NamedTree {
+                address: tree_pubkey.to_string(),
+                depth: Some(depth),
+                buffer_size: Some(buffer_size),
+                canopy_depth: Some(canopy_depth),
+            })
+            .context("Error registering new tree")?;
+    }
+
+    Ok(CreateResult {
+        transaction,
+        merkle_tree: tree_pubkey.to_string(),
+    })
+}
+
+pub async fn show<C: ChainClient>(
+    client: &SolanaClient<C>,
+    project: &Project,
+    args: CompressionShow,
+) -> Result<ShowResult> {
+    let CompressionShow { merkle_tree } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+
+    let MerkleTreeAccount {
+        max_depth,
+        max_buffer_size,
+        authority,
+        creation_slot,
+        sequence_number,
+        root,
+        rightmost_index,
+        canopy_depth,
+    } = fetch_tree(client, &merkle_tree).await?;
+
+    Ok(ShowResult {
+        merkle_tree: merkle_tree.to_string(),
+        authority: authority.to_string(),
+        max_depth,
+        max_buffer_size,
+        canopy_depth,
+        creation_slot,
+        sequence_number,
+        rightmost_index,
+        root: bs58::encode(root).into_string(),
+    })
+}
+
+pub async fn append<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: CompressionAppend,
+) -> Result<TransactionsResult> {
+    let CompressionAppend {
+        merkle_tree,
+        leaves,
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+    let payer = signers.payer();
+    let authority = signers.authority();
+    let mut transactions = vec![];
+
+    for chunk in leaves.chunks(MAX_APPEND_LEAVES) {
+        let ixs = chunk
+            .iter()
+            .map(|&leaf| compression::append(&merkle_tree, &authority, leaf))
+            .collect::<Vec<_>>();
+
+        transactions.push(
+            client
+                .send_transaction(&ixs, Some(&payer), &signers.with(&[]))
+                .await?,
+        );
+    }
+
+    Ok(TransactionsResult { transactions })
+}
+
+pub async fn replace<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: CompressionReplace,
+) -> Result<TransactionOutcome> {
+    let CompressionReplace {
+        merkle_tree,
+        previous_leaf,
+        new_leaf,
+        proof: LeafProof { index, proof, root },
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+    let root = proof_root(client, &merkle_tree, root).await?;
+
+    send(
+        client,
+        signers,
+        compression::replace_leaf(
+            &merkle_tree,
+            &signers.authority(),
+            root,
+            previous_leaf,
+            new_leaf,
+            index,
+            &proof,
+        ),
+    )
+    .await
+}
+
+pub async fn verify<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: CompressionVerify,
+) -> Result<TransactionOutcome> {
+    let CompressionVerify {
+        merkle_tree,
+        leaf,
+        proof: LeafProof { index, proof, root },
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+    let root = proof_root(client, &merkle_tree, root).await?;
+
+    send(
+        client,
+        signers,
+        compression::verify_leaf(&merkle_tree, root, leaf, index, &proof),
+    )
+    .await
+}
+
+pub async fn transfer_authority<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: CompressionTransferAuthority,
+) -> Result<TransactionOutcome> {
+    let CompressionTransferAuthority {
+        merkle_tree,
+        new_authority,
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+
+    send(
+        client,
+        signers,
+        compression::transfer_authority(&merkle_tree, &signers.authority(), new_authority),
+    )
+    .await
+}
+
+pub async fn close<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: CompressionClose,
+) -> Result<TransactionOutcome> {
+    let CompressionClose {
+        merkle_tree,
+        recipient,
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+
+    send(
+        client,
+        signers,
+        compression::close_empty_tree(
+            &merkle_tree,
+            &signers.authority(),
+            &recipient.unwrap_or_else(|| signers.payer()),
+        ),
+    )
+    .await
+}
+
+/// Check whether a tree can be closed by the current authority, returning its
+/// balance if so or the reason it cannot be closed otherwise
+async fn closable_balance<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    merkle_tree: &Pubkey,
+) -> Result<Result<u64, String>> {
+    let Some(account) = client
+        .account(merkle_tree)
+        .await
+        .with_context(|| format!("Error getting Merkle tree {merkle_tree}"))
+        .code(ErrorCode::Rpc)?
+    else {
+        return Ok(Err("Account does not exist".into()));
+    };
+
+    if account.owner != spl_account_compression::ID {
+        return Ok(Err(format!(
+            "Account is owned by {}, not the account compression program",
+            account.owner
+        )));
+    }
+
+    let tree = match decode_merkle_tree(&account.data) {
+        Ok(t) => t,
+        Err(e) => return Ok(Err(e.to_string())),
+    };
+
+    if tree.authority == tree_config_address(merkle_tree) {
+        return Ok(Err("Tree is managed by Bubblegum, which has no \
+                       instruction for closing trees, so its rent cannot be \
+                       reclaimed"
+            .into()));
+    }
+
+    let authority = signers.authority();
+    if tree.authority != authority {
+        return Ok(Err(format!(
+            "Tree authority is {}, not the --authority signer {authority}",
+            tree.authority
+        )));
+    }
+
+    if !tree.is_empty() {
+        return Ok(Err(format!(
+            "Tree is not empty ({} leaves appended); every leaf must be replaced with an empty \
+             leaf before it can be closed",
+            tree.rightmost_index
+        )));
+    }
+
+    Ok(Ok(account.lamports))
+}
+
+pub async fn close_trees<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: CloseTree,
+) -> Result<CloseTreesResult> {
+    let CloseTree { trees, recipient } = args;
+    let trees = project.trees(&trees)?;
+    let recipient = recipient.unwrap_or_else(|| signers.payer());
+    let mut closed = vec![];
+    let mut lamports_reclaimed = 0;
+
+    for merkle_tree in trees {
+        // A failure only affects its own tree, so that one bad tree doesn't
+        // hide the outcome of the rest of the sweep
+        let outcome = match closable_balance(client, signers, &merkle_tree).await {
+            Ok(Ok(lamports)) => match send(
+                client,
+                signers,
+                compression::close_empty_tree(&merkle_tree, &signers.authority(), &recipient),
+            )
+            .await
+            {
+                Ok(transaction) => {
+                    if let TransactionOutcome::Confirmed { .. } = transaction {
+                        lamports_reclaimed += lamports;
+                    }
+
+                    CloseOutcome::Closed {
+                        transaction,
+                        lamports,
+                    }
+                },
+                Err(e) => CloseOutcome::failed(&e),
+            },
+            Ok(Err(reason)) => CloseOutcome::Skipped { reason },
+            Err(e) => CloseOutcome::failed(&e),
+        };
+
+        closed.push(ClosedTree {
+            merkle_tree: merkle_tree.to_string(),
+            outcome,
+        });
+    }
+
+    Ok(CloseTreesResult {
+        trees: closed,
+        lamports_reclaimed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use arborist::{
+        chain::{MockChain, MockSend},
+        tree::empty_root,
+    };
+    use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+    use super::*;
+    use crate::{
+        cli::TreeArg,
+        test_support::{block_on, command_signers, tree_account, TempDir},
+    };
+
+    #[test]
+    fn close_failure_does_not_stop_sweep() {
+        let keypair = Keypair::new();
+        let authority = keypair.pubkey();
+        let signers = command_signers(&keypair);
+        let dir = TempDir::new("compression");
+
+        let mut empty = tree_account(0, empty_root(3), 0);
+        empty.data[10..42].copy_from_slice(authority.as_ref());
+        let (failed, closed, missing) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let chain = MockChain::new();
+        chain.set_account(failed, empty.clone());
+        chain.set_account(closed, empty);
+        chain.push_send(MockSend::Fail(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(1),
+        )));
+        let client = SolanaClient::new(chain, false);
+
+        let res = block_on(close_trees(&client, &signers, &dir.project(), CloseTree {
+            trees: vec![
+                TreeArg::Address(failed),
+                TreeArg::Address(closed),
+                TreeArg::Address(missing),
+            ],
+            recipient: None,
+        }))
+        .unwrap();
+
+        assert!(matches!(res.trees[0].outcome, CloseOutcome::Failed {
+            code: ErrorCode::TransactionFailed,
+            ..
+        }));
+        assert!(matches!(res.trees[1].outcome, CloseOutcome::Closed {
+            lamports: 1,
+            ..
+        }));
+        assert!(matches!(res.trees[2].outcome, CloseOutcome::Skipped { .. }));
+        assert_eq!(res.lamports_reclaimed, 1);
+
+        let err = res.check().unwrap_err();
+        assert_eq!(ErrorCode::of(&err), ErrorCode::TransactionFailed);
+    }
+}
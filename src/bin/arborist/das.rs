@@ -0,0 +1,754 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Context, Result};
+use arborist::snapshot::LeafState;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    cli::{ListAssets, ListAssetsSort, SortDirection},
+    output::{self, ErrorCode, Output, ResultExt},
+    project::Project,
+};
+
+/// Maximum page size accepted by DAS `searchAssets` implementations
+const MAX_PAGE_SIZE: u32 = 1000;
+
+/// Minimal client for the Digital Asset Standard (DAS) read API
+#[allow(clippy::module_name_repetitions)]
+pub struct DasClient {
+    http: reqwest::Client,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct JsonRpcRequest<'a, P> {
+    jsonrpc: &'static str,
+    id: &'static str,
+    method: &'a str,
+    params: P,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse<T> {
+    result: Option<T>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchAssets {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    creator_address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grouping: Option<(&'static str, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tree: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compressed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    condition_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort_by: Option<AssetSorting>,
+    limit: u32,
+    page: u32,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AssetSorting {
+    sort_by: &'static str,
+    sort_direction: &'static str,
+}
+
+#[derive(Deserialize)]
+struct AssetList {
+    #[serde(default)]
+    items: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    id: String,
+    #[serde(default)]
+    content: Option<AssetContent>,
+    #[serde(default)]
+    compression: Option<AssetCompression>,
+    ownership: AssetOwnership,
+    #[serde(default)]
+    burnt: bool,
+}
+
+#[derive(Deserialize)]
+struct AssetContent {
+    #[serde(default)]
+    json_uri: String,
+    #[serde(default)]
+    metadata: Option<AssetMetadata>,
+}
+
+#[derive(Deserialize)]
+struct AssetMetadata {
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct AssetCompression {
+    #[serde(default)]
+    compressed: bool,
+    #[serde(default)]
+    tree: String,
+    #[serde(default)]
+    leaf_id: u64,
+    #[serde(default)]
+    data_hash: String,
+    #[serde(default)]
+    creator_hash: String,
+}
+
+#[derive(Deserialize)]
+struct AssetOwnership {
+    owner: String,
+    #[serde(default)]
+    delegate: Option<String>,
+}
+
+/// A single row of `list-assets` output
+#[derive(Serialize)]
+struct AssetRow {
+    id: String,
+    tree: Option<String>,
+    leaf_index: Option<u64>,
+    name: String,
+    uri: String,
+    owner: String,
+    delegate: Option<String>,
+    compressed: bool,
+    burnt: bool,
+}
+
+impl From<Asset> for AssetRow {
+    fn from(asset: Asset) -> Self {
+        let Asset {
+            id,
+            content,
+            compression,
+            ownership: AssetOwnership { owner, delegate },
+            burnt,
+        } = asset;
+
+        let (name, uri) = content.map_or_else(Default::default, |c| {
+            (c.metadata.map(|m| m.name).unwrap_or_default(), c.json_uri)
+        });
+
+        let compression = compression.filter(|c| c.compressed);
+
+        Self {
+            id,
+            tree: compression.as_ref().map(|c| c.tree.clone()),
+            leaf_index: compression.as_ref().map(|c| c.leaf_id),
+            name,
+            uri,
+            owner,
+            delegate,
+            compressed: compression.is_some(),
+            burnt,
+        }
+    }
+}
+
+impl DasClient {
+    #[inline]
+    #[must_use]
+    pub fn new(url: String) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    async fn call<P: Serialize, T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: P,
+    ) -> Result<T> {
+        let res: JsonRpcResponse<T> = self
+            .http
+            .post(&self.url)
+            .json(&JsonRpcRequest {
+                jsonrpc: "2.0",
+                id: "arborist",
+                method,
+                params,
+            })
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .with_context(|| format!("Error sending {method} request to {}", self.url))?
+            .json()
+            .await
+            .with_context(|| format!("Error parsing {method} response"))?;
+
+        match res {
+            JsonRpcResponse {
+                error: Some(JsonRpcError { code, message }),
+                ..
+            } => Err(anyhow!(
+                "DAS API returned error {code} for {method}: {message}"
+            ))
+            .code(ErrorCode::Das),
+            JsonRpcResponse {
+                result: Some(r), ..
+            } => Ok(r),
+            JsonRpcResponse { result: None, .. } => {
+                Err(anyhow!("DAS API returned no result for {method}")).code(ErrorCode::Das)
+            },
+        }
+    }
+
+    async fn search_assets(&self, params: SearchAssets) -> Result<AssetList> {
+        self.call("searchAssets", params).await
+    }
+}
+
+/// Result of the `list-assets` command
+#[derive(Serialize)]
+#[serde(transparent)]
+pub struct AssetRows(Vec<AssetRow>);
+
+impl Output for AssetRows {
+    fn write_text(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        output::write_table(
+            [
+                "ID",
+                "TREE",
+                "LEAF",
+                "NAME",
+                "URI",
+                "OWNER",
+                "DELEGATE",
+                "COMPRESSED",
+                "BURNT",
+            ],
+            self.0.iter().map(|r| {
+                [
+                    r.id.clone(),
+                    r.tree.clone().unwrap_or_default(),
+                    r.leaf_index.map(|i| i.to_string()).unwrap_or_default(),
+                    r.name.clone(),
+                    r.uri.clone(),
+                    r.owner.clone(),
+                    r.delegate.clone().unwrap_or_default(),
+                    r.compressed.to_string(),
+                    r.burnt.to_string(),
+                ]
+            }),
+            out,
+        )
+    }
+
+    fn write_csv(&self, out: &mut dyn Write) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(out);
+
+        for row in &self.0 {
+            csv.serialize(row)
+                .context("Error serializing assets to CSV")?;
+        }
+
+        csv.flush().context("Error writing CSV output")
+    }
+}
+
+pub async fn list_assets(
+    das: &DasClient,
+    project: &Project,
+    args: ListAssets,
+) -> Result<AssetRows> {
+    let ListAssets {
+        owner,
+        tree,
+        collection,
+        creator,
+        include_uncompressed,
+        page,
+        limit,
+        all,
+        sort,
+        direction,
+    } = args;
+    let tree = tree.map(|t| project.tree(&t)).transpose()?;
+
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(anyhow!(
+            "Invalid page size {limit} - must be between 1 and {MAX_PAGE_SIZE}"
+        ))
+        .code(ErrorCode::InvalidArgument);
+    }
+
+    if page == 0 {
+        return Err(anyhow!(
+            "Invalid page number 0 - pages are numbered starting from 1"
+        ))
+        .code(ErrorCode::InvalidArgument);
+    }
+
+    let sort_by = sort.map(|s| AssetSorting {
+        sort_by: match s {
+            ListAssetsSort::Created => "created",
+            ListAssetsSort::Updated => "updated",
+            ListAssetsSort::RecentAction => "recent_action",
+            ListAssetsSort::None => "none",
+        },
+        sort_direction: match direction {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        },
+    });
+
+    let mut rows = vec![];
+    for page in page.. {
+        let AssetList { items } = das
+            .search_assets(SearchAssets {
+                owner_address: owner.as_ref().map(Pubkey::to_string),
+                creator_address: creator.as_ref().map(Pubkey::to_string),
+                grouping: collection.map(|c| ("collection", c.to_string())),
+                tree: tree.as_ref().map(Pubkey::to_string),
+                compressed: (!include_uncompressed).then_some(true),
+                condition_type: Some("all"),
+                sort_by,
+                limit,
+                page,
+            })
+            .await
+            .with_context(|| format!("Error fetching page {page} of assets"))?;
+
+        let done = !all || items.len() < usize::try_from(limit).unwrap_or(usize::MAX);
+        rows.extend(items.into_iter().map(AssetRow::from));
+
+        if done {
+            break;
+        }
+    }
+
+    Ok(AssetRows(rows))
+}
+
+/// Decode a base58-encoded hash returned by the DAS API
+fn parse_hash(hash: &str) -> Result<[u8; 32]> {
+    let mut out = [0; 32];
+    let len = bs58::decode(hash)
+        .onto(&mut out)
+        .with_context(|| format!("Invalid hash {hash:?}"))?;
+
+    if len != out.len() {
+        return Err(anyhow!("Invalid hash {hash:?}"));
+    }
+
+    Ok(out)
+}
+
+/// Reconstruct the leaf storing a compressed asset
+fn asset_leaf(merkle_tree: &Pubkey, asset: Asset) -> Result<LeafState> {
+    let Asset {
+        id,
+        compression,
+        ownership: AssetOwnership { owner, delegate },
+        ..
+    } = asset;
+    let AssetCompression {
+        leaf_id,
+        data_hash,
+        creator_hash,
+        ..
+    } = compression.with_context(|| format!("Asset {id} has no compression info"))?;
+
+    let owner: Pubkey = owner
+        .parse()
+        .with_context(|| format!("Invalid owner for asset {id}"))?;
+    let delegate = delegate
+        .map(|d| d.parse())
+        .transpose()
+        .with_context(|| format!("Invalid delegate for asset {id}"))?
+        .unwrap_or(owner);
+    let index = u32::try_from(leaf_id)
+        .with_context(|| format!("Leaf index {leaf_id} of asset {id} is out of range"))?;
+
+    let leaf = LeafState::new(
+        merkle_tree,
+        index,
+        leaf_id,
+        owner,
+        delegate,
+        parse_hash(&data_hash).with_context(|| format!("Invalid data hash for asset {id}"))?,
+        parse_hash(&creator_hash)
+            .with_context(|| format!("Invalid creator hash for asset {id}"))?,
+    );
+
+    if leaf.asset_id.to_string() != id {
+        return Err(anyhow!(
+            "Asset {id} does not match the ID derived from its leaf, {}",
+            leaf.asset_id
+        ));
+    }
+
+    Ok(leaf)
+}
+
+/// Fetch every unburnt asset in a tree from the DAS index, ordered by leaf
+/// index
+pub async fn tree_leaves(das: &DasClient, merkle_tree: &Pubkey) -> Result<Vec<LeafState>> {
+    let mut leaves = vec![];
+
+    for page in 1.. {
+        let AssetList { items } = das
+            .search_assets(SearchAssets {
+                owner_address: None,
+                creator_address: None,
+                grouping: None,
+                tree: Some(merkle_tree.to_string()),
+                compressed: Some(true),
+                condition_type: None,
+                sort_by: None,
+                limit: MAX_PAGE_SIZE,
+                page,
+            })
+            .await
+            .with_context(|| format!("Error fetching page {page} of leaves"))?;
+
+        let done = items.len() < MAX_PAGE_SIZE as usize;
+        for asset in items.into_iter().filter(|a| !a.burnt) {
+            leaves.push(
+                asset_leaf(merkle_tree, asset)
+                    .context("Invalid asset returned by DAS API")
+                    .code(ErrorCode::Das)?,
+            );
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    leaves.sort_by_key(|l| l.index);
+    Ok(leaves)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+    };
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::test_support::{block_on, TempDir};
+
+    const TREE: &str = "8cS8HnB1o8nYdHM9QDHyUQdYmA2cN7mX2pNX6bq6jRdE";
+    const OWNER: &str = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin";
+    const DATA_HASH: &str = "US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx";
+    const CREATOR_HASH: &str = "cGfHiC6Kgg3FpFZvgwGcswsCRtp4aBP2fzuXRQPizuN";
+
+    /// A `searchAssets` response recorded from a DAS provider for a wallet
+    /// holding one compressed and one uncompressed asset
+    const SEARCH_ASSETS: &str = r#"{
+        "jsonrpc": "2.0",
+        "result": {
+            "total": 2,
+            "limit": 100,
+            "page": 1,
+            "items": [
+                {
+                    "interface": "V1_NFT",
+                    "id": "5T6tJApHwCQ6Jay6ryLBECmHGk2U8g5BuFHCPYtbVpRT",
+                    "content": {
+                        "$schema": "https://schema.metaplex.com/nft1.0.json",
+                        "json_uri": "https://arweave.net/sapling.json",
+                        "files": [],
+                        "metadata": { "name": "Sapling #1", "symbol": "SAP" },
+                        "links": {}
+                    },
+                    "authorities": [],
+                    "compression": {
+                        "eligible": false,
+                        "compressed": true,
+                        "data_hash": "US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx",
+                        "creator_hash": "cGfHiC6Kgg3FpFZvgwGcswsCRtp4aBP2fzuXRQPizuN",
+                        "asset_hash": "",
+                        "tree": "8cS8HnB1o8nYdHM9QDHyUQdYmA2cN7mX2pNX6bq6jRdE",
+                        "seq": 2,
+                        "leaf_id": 1
+                    },
+                    "grouping": [],
+                    "royalty": { "basis_points": 0, "primary_sale_happened": false },
+                    "creators": [],
+                    "ownership": {
+                        "frozen": false,
+                        "delegated": true,
+                        "delegate": "HgTb4faGAVqzeU4aTMYYZHv13UnceyhED16fDYRXi3wi",
+                        "ownership_model": "single",
+                        "owner": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"
+                    },
+                    "mutable": true,
+                    "burnt": false
+                },
+                {
+                    "interface": "ProgrammableNFT",
+                    "id": "DobZRTWvKB2kM2jwjXEvdxxp2PukVxZ1SdkGp7u3YYbW",
+                    "content": {
+                        "json_uri": "https://arweave.net/oak.json",
+                        "metadata": { "name": "Oak" }
+                    },
+                    "compression": {
+                        "eligible": false,
+                        "compressed": false,
+                        "data_hash": "",
+                        "creator_hash": "",
+                        "asset_hash": "",
+                        "tree": "",
+                        "seq": 0,
+                        "leaf_id": 0
+                    },
+                    "ownership": {
+                        "frozen": false,
+                        "delegated": false,
+                        "delegate": null,
+                        "ownership_model": "single",
+                        "owner": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"
+                    },
+                    "burnt": false
+                }
+            ]
+        },
+        "id": "arborist"
+    }"#;
+
+    /// Serve JSON-RPC responses over HTTP, answering the `n`th request with
+    /// `respond(n, params)` and recording the params of every request
+    fn das_stand_in(
+        respond: impl Fn(usize, &Value) -> String + Send + 'static,
+    ) -> (DasClient, Arc<Mutex<Vec<Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+        let recorded = Arc::clone(&requests);
+
+        std::thread::spawn(move || {
+            for (n, stream) in listener.incoming().enumerate() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let mut len = 0;
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).unwrap();
+                    let line = line.trim_end().to_ascii_lowercase();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(l) = line.strip_prefix("content-length:") {
+                        len = l.trim().parse().unwrap();
+                    }
+                }
+
+                let mut body = vec![0; len];
+                stream.read_exact(&mut body).unwrap();
+                let req: Value = serde_json::from_slice(&body).unwrap();
+                let res = respond(n, &req["params"]);
+                recorded.lock().unwrap().push(req["params"].clone());
+
+                write!(
+                    stream.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: \
+                     {}\r\nConnection: close\r\n\r\n{res}",
+                    res.len(),
+                )
+                .unwrap();
+            }
+        });
+
+        (DasClient::new(url), requests)
+    }
+
+    /// A compressed asset in [`TREE`] as returned by `searchAssets`
+    fn asset(index: u32, burnt: bool) -> Value {
+        let tree: Pubkey = TREE.parse().unwrap();
+
+        json!({
+            "id": arborist::hash::asset_id(&tree, index.into()).to_string(),
+            "compression": {
+                "compressed": true,
+                "tree": TREE,
+                "leaf_id": index,
+                "data_hash": DATA_HASH,
+                "creator_hash": CREATOR_HASH,
+            },
+            "ownership": { "owner": OWNER, "delegate": null },
+            "burnt": burnt,
+        })
+    }
+
+    fn page(items: impl IntoIterator<Item = Value>) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "result": { "items": items.into_iter().collect::<Vec<_>>() },
+            "id": "arborist",
+        })
+        .to_string()
+    }
+
+    fn list_args(limit: u32, all: bool) -> ListAssets {
+        ListAssets {
+            owner: Some(OWNER.parse().unwrap()),
+            tree: None,
+            collection: None,
+            creator: None,
+            include_uncompressed: true,
+            page: 1,
+            limit,
+            all,
+            sort: Some(ListAssetsSort::Created),
+            direction: SortDirection::Desc,
+        }
+    }
+
+    #[test]
+    fn decodes_search_assets_response() {
+        let (das, requests) = das_stand_in(|_, _| SEARCH_ASSETS.into());
+        let dir = TempDir::new("das");
+
+        let AssetRows(rows) =
+            block_on(list_assets(&das, &dir.project(), list_args(100, false))).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].id, "5T6tJApHwCQ6Jay6ryLBECmHGk2U8g5BuFHCPYtbVpRT");
+        assert_eq!(rows[0].tree.as_deref(), Some(TREE));
+        assert_eq!(rows[0].leaf_index, Some(1));
+        assert_eq!(rows[0].name, "Sapling #1");
+        assert_eq!(rows[0].uri, "https://arweave.net/sapling.json");
+        assert_eq!(rows[0].owner, OWNER);
+        assert_eq!(
+            rows[0].delegate.as_deref(),
+            Some("HgTb4faGAVqzeU4aTMYYZHv13UnceyhED16fDYRXi3wi")
+        );
+        assert!(rows[0].compressed);
+        assert_eq!(rows[1].name, "Oak");
+        assert_eq!(rows[1].tree, None);
+        assert_eq!(rows[1].leaf_index, None);
+        assert!(!rows[1].compressed);
+
+        assert_eq!(requests.lock().unwrap()[..], [json!({
+            "ownerAddress": OWNER,
+            "conditionType": "all",
+            "sortBy": { "sortBy": "created", "sortDirection": "desc" },
+            "limit": 100,
+            "page": 1,
+        })]);
+    }
+
+    #[test]
+    fn lists_every_page() {
+        let (das, requests) = das_stand_in(|n, _| {
+            page(match n {
+                0 => vec![asset(0, false), asset(1, false)],
+                _ => vec![asset(2, false)],
+            })
+        });
+        let dir = TempDir::new("das");
+
+        let AssetRows(rows) =
+            block_on(list_assets(&das, &dir.project(), list_args(2, true))).unwrap();
+
+        assert_eq!(
+            rows.iter().map(|r| r.leaf_index).collect::<Vec<_>>(),
+            [Some(0), Some(1), Some(2)]
+        );
+        let pages = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|r| r["page"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(pages, [json!(1), json!(2)]);
+    }
+
+    #[test]
+    fn tree_leaves_stops_at_short_page() {
+        let (das, requests) = das_stand_in(|n, params| {
+            assert_eq!(params["tree"], TREE);
+            assert_eq!(params["compressed"], true);
+
+            page(match n {
+                0 => (0..MAX_PAGE_SIZE).rev().map(|i| asset(i, i == 7)).collect(),
+                _ => vec![asset(MAX_PAGE_SIZE, false)],
+            })
+        });
+
+        let leaves = block_on(tree_leaves(&das, &TREE.parse().unwrap())).unwrap();
+
+        assert_eq!(requests.lock().unwrap().len(), 2);
+        assert_eq!(leaves.len(), usize::try_from(MAX_PAGE_SIZE).unwrap());
+        assert!(leaves.windows(2).all(|w| w[0].index < w[1].index));
+        assert!(leaves.iter().all(|l| l.index != 7));
+        assert_eq!(leaves.last().unwrap().index, MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn reports_das_errors() {
+        let (das, _) = das_stand_in(|_, _| {
+            json!({
+                "jsonrpc": "2.0",
+                "error": { "code": -32000, "message": "Database Error: RecordNotFound" },
+                "id": "arborist",
+            })
+            .to_string()
+        });
+
+        let err = block_on(tree_leaves(&das, &TREE.parse().unwrap())).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), ErrorCode::Das);
+        assert!(format!("{err:#}").contains("RecordNotFound"));
+    }
+
+    #[test]
+    fn rebuilds_asset_leaf() {
+        let tree: Pubkey = TREE.parse().unwrap();
+        let owner: Pubkey = OWNER.parse().unwrap();
+        let asset = serde_json::from_value(asset(2, false)).unwrap();
+
+        let leaf = asset_leaf(&tree, asset).unwrap();
+
+        assert_eq!(
+            leaf,
+            LeafState::new(&tree, 2, 2, owner, owner, [7; 32], [9; 32])
+        );
+    }
+
+    #[test]
+    fn rejects_inconsistent_assets() {
+        let tree: Pubkey = TREE.parse().unwrap();
+        let invalid = |f: fn(&mut Value)| {
+            let mut asset = asset(2, false);
+            f(&mut asset);
+            asset_leaf(&tree, serde_json::from_value(asset).unwrap()).unwrap_err()
+        };
+
+        let err = invalid(|a| a["compression"]["leaf_id"] = json!(3));
+        assert!(err.to_string().contains("does not match the ID derived"));
+        let err = invalid(|a| a["compression"] = Value::Null);
+        assert!(err.to_string().contains("has no compression info"));
+        let err = invalid(|a| a["compression"]["data_hash"] = json!("3yZe7d"));
+        assert!(err.to_string().contains("Invalid data hash"));
+        let err = invalid(|a| a["ownership"]["owner"] = json!("not-a-pubkey"));
+        assert!(err.to_string().contains("Invalid owner"));
+    }
+}
@@ -0,0 +1,286 @@
+use std::{io::Write, path::Path, str::FromStr};
+
+use anyhow::{anyhow, Context, Result};
+use arborist::{
+    chain::{ChainClient, ConfirmedTransaction},
+    client::SolanaClient,
+    decode::{decode_instructions, DecodedInstruction},
+    hash::asset_id,
+};
+use serde::Serialize;
+use solana_sdk::signature::Signature;
+
+use crate::{
+    cli::DecodeTx,
+    output::{ErrorCode, Output, ResultExt},
+};
+
+/// A decoded instruction, with the leaf it changed and the tree's new root
+#[derive(Debug, Serialize)]
+pub struct InstructionRow {
+    pub index: usize,
+    pub instruction: &'static str,
+    pub inner: bool,
+    pub merkle_tree: Option<String>,
+    pub asset_id: Option<String>,
+    pub leaf_index: Option<u32>,
+    pub old_owner: Option<String>,
+    pub new_owner: Option<String>,
+    pub new_delegate: Option<String>,
+    pub sequence_number: Option<u64>,
+    pub new_root: Option<String>,
+}
+
+impl From<&DecodedInstruction> for InstructionRow {
+    fn from(ix: &DecodedInstruction) -> Self {
+        let leaf = ix.leaf_schema();
+        let change_log = ix.change_log();
+
+        // Burns log no leaf, but the asset can still be derived from the
+        // instruction's arguments
+        let asset = leaf.map(|l| l.asset_id).or_else(|| {
+            ix.merkle_tree
+                .zip(ix.nonce)
+                .map(|(tree, nonce)| asset_id(&tree, nonce))
+        });
+
+        Self {
+            index: ix.index,
+            instruction: ix.kind.name(),
+            inner: ix.inner,
+            merkle_tree: ix
+                .merkle_tree
+                .or(change_log.map(|c| c.merkle_tree))
+                .map(|t| t.to_string()),
+            asset_id: asset.map(|a| a.to_string()),
+            leaf_index: change_log.map(|c| c.leaf_index).or(ix.leaf_index),
+            old_owner: ix.leaf_owner.map(|o| o.to_string()),
+            new_owner: leaf.map(|l| l.owner.to_string()),
+            new_delegate: leaf.map(|l| l.delegate.to_string()),
+            sequence_number: change_log.map(|c| c.sequence_number),
+            new_root: change_log.map(|c| bs58::encode(c.root).into_string()),
+        }
+    }
+}
+
+impl InstructionRow {
+    /// Write the fields of this row that are set, one per line
+    pub fn write_fields(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        let fields = [
+            ("Tree", self.merkle_tree.clone()),
+            ("Asset", self.asset_id.clone()),
+            ("Leaf index", self.leaf_index.map(|i| i.to_string())),
+            ("Old owner", self.old_owner.clone()),
+            ("New owner", self.new_owner.clone()),
+            ("New delegate", self.new_delegate.clone()),
+            (
+                "Sequence number",
+                self.sequence_number.map(|s| s.to_string()),
+            ),
+            ("New root", self.new_root.clone()),
+        ];
+
+        for (name, value) in fields {
+            if let Some(value) = value {
+                writeln!(out, "    {name}: {value}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Result of the `decode-tx` command
+#[derive(Debug, Serialize)]
+pub struct DecodeTxResult {
+    signature: String,
+    slot: u64,
+    block_time: Option<i64>,
+    error: Option<String>,
+    instructions: Vec<InstructionRow>,
+}
+
+impl Output for DecodeTxResult {
+    fn write_text(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(out, "Signature: {}", self.signature)?;
+        writeln!(out, "Slot: {}", self.slot)?;
+        match &self.error {
+            Some(e) => writeln!(out, "Status: failed: {e}")?,
+            None => writeln!(out, "Status: succeeded")?,
+        }
+
+        if self.instructions.is_empty() {
+            return writeln!(
+                out,
+                "No Bubblegum or account compression instructions found"
+            );
+        }
+
+        for row in &self.instructions {
+            let cpi = if row.inner { " (CPI)" } else { "" };
+            writeln!(out, "[{}] {}{cpi}", row.index, row.instruction)?;
+            row.write_fields(out)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_csv(&self, out: &mut dyn Write) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(out);
+
+        for row in &self.instructions {
+            csv.serialize(row)
+                .context("Error serializing instructions to CSV")?;
+        }
+
+        csv.flush().context("Error writing CSV output")
+    }
+}
+
+/// Fetch a transaction by signature, or read it from a JSON file if the
+/// argument is not a signature
+async fn load_transaction<C: ChainClient>(
+    client: &SolanaClient<C>,
+    transaction: &str,
+) -> Result<ConfirmedTransaction> {
+    if let Ok(signature) = Signature::from_str(transaction) {
+        return client
+            .transaction(&signature)
+            .await
+            .with_context(|| format!("Error getting transaction {signature}"))
+            .code(ErrorCode::Rpc)?
+            .with_context(|| format!("Transaction {signature} not found"))
+            .code(ErrorCode::InvalidArgument);
+    }
+
+    let path = Path::new(transaction);
+    if !path.exists() {
+        return Err(anyhow!(
+            "{transaction:?} is neither a transaction signature nor a file"
+        ))
+        .code(ErrorCode::InvalidArgument);
+    }
+
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Error reading {}", path.display()))
+        .code(ErrorCode::Io)?;
+
+    ConfirmedTransaction::from_json(&json)
+        .with_context(|| format!("Error parsing transaction from {}", path.display()))
+        .code(ErrorCode::InvalidArgument)
+}
+
+pub async fn decode_tx<C: ChainClient>(
+    client: &SolanaClient<C>,
+    args: DecodeTx,
+) -> Result<DecodeTxResult> {
+    let DecodeTx { transaction } = args;
+
+    let txn = load_transaction(client, &transaction).await?;
+
+    Ok(DecodeTxResult {
+        signature: txn
+            .transaction
+            .signatures
+            .first()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+        slot: txn.slot,
+        block_time: txn.block_time,
+        error: txn.err.as_ref().map(ToString::to_string),
+        instructions: decode_instructions(&txn)
+            .iter()
+            .map(InstructionRow::from)
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use arborist::chain::MockChain;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::test_support::{
+        block_on, confirmed_transaction, leaf_events, mint_instruction, TempDir,
+    };
+
+    /// Encode a transaction as a `getTransaction` response
+    fn to_json(txn: &ConfirmedTransaction) -> String {
+        let inner = txn.inner_instructions[0]
+            .instructions
+            .iter()
+            .map(|ix| {
+                serde_json::json!({
+                    "programIdIndex": ix.program_id_index,
+                    "accounts": ix.accounts,
+                    "data": bs58::encode(&ix.data).into_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "slot": txn.slot,
+                "blockTime": txn.block_time,
+                "transaction": [
+                    base64::encode(bincode::serialize(&txn.transaction).unwrap()),
+                    "base64",
+                ],
+                "meta": {
+                    "err": null,
+                    "status": { "Ok": null },
+                    "fee": 5000,
+                    "preBalances": [],
+                    "postBalances": [],
+                    "innerInstructions": [{ "index": 0, "instructions": inner }],
+                    "logMessages": [],
+                },
+            },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn decodes_fixture() {
+        let merkle_tree = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let dir = TempDir::new("decode");
+        let file = dir.path().join("transaction.json");
+        let txn = confirmed_transaction(
+            Signature::new_unique(),
+            42,
+            mint_instruction(&merkle_tree, &owner),
+            &owner,
+            &leaf_events(&merkle_tree, 3, &owner, 4),
+        );
+        std::fs::write(&file, to_json(&txn)).unwrap();
+
+        let client = SolanaClient::new(MockChain::new(), false);
+        let result = block_on(decode_tx(&client, DecodeTx {
+            transaction: file.display().to_string(),
+        }))
+        .unwrap();
+
+        assert_eq!(result.slot, 42);
+        assert_eq!(result.error, None);
+        assert_eq!(result.instructions.len(), 1);
+
+        let mint = &result.instructions[0];
+        assert_eq!(mint.instruction, "MintV1");
+        assert_eq!(mint.merkle_tree, Some(merkle_tree.to_string()));
+        assert_eq!(mint.asset_id, Some(asset_id(&merkle_tree, 3).to_string()));
+        assert_eq!(mint.leaf_index, Some(3));
+        assert_eq!(mint.old_owner, None);
+        assert_eq!(mint.new_owner, Some(owner.to_string()));
+        assert_eq!(mint.sequence_number, Some(4));
+        assert_eq!(mint.new_root, Some(bs58::encode([4; 32]).into_string()));
+
+        assert!(block_on(decode_tx(&client, DecodeTx {
+            transaction: "not-a-file".into(),
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use arborist::{
+    chain::ChainClient,
+    client::SolanaClient,
+    decode::{decode_instructions, InstructionKind},
+};
+use serde::Serialize;
+use solana_sdk::clock::UnixTimestamp;
+
+use crate::{
+    cli::History,
+    decode::InstructionRow,
+    output::{self, ErrorCode, Output, ResultExt},
+    project::Project,
+};
+
+/// Maximum number of signatures returned by a single
+/// `getSignaturesForAddress` request
+const SIGNATURE_PAGE_SIZE: usize = 1000;
+
+/// Group Bubblegum instructions into the actions reported by `history`,
+/// returning `None` for account compression instructions
+fn action(kind: InstructionKind) -> Option<&'static str> {
+    Some(match kind {
+        InstructionKind::MintV1 | InstructionKind::MintToCollectionV1 => "mint",
+        InstructionKind::Transfer => "transfer",
+        InstructionKind::Burn => "burn",
+        InstructionKind::Delegate => "delegate",
+        InstructionKind::Redeem => "redeem",
+        InstructionKind::CancelRedeem => "cancel-redeem",
+        InstructionKind::VerifyCreator
+        | InstructionKind::VerifyCollection
+        | InstructionKind::SetAndVerifyCollection => "verify",
+        InstructionKind::UnverifyCreator | InstructionKind::UnverifyCollection => "unverify",
+        InstructionKind::DecompressV1 => "decompress",
+        InstructionKind::Compress => "compress",
+        InstructionKind::CreateTree => "create-tree",
+        InstructionKind::SetTreeDelegate => "set-tree-delegate",
+        InstructionKind::SetDecompressibleState => "set-decompressible",
+        InstructionKind::InitEmptyMerkleTree
+        | InstructionKind::ReplaceLeaf
+        | InstructionKind::TransferAuthority
+        | InstructionKind::VerifyLeaf
+        | InstructionKind::Append
+        | InstructionKind::InsertOrAppend
+        | InstructionKind::CloseEmptyTree => return None,
+    })
+}
+
+/// A single action in the output of `history`
+#[derive(Debug, Serialize)]
+struct HistoryRow {
+    slot: u64,
+    block_time: Option<UnixTimestamp>,
+    signature: String,
+    action: &'static str,
+    instruction: &'static str,
+    asset_id: Option<String>,
+    leaf_index: Option<u32>,
+    old_owner: Option<String>,
+    new_owner: Option<String>,
+    new_delegate: Option<String>,
+    sequence_number: Option<u64>,
+    new_root: Option<String>,
+}
+
+/// Result of the `history` command
+#[derive(Debug, Serialize)]
+pub struct HistoryResult {
+    merkle_tree: String,
+    asset: Option<String>,
+    actions: Vec<HistoryRow>,
+}
+
+impl Output for HistoryResult {
+    fn write_text(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        match &self.asset {
+            Some(a) => writeln!(out, "Provenance of {a} in {}", self.merkle_tree)?,
+            None => writeln!(out, "History of {}", self.merkle_tree)?,
+        }
+
+        if self.actions.is_empty() {
+            return writeln!(out, "No Bubblegum actions found");
+        }
+
+        writeln!(out)?;
+        output::write_table(
+            [
+                "SLOT",
+                "SIGNATURE",
+                "ACTION",
+                "ASSET",
+                "LEAF",
+                "OLD OWNER",
+                "NEW OWNER",
+            ],
+            self.actions.iter().map(|r| {
+                [
+                    r.slot.to_string(),
+                    r.signature.clone(),
+                    r.action.to_owned(),
+                    r.asset_id.clone().unwrap_or_default(),
+                    r.leaf_index.map(|i| i.to_string()).unwrap_or_default(),
+                    r.old_owner.clone().unwrap_or_default(),
+                    r.new_owner.clone().unwrap_or_default(),
+                ]
+            }),
+            out,
+        )?;
+
+        if self.asset.is_some() {
+            writeln!(out)?;
+            match self.actions.last() {
+                Some(r) if matches!(r.action, "burn" | "redeem") => {
+                    writeln!(out, "Current owner: none ({})", r.action)?;
+                },
+                Some(HistoryRow {
+                    new_owner: Some(owner),
+                    ..
+                }) => writeln!(out, "Current owner: {owner}")?,
+                _ => writeln!(out, "Current owner: unknown")?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_csv(&self, out: &mut dyn Write) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(out);
+
+        for row in &self.actions {
+            csv.serialize(row)
+                .context("Error serializing history to CSV")?;
+        }
+
+        csv.flush().context("Error writing CSV output")
+    }
+}
+
+pub async fn history<C: ChainClient>(
+    client: &SolanaClient<C>,
+    project: &Project,
+    args: History,
+) -> Result<HistoryResult> {
+    let History {
+        merkle_tree,
+        asset,
+        since,
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+    let since = since.unwrap_or(0);
+
+    // Signatures are returned newest first, so paging can stop as soon as a
+    // page reaches back before the requested slot
+    let mut signatures = vec![];
+    let mut before = None;
+    loop {
+        let page = client
+            .signatures_for_address(&merkle_tree, before, SIGNATURE_PAGE_SIZE)
+            .await
+            .with_context(|| format!("Error getting signatures for {merkle_tree}"))
+            .code(ErrorCode::Rpc)?;
+
+        let done = page.len() < SIGNATURE_PAGE_SIZE || page.last().is_none_or(|s| s.slot < since);
+        before = page.last().map(|s| s.signature);
+        signatures.extend(page.into_iter().filter(|s| s.slot >= since));
+
+        if done {
+            break;
+        }
+    }
+
+    let asset = asset.map(|a| a.to_string());
+    let mut actions = vec![];
+
+    // Failed transactions changed nothing, so they are left out of the log
+    for info in signatures.into_iter().rev().filter(|s| s.err.is_none()) {
+        let txn = client
+            .transaction(&info.signature)
+            .await
+            .with_context(|| format!("Error getting transaction {}", info.signature))
+            .code(ErrorCode::Rpc)?
+            .with_context(|| format!("Transaction {} not found", info.signature))
+            .code(ErrorCode::Rpc)?;
+
+        for ix in decode_instructions(&txn)
+            .iter()
+            .filter(|i| i.merkle_tree == Some(merkle_tree))
+        {
+            let Some(action) = action(ix.kind) else {
+                continue;
+            };
+            let InstructionRow {
+                instruction,
+                asset_id,
+                leaf_index,
+                old_owner,
+                new_owner,
+                new_delegate,
+                sequence_number,
+                new_root,
+                ..
+            } = InstructionRow::from(ix);
+
+            if asset.is_some() && asset_id != asset {
+                continue;
+            }
+
+            actions.push(HistoryRow {
+                slot: txn.slot,
+                block_time: txn.block_time,
+                signature: info.signature.to_string(),
+                action,
+                instruction,
+                asset_id,
+                leaf_index,
+                old_owner,
+                new_owner,
+                new_delegate,
+                sequence_number,
+                new_root,
+            });
+        }
+    }
+
+    Ok(HistoryResult {
+        merkle_tree: merkle_tree.to_string(),
+        asset,
+        actions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use arborist::{
+        chain::{ConfirmedTransaction, MockChain},
+        hash::asset_id,
+        instructions::{self, Leaf, LeafAuthority},
+    };
+    use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::TransactionError};
+
+    use super::*;
+    use crate::{
+        cli::TreeArg,
+        test_support::{block_on, confirmed_transaction, leaf_events, mint_instruction, TempDir},
+    };
+
+    #[test]
+    fn traces_asset_provenance() {
+        let merkle_tree = Pubkey::new_unique();
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let carol = Pubkey::new_unique();
+        let transfer = |from: &Pubkey, to: &Pubkey| {
+            instructions::transfer(
+                &merkle_tree,
+                from,
+                from,
+                LeafAuthority::Owner,
+                to,
+                &Leaf {
+                    root: [0; 32],
+                    data_hash: [1; 32],
+                    creator_hash: [2; 32],
+                    nonce: 0,
+                    index: 0,
+                },
+                &[],
+            )
+        };
+
+        let chain = MockChain::new();
+        let mint = |nonce, owner: &Pubkey, slot| {
+            confirmed_transaction(
+                Signature::new_unique(),
+                slot,
+                mint_instruction(&merkle_tree, owner),
+                owner,
+                &leaf_events(&merkle_tree, nonce, owner, u8::try_from(slot).unwrap()),
+            )
+        };
+        chain.add_transaction(mint(0, &alice, 10));
+        chain.add_transaction(mint(1, &carol, 11));
+        chain.add_transaction(confirmed_transaction(
+            Signature::new_unique(),
+            12,
+            transfer(&alice, &bob),
+            &alice,
+            &leaf_events(&merkle_tree, 0, &bob, 12),
+        ));
+        chain.add_transaction(ConfirmedTransaction {
+            err: Some(TransactionError::AccountInUse),
+            ..confirmed_transaction(
+                Signature::new_unique(),
+                13,
+                transfer(&bob, &carol),
+                &bob,
+                &[],
+            )
+        });
+
+        let client = SolanaClient::new(chain, false);
+        let dir = TempDir::new("history");
+        let project = dir.project();
+        let run = |asset, since| {
+            block_on(history(&client, &project, History {
+                merkle_tree: TreeArg::Address(merkle_tree),
+                asset,
+                since,
+            }))
+            .unwrap()
+        };
+
+        let all = run(None, None);
+        assert_eq!(
+            all.actions
+                .iter()
+                .map(|a| (a.slot, a.action))
+                .collect::<Vec<_>>(),
+            [(10, "mint"), (11, "mint"), (12, "transfer")]
+        );
+
+        let asset = asset_id(&merkle_tree, 0);
+        let provenance = run(Some(asset), None);
+        assert_eq!(
+            provenance
+                .actions
+                .iter()
+                .map(|a| (a.action, a.old_owner.clone(), a.new_owner.clone()))
+                .collect::<Vec<_>>(),
+            [
+                ("mint", None, Some(alice.to_string())),
+                ("transfer", Some(alice.to_string()), Some(bob.to_string())),
+            ]
+        );
+        assert_eq!(provenance.actions[1].sequence_number, Some(12));
+        assert!(provenance
+            .actions
+            .iter()
+            .all(|a| a.asset_id == Some(asset.to_string())));
+
+        assert_eq!(run(None, Some(11)).actions.len(), 2);
+    }
+}
@@ -0,0 +1,367 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chacha20poly1305::{
+    aead::{Aead, NewAead, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{write_keypair_file, Keypair},
+    signer::Signer,
+};
+
+use crate::{
+    cli::{KeystoreExport, KeystoreImport, KeystoreNew},
+    output::{ErrorCode, Output, ResultExt},
+    signer::{self, SignerArgs},
+};
+
+/// Environment variable read for keystore passphrases before prompting
+pub const PASSPHRASE_ENV: &str = "ARBORIST_KEYSTORE_PASSPHRASE";
+
+const KEYSTORE_VERSION: u8 = 1;
+const SALT_LEN: usize = 32;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+
+/// Parameters of the scrypt key derivation function
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct ScryptParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+impl Default for ScryptParams {
+    fn default() -> Self {
+        Self {
+            log_n: scrypt::Params::RECOMMENDED_LOG_N,
+            r: scrypt::Params::RECOMMENDED_R,
+            p: scrypt::Params::RECOMMENDED_P,
+        }
+    }
+}
+
+/// A keypair encrypted with XChaCha20-Poly1305 under a key derived from a
+/// passphrase using scrypt.  The public key is authenticated as associated
+/// data.
+#[derive(Debug, Serialize, Deserialize)]
+struct Keystore {
+    version: u8,
+    pubkey: String,
+    kdf: ScryptParams,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: ScryptParams) -> Result<[u8; KEY_LEN]> {
+    let ScryptParams { log_n, r, p } = params;
+    let params = scrypt::Params::new(log_n, r, p, KEY_LEN)
+        .map_err(|e| anyhow!("Invalid scrypt parameters: {e}"))?;
+
+    let mut key = [0; KEY_LEN];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|e| anyhow!("Error deriving keystore key: {e}"))?;
+
+    Ok(key)
+}
+
+fn encrypt(keypair: &Keypair, passphrase: &str, kdf: ScryptParams) -> Result<Keystore> {
+    let pubkey = keypair.pubkey();
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let key = derive_key(passphrase, &salt, kdf)?;
+    let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&key))
+        .encrypt(XNonce::from_slice(&nonce), Payload {
+            msg: &keypair.to_bytes(),
+            aad: pubkey.as_ref(),
+        })
+        .map_err(|_| anyhow!("Error encrypting keypair"))?;
+
+    Ok(Keystore {
+        version: KEYSTORE_VERSION,
+        pubkey: pubkey.to_string(),
+        kdf,
+        salt: base64::encode(salt),
+        nonce: base64::encode(nonce),
+        ciphertext: base64::encode(ciphertext),
+    })
+}
+
+fn decrypt(keystore: &Keystore, passphrase: &str) -> Result<Keypair> {
+    let Keystore {
+        version,
+        pubkey,
+        kdf,
+        salt,
+        nonce,
+        ciphertext,
+    } = keystore;
+
+    if *version != KEYSTORE_VERSION {
+        bail!("Unsupported keystore version {version}");
+    }
+
+    let pubkey: Pubkey = pubkey.parse().context("Invalid public key in keystore")?;
+    let salt = base64::decode(salt).context("Invalid salt in keystore")?;
+    let nonce = base64::decode(nonce).context("Invalid nonce in keystore")?;
+    let ciphertext = base64::decode(ciphertext).context("Invalid ciphertext in keystore")?;
+
+    if nonce.len() != NONCE_LEN {
+        bail!("Invalid nonce length in keystore");
+    }
+
+    let key = derive_key(passphrase, &salt, *kdf)?;
+    let bytes = XChaCha20Poly1305::new(Key::from_slice(&key))
+        .decrypt(XNonce::from_slice(&nonce), Payload {
+            msg: &ciphertext,
+            aad: pubkey.as_ref(),
+        })
+        .map_err(|_| anyhow!("Incorrect passphrase or corrupted keystore"))?;
+
+    let keypair = Keypair::from_bytes(&bytes).context("Invalid keypair in keystore")?;
+
+    if keypair.pubkey() != pubkey {
+        bail!("Keystore public key does not match its keypair");
+    }
+
+    Ok(keypair)
+}
+
+fn check_passphrase(passphrase: String) -> Result<String> {
+    if passphrase.is_empty() {
+        bail!("Keystore passphrase must not be empty");
+    }
+
+    Ok(passphrase)
+}
+
+/// Read the keystore passphrase from the environment, or prompt for it
+fn passphrase(keypair_name: &str, confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return check_passphrase(passphrase)
+            .with_context(|| format!("Invalid passphrase in {PASSPHRASE_ENV}"));
+    }
+
+    let passphrase = check_passphrase(
+        rpassword::prompt_password(format!("[{keypair_name}] keystore passphrase: "))
+            .context("Error reading keystore passphrase")?,
+    )?;
+
+    if confirm {
+        let confirmed = rpassword::prompt_password("Enter same passphrase again: ")
+            .context("Error reading keystore passphrase")?;
+
+        if confirmed != passphrase {
+            bail!("Passphrases did not match");
+        }
+    }
+
+    Ok(passphrase)
+}
+
+/// Load and decrypt the keypair stored in a keystore file
+///
+/// # Errors
+/// This function fails if the file cannot be read or parsed, or the
+/// passphrase is incorrect.
+pub fn read_keystore(path: &str, keypair_name: &str) -> Result<Keypair> {
+    let keystore: Keystore = serde_json::from_slice(
+        &fs::read(path).with_context(|| format!("Error reading keystore {path:?}"))?,
+    )
+    .with_context(|| format!("Error parsing keystore {path:?}"))?;
+
+    decrypt(&keystore, &passphrase(keypair_name, false)?)
+        .with_context(|| format!("Error decrypting keystore {path:?}"))
+}
+
+fn write_keystore(keypair: &Keypair, path: &Path, force: bool) -> Result<()> {
+    if !force && path.exists() {
+        return Err(anyhow!(
+            "Refusing to overwrite {} without --force",
+            path.display()
+        ))
+        .code(ErrorCode::InvalidArgument);
+    }
+
+    let keystore = encrypt(
+        keypair,
+        &passphrase("new keystore", true)?,
+        ScryptParams::default(),
+    )?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Error creating directory {}", dir.display()))?;
+    }
+
+    let json = serde_json::to_vec_pretty(&keystore).context("Error serializing keystore")?;
+
+    write_private(path, &json).with_context(|| format!("Error writing keystore {}", path.display()))
+}
+
+/// Write a file readable only by its owner.  Keystores are encrypted, but
+/// there's no reason to make them world-readable.
+#[cfg(unix)]
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::{
+        io::Write,
+        os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    };
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(data)?;
+
+    // The mode above only applies when the file is created, not when an
+    // existing file is overwritten with --force
+    file.set_permissions(fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> { fs::write(path, data) }
+
+/// Result of the `keystore` commands
+#[derive(Debug, Serialize)]
+pub struct KeystoreResult {
+    pubkey: String,
+    path: String,
+}
+
+impl Output for KeystoreResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        writeln!(out, "Wrote keypair for {} to {}", self.pubkey, self.path)
+    }
+}
+
+pub fn new(args: KeystoreNew) -> Result<KeystoreResult> {
+    let KeystoreNew { outfile, force } = args;
+
+    let keypair = Keypair::new();
+    write_keystore(&keypair, &outfile, force)?;
+
+    Ok(KeystoreResult {
+        pubkey: keypair.pubkey().to_string(),
+        path: outfile.display().to_string(),
+    })
+}
+
+pub fn import(signer_args: &SignerArgs, args: KeystoreImport) -> Result<KeystoreResult> {
+    let KeystoreImport {
+        keypair,
+        outfile,
+        force,
+    } = args;
+
+    let keypair = signer::keypair_from_path(signer_args, &keypair, "keypair")
+        .map_err(|e| anyhow!("{e}"))
+        .context("Error loading keypair to import")
+        .code(ErrorCode::Signer)?;
+    write_keystore(&keypair, &outfile, force)?;
+
+    Ok(KeystoreResult {
+        pubkey: keypair.pubkey().to_string(),
+        path: outfile.display().to_string(),
+    })
+}
+
+pub fn export(args: KeystoreExport) -> Result<KeystoreResult> {
+    let KeystoreExport {
+        keystore,
+        outfile,
+        force,
+    } = args;
+
+    if !force && outfile.exists() {
+        return Err(anyhow!(
+            "Refusing to overwrite {} without --force",
+            outfile.display()
+        ))
+        .code(ErrorCode::InvalidArgument);
+    }
+
+    let keypair = read_keystore(&keystore, "keystore").code(ErrorCode::Signer)?;
+    write_keypair_file(&keypair, &outfile)
+        .map_err(|e| anyhow!("{e}"))
+        .with_context(|| format!("Error writing keypair file {}", outfile.display()))
+        .code(ErrorCode::Io)?;
+
+    Ok(KeystoreResult {
+        pubkey: keypair.pubkey().to_string(),
+        path: outfile.display().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    /// Cheap parameters to keep tests fast
+    const TEST_PARAMS: ScryptParams = ScryptParams {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+
+    #[test]
+    fn round_trips_keypair() {
+        let keypair = Keypair::new();
+        let keystore = encrypt(&keypair, "hunter2", TEST_PARAMS).unwrap();
+
+        assert_eq!(keystore.pubkey, keypair.pubkey().to_string());
+        assert_eq!(
+            decrypt(&keystore, "hunter2").unwrap().to_bytes(),
+            keypair.to_bytes()
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_passphrase() {
+        let keystore = encrypt(&Keypair::new(), "hunter2", TEST_PARAMS).unwrap();
+
+        assert!(decrypt(&keystore, "hunter3").is_err());
+    }
+
+    #[test]
+    fn rejects_empty_passphrase() {
+        assert!(check_passphrase(String::new()).is_err());
+        assert_eq!(check_passphrase("hunter2".into()).unwrap(), "hunter2");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restricts_overwritten_keystore() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("keystore");
+        let path = dir.path().join("keystore.json");
+        fs::write(&path, "{}").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private(&path, b"[]").unwrap();
+
+        let meta = fs::metadata(&path).unwrap();
+        assert_eq!(meta.permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read(&path).unwrap(), b"[]");
+    }
+
+    #[test]
+    fn rejects_tampered_pubkey() {
+        let mut keystore = encrypt(&Keypair::new(), "hunter2", TEST_PARAMS).unwrap();
+        keystore.pubkey = Pubkey::new_unique().to_string();
+
+        assert!(decrypt(&keystore, "hunter2").is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Context, Result};
+use arborist::{
+    chain::ChainClient,
+    client::{SolanaClient, TransactionOutcome},
+    lut::{tree_addresses, LookupTable, MAX_EXTEND_ADDRESSES},
+};
+use serde::Serialize;
+use solana_address_lookup_table_program::{
+    instruction::{
+        close_lookup_table, create_lookup_table, deactivate_lookup_table, extend_lookup_table,
+    },
+    state::LOOKUP_TABLE_MAX_ADDRESSES,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey};
+
+use crate::{
+    cli::{LutAddresses, LutClose, LutCreate, LutDeactivate, LutExtend, LutShow},
+    output::{ErrorCode, Output, ResultExt, TransactionsResult},
+    project::Project,
+    signer::CommandSigners,
+};
+
+/// Fetch and decode a lookup table, failing if it does not exist
+async fn fetch_table<C: ChainClient>(
+    client: &SolanaClient<C>,
+    key: Pubkey,
+) -> Result<LookupTable> {
+    client
+        .lookup_table(&key)
+        .await
+        .with_context(|| format!("Error getting lookup table {key}"))?
+        .with_context(|| format!("Lookup table {key} does not exist"))
+        .code(ErrorCode::InvalidArgument)
+}
+
+fn collect_addresses(project: &Project, addresses: LutAddresses) -> Result<Vec<Pubkey>> {
+    let LutAddresses { trees, addresses } = addresses;
+
+    let mut seen = BTreeSet::new();
+    Ok(project
+        .trees(&trees)?
+        .into_iter()
+        .flat_map(tree_addresses)
+        .chain(addresses)
+        .filter(|a| seen.insert(*a))
+        .collect())
+}
+
+/// Result of the `lut create` command
+#[derive(Debug, Serialize)]
+pub struct CreateResult {
+    transactions: Vec<TransactionOutcome>,
+    lookup_table: String,
+}
+
+impl Output for CreateResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        for txn in &self.transactions {
+            txn.write_text(out)?;
+        }
+
+        writeln!(out, "Lookup table: {}", self.lookup_table)
+    }
+}
+
+/// Result of the `lut show` command
+#[derive(Debug, Serialize)]
+pub struct ShowResult {
+    lookup_table: String,
+    authority: Option<String>,
+    deactivation_slot: Option<u64>,
+    last_extended_slot: u64,
+    addresses: Vec<String>,
+}
+
+impl Output for ShowResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        writeln!(out, "Lookup table: {}", self.lookup_table)?;
+        writeln!(
+            out,
+            "Authority: {}",
+            self.authority.as_deref().unwrap_or("none (frozen)")
+        )?;
+
+        if let Some(slot) = self.deactivation_slot {
+            writeln!(out, "Deactivated at slot: {slot}")?;
+        }
+
+        writeln!(out, "Last extended at slot: {}", self.last_extended_slot)?;
+        writeln!(out, "Addresses ({}):", self.addresses.len())?;
+
+        for (i, addr) in self.addresses.iter().enumerate() {
+            writeln!(out, "  {i:>3}: {addr}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Send extend instructions for a list of addresses, optionally preceded by
+/// another instruction in the first transaction
+async fn extend<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    table: Pubkey,
+    first: Option<Instruction>,
+    addresses: &[Pubkey],
+) -> Result<Vec<TransactionOutcome>> {
+    let payer = signers.payer();
+    let mut first = first;
+    let mut transactions = vec![];
+
+    for chunk in addresses.chunks(MAX_EXTEND_ADDRESSES) {
+        let ixs = first
+            .take()
+            .into_iter()
+            .chain([extend_lookup_table(
+                table,
+                signers.authority(),
+                Some(payer),
+                chunk.to_vec(),
+            )])
+            .collect::<Vec<_>>();
+
+        transactions.push(
+            client
+                .send_message(&ixs, Some(&payer), &signers.with(&[]), &[])
+                .await?,
+        );
+    }
+
+    if let Some(ix) = first {
+        transactions.push(
+            client
+                .send_message(&[ix], Some(&payer), &signers.with(&[]), &[])
+                .await?,
+        );
+    }
+
+    Ok(transactions)
+}
+
+pub async fn create<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: LutCreate,
+) -> Result<CreateResult> {
+    let LutCreate { addresses } = args;
+    let addresses = collect_addresses(project, addresses)?;
+
+    let slot = client
+        .slot(CommitmentConfig::finalized())
+        .await
+        .context("Error getting recent slot")?;
+    let (ix, table) = create_lookup_table(signers.authority(), signers.payer(), slot);
+
+    let transactions = extend(client, signers, table, Some(ix), &addresses).await?;
+
+    Ok(CreateResult {
+        transactions,
+        lookup_table: table.to_string(),
+    })
+}
+
+pub async fn extend_table<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    project: &Project,
+    args: LutExtend,
+) -> Result<TransactionsResult> {
+    let LutExtend { table, addresses } = args;
+
+    let LookupTable {
+        addresses: existing,
+        ..
+    } = fetch_table(client, table).await?;
+
+    let addresses = collect_addresses(project, addresses)?
+        .into_iter()
+        .filter(|a| !existing.contains(a))
+        .collect::<Vec<_>>();
+
+    if existing.len() + addresses.len() > LOOKUP_TABLE_MAX_ADDRESSES {
+        return Err(anyhow!(
+            "Lookup table {table} cannot hold {} more addresses (contains {} of a maximum \
+             {LOOKUP_TABLE_MAX_ADDRESSES})",
+            addresses.len(),
+            existing.len(),
+        ))
+        .code(ErrorCode::InvalidArgument);
+    }
+
+    Ok(TransactionsResult {
+        transactions: extend(client, signers, table, None, &addresses).await?,
+    })
+}
+
+pub async fn deactivate<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    args: LutDeactivate,
+) -> Result<TransactionOutcome> {
+    let LutDeactivate { table } = args;
+
+    client
+        .send_message(
+            &[deactivate_lookup_table(table, signers.authority())],
+            Some(&signers.payer()),
+            &signers.with(&[]),
+            &[],
+        )
+        .await
+        .map_err(Into::into)
+}
+
+pub async fn close<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    args: LutClose,
+) -> Result<TransactionOutcome> {
+    let LutClose { table, recipient } = args;
+
+    client
+        .send_message(
+            &[close_lookup_table(
+                table,
+                signers.authority(),
+                recipient.unwrap_or_else(|| signers.payer()),
+            )],
+            Some(&signers.payer()),
+            &signers.with(&[]),
+            &[],
+        )
+        .await
+        .map_err(Into::into)
+}
+
+pub async fn show<C: ChainClient>(client: &SolanaClient<C>, args: LutShow) -> Result<ShowResult> {
+    let LutShow { table } = args;
+
+    let LookupTable {
+        authority,
+        deactivation_slot,
+        last_extended_slot,
+        addresses,
+    } = fetch_table(client, table).await?;
+
+    Ok(ShowResult {
+        lookup_table: table.to_string(),
+        authority: authority.map(|a| a.to_string()),
+        deactivation_slot: (deactivation_slot != u64::MAX).then_some(deactivation_slot),
+        last_extended_slot,
+        addresses: addresses.iter().map(ToString::to_string).collect(),
+    })
+}
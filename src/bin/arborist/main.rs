@@ -0,0 +1,324 @@
+//! Entrypoint for the arborist binary
+
+#![deny(
+    clippy::disallowed_methods,
+    clippy::suspicious,
+    clippy::style,
+    clippy::clone_on_ref_ptr,
+    missing_debug_implementations,
+    missing_copy_implementations
+)]
+#![warn(clippy::pedantic, missing_docs)]
+
+mod batch;
+mod bubblegum;
+mod cli;
+mod compression;
+mod das;
+mod decode;
+mod history;
+mod keystore;
+mod lut;
+mod monitor;
+mod multisig;
+mod nonce;
+mod offline;
+mod output;
+mod project;
+mod signer;
+mod snapshot;
+#[cfg(test)]
+mod test_support;
+mod watch;
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use arborist::{
+    chain::ChainClient,
+    client::{DurableNonce, SolanaClient},
+};
+use cli::{Compression, Config as ConfigCmd, Keystore, Lut, Nonce, NonceArgs, Opts, Subcommand};
+use output::{ErrorCode, OutputFormat, ResultExt};
+use solana_cli_config::Config;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::signer::Signer;
+
+fn main() {
+    let opts: Opts = clap::Parser::parse();
+    let output = opts.output;
+
+    match run(opts) {
+        Ok(()) => (),
+        Err(e) => {
+            output.print_error(&e);
+            std::process::exit(1);
+        },
+    }
+}
+
+/// Load the Solana CLI configuration, or the default configuration if it does
+/// not exist
+fn load_solana_config(path: &str) -> Result<Config> {
+    Config::load(path)
+        .or_else(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Ok(Config::default())
+            } else {
+                Err(e)
+            }
+        })
+        .context("Error loading Solana CLI configuration")
+        .code(ErrorCode::Config)
+}
+
+fn run(opts: Opts) -> Result<()> {
+    let Opts {
+        solana_config,
+        project_config,
+        profile,
+        rpc_url,
+        rpc_timeout,
+        rpc_commitment,
+        das_url,
+        ws_url,
+        keypair,
+        signer,
+        roles,
+        mut compute_budget,
+        send,
+        lookup_tables,
+        nonce,
+        output,
+        subcmd,
+    } = opts;
+
+    let cfg = load_solana_config(&solana_config)?;
+
+    // Options given on the command line take precedence over the selected
+    // profile, which takes precedence over the Solana CLI configuration
+    let mut project = project::Project::load(project_config)?;
+
+    // Handle config commands first, so that they can fix a broken profile
+    let subcmd = match subcmd {
+        Subcommand::Config(c) => return config(&mut project, output, c),
+        s => s,
+    };
+    let profile = project.profile(profile.as_deref())?;
+
+    if !compute_budget.auto_priority_fee {
+        compute_budget.priority_fee = compute_budget.priority_fee.or(profile.priority_fee);
+    }
+
+    let keypair_path = keypair.or(profile.keypair).unwrap_or(cfg.keypair_path);
+    let signer_config = signer::SignerFromPathConfig {
+        allow_null_signer: send.sign_only,
+    };
+    let loader = SignerLoader {
+        args: &signer,
+        roles: &roles,
+        keypair_path: &keypair_path,
+        config: signer_config,
+    };
+
+    let NonceArgs {
+        nonce,
+        nonce_authority,
+    } = nonce;
+    let nonce_authority = nonce_authority
+        .map(|path| {
+            signer::signer_from_path(&signer, &path, "nonce authority", signer_config)
+                .map_err(|e| anyhow::anyhow!("{e}"))
+                .context("Error parsing nonce authority")
+                .code(ErrorCode::Signer)
+        })
+        .transpose()?;
+
+    let rpc_url = solana_clap_v3_utils::input_validators::normalize_to_url_if_moniker(
+        rpc_url.or(profile.url).unwrap_or(cfg.json_rpc_url),
+    );
+    let das = das::DasClient::new(
+        das_url
+            .or(profile.das_url)
+            .unwrap_or_else(|| rpc_url.clone()),
+    );
+    let rpc_commitment = match rpc_commitment {
+        Some(c) => Some(c),
+        None => profile
+            .commitment
+            .as_deref()
+            .map(str::parse)
+            .transpose()
+            .context("Invalid commitment level in project config profile")
+            .code(ErrorCode::Config)?,
+    };
+
+    let client = SolanaClient::new(
+        RpcClient::new_with_timeout_and_commitment(
+            rpc_url,
+            Duration::from_secs(rpc_timeout),
+            rpc_commitment
+                .map_or_else(|| cfg.commitment.parse(), Ok)
+                .context("Invalid commitment level in Solana CLI configuration")
+                .code(ErrorCode::Config)?,
+        ),
+        output.is_text(),
+    )
+    .compute_budget(compute_budget.into())
+    .send_config(send.into())
+    .lookup_tables(lookup_tables.into())
+    .nonce(nonce.map(|account| DurableNonce {
+        account,
+        authority: nonce_authority,
+    }));
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Error initializing async runtime")?
+        .block_on(dispatch(
+            subcmd,
+            &client,
+            &das,
+            &ws_url.unwrap_or_else(|| Config::compute_websocket_url(&client.url())),
+            &mut project,
+            &loader,
+            output,
+        ))
+}
+
+/// Run a `config` subcommand and print its result
+fn config(project: &mut project::Project, output: OutputFormat, cmd: ConfigCmd) -> Result<()> {
+    match cmd {
+        ConfigCmd::Get(g) => output.print(&project::get(project, g)?),
+        ConfigCmd::Set(s) => output.print(&project::set(project, s)?),
+        ConfigCmd::List => output.print(&project::list(project)?),
+    }
+}
+
+/// Lazily loads signers, so that commands which don't sign anything never
+/// prompt for a keypair
+struct SignerLoader<'a> {
+    args: &'a signer::SignerArgs,
+    roles: &'a cli::SignerRoleArgs,
+    keypair_path: &'a str,
+    config: signer::SignerFromPathConfig,
+}
+
+impl SignerLoader<'_> {
+    /// Load the named signers for a command
+    fn command_signers(&self) -> Result<signer::CommandSigners> {
+        signer::CommandSigners::load(self.args, self.roles, self.keypair_path, self.config)
+            .map_err(|e| anyhow::anyhow!("{e}"))
+            .context("Error loading signers")
+            .code(ErrorCode::Signer)
+    }
+
+    /// Load only the signers given with --signer
+    fn extra_signers(&self) -> Result<Vec<Box<dyn Signer>>> {
+        signer::signers_from_paths(
+            self.args,
+            self.roles.signers.iter().map(|s| (s.as_str(), "signer")),
+            self.config,
+        )
+        .map(|(signers, _)| signers)
+        .map_err(|e| anyhow::anyhow!("{e}"))
+        .context("Error loading signers")
+        .code(ErrorCode::Signer)
+    }
+}
+
+/// Run a subcommand and print its result
+async fn dispatch<C: ChainClient>(
+    subcmd: Subcommand,
+    client: &SolanaClient<C>,
+    das: &das::DasClient,
+    ws_url: &str,
+    project: &mut project::Project,
+    signers: &SignerLoader<'_>,
+    output: OutputFormat,
+) -> Result<()> {
+    match subcmd {
+        Subcommand::CreateTree(c) => output
+            .print(&bubblegum::create_tree(client, &signers.command_signers()?, project, c).await?),
+        Subcommand::DelegateTree(d) => output.print(
+            &bubblegum::delegate_tree(client, &signers.command_signers()?, project, d).await?,
+        ),
+        Subcommand::SetDecompressible(s) => output.print(
+            &bubblegum::set_decompressible(client, &signers.command_signers()?, project, s).await?,
+        ),
+        Subcommand::Mint(m) => {
+            output.print(&bubblegum::mint(client, &signers.command_signers()?, project, m).await?)
+        },
+        Subcommand::MintBatch(m) => {
+            output.print(&batch::mint_batch(client, &signers.command_signers()?, project, m).await?)
+        },
+        Subcommand::CloseTree(c) => {
+            let res =
+                compression::close_trees(client, &signers.command_signers()?, project, c).await?;
+            output.print(&res)?;
+            res.check()
+        },
+        Subcommand::Compression(Compression::Create(c)) => output
+            .print(&compression::create(client, &signers.command_signers()?, project, c).await?),
+        Subcommand::Compression(Compression::Show(s)) => {
+            output.print(&compression::show(client, project, s).await?)
+        },
+        Subcommand::Compression(Compression::Append(a)) => output
+            .print(&compression::append(client, &signers.command_signers()?, project, a).await?),
+        Subcommand::Compression(Compression::Replace(r)) => output
+            .print(&compression::replace(client, &signers.command_signers()?, project, r).await?),
+        Subcommand::Compression(Compression::Verify(v)) => output
+            .print(&compression::verify(client, &signers.command_signers()?, project, v).await?),
+        Subcommand::Compression(Compression::TransferAuthority(t)) => output.print(
+            &compression::transfer_authority(client, &signers.command_signers()?, project, t)
+                .await?,
+        ),
+        Subcommand::Compression(Compression::Close(c)) => output
+            .print(&compression::close(client, &signers.command_signers()?, project, c).await?),
+        Subcommand::WatchTree(w) => watch::watch_tree(client, project, ws_url, output, w).await,
+        Subcommand::Monitor(m) => {
+            let payer = || Ok(signers.command_signers()?.payer());
+            monitor::monitor(client, project, payer, output, m).await
+        },
+        Subcommand::DecodeTx(d) => output.print(&decode::decode_tx(client, d).await?),
+        Subcommand::History(h) => output.print(&history::history(client, project, h).await?),
+        Subcommand::ExportTree(e) => {
+            output.print(&snapshot::export_tree(client, das, project, e).await?)
+        },
+        Subcommand::InspectSnapshot(i) => output.print(&snapshot::inspect_snapshot(i)?),
+        Subcommand::ListAssets(l) => output.print(&das::list_assets(das, project, l).await?),
+        Subcommand::Lut(Lut::Create(c)) => {
+            output.print(&lut::create(client, &signers.command_signers()?, project, c).await?)
+        },
+        Subcommand::Lut(Lut::Extend(e)) => {
+            output.print(&lut::extend_table(client, &signers.command_signers()?, project, e).await?)
+        },
+        Subcommand::Lut(Lut::Deactivate(d)) => {
+            output.print(&lut::deactivate(client, &signers.command_signers()?, d).await?)
+        },
+        Subcommand::Lut(Lut::Close(c)) => {
+            output.print(&lut::close(client, &signers.command_signers()?, c).await?)
+        },
+        Subcommand::Lut(Lut::Show(s)) => output.print(&lut::show(client, s).await?),
+        Subcommand::Submit(s) => {
+            output.print(&offline::submit(client, &signers.extra_signers()?, s).await?)
+        },
+        Subcommand::Nonce(Nonce::Create(c)) => {
+            output.print(&nonce::create(client, &signers.command_signers()?, c).await?)
+        },
+        Subcommand::Nonce(Nonce::Show(s)) => output.print(&nonce::show(client, s).await?),
+        Subcommand::Nonce(Nonce::Advance(a)) => {
+            output.print(&nonce::advance(client, &signers.command_signers()?, a).await?)
+        },
+        Subcommand::Nonce(Nonce::Withdraw(w)) => {
+            output.print(&nonce::withdraw(client, &signers.command_signers()?, w).await?)
+        },
+        Subcommand::Keystore(Keystore::New(n)) => output.print(&keystore::new(n)?),
+        Subcommand::Keystore(Keystore::Import(i)) => {
+            output.print(&keystore::import(signers.args, i)?)
+        },
+        Subcommand::Keystore(Keystore::Export(e)) => output.print(&keystore::export(e)?),
+        Subcommand::Config(_) => unreachable!("Config commands are handled by run"),
+    }
+}
@@ -0,0 +1,657 @@
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    fmt::Write as _,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use arborist::{
+    accounts::{decode_merkle_tree, decode_tree_config},
+    chain::ChainClient,
+    client::SolanaClient,
+    tree::tree_config_address,
+};
+use futures_util::future;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_request::MAX_MULTIPLE_ACCOUNTS;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    cli::{Monitor, TreeArg},
+    output::{ErrorCode, Output, OutputFormat, ResultExt},
+    project::Project,
+};
+
+/// Contents of a `monitor.toml` configuration file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MonitorConfig {
+    /// Address to serve the `/metrics` endpoint on
+    #[serde(default = "MonitorConfig::default_listen")]
+    listen: SocketAddr,
+
+    /// Seconds between polls of the monitored accounts
+    #[serde(default = "MonitorConfig::default_interval")]
+    interval: u64,
+
+    /// Addresses or names of the trees to monitor
+    trees: Vec<String>,
+
+    /// Addresses of the fee payers whose balances are reported, defaulting to
+    /// the configured fee payer
+    #[serde(default)]
+    payers: Vec<String>,
+
+    /// Conditions that trigger an alert
+    #[serde(default)]
+    alerts: AlertConfig,
+}
+
+impl MonitorConfig {
+    fn default_listen() -> SocketAddr { ([127, 0, 0, 1], 9464).into() }
+
+    fn default_interval() -> u64 { 30 }
+
+    fn load(path: &Path) -> Result<Self> {
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("Error reading monitor config {}", path.display()))
+            .code(ErrorCode::Config)?;
+
+        toml::from_str(&s)
+            .with_context(|| format!("Error parsing monitor config {}", path.display()))
+            .code(ErrorCode::Config)
+    }
+}
+
+/// Thresholds for alerts, and where to send them
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertConfig {
+    /// URL to POST each alert to as JSON
+    webhook: Option<String>,
+
+    /// Fraction of a tree's mint capacity, from 0 to 1, above which it is
+    /// considered nearly full
+    fill_threshold: Option<f64>,
+
+    /// Balance in lamports below which a fee payer is considered low on funds
+    min_payer_balance: Option<u64>,
+}
+
+/// A monitored condition that was newly crossed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "alert", rename_all = "kebab-case")]
+pub enum Alert {
+    /// A tree's mint count crossed the fill threshold
+    TreeFill {
+        merkle_tree: String,
+        num_minted: u64,
+        capacity: u64,
+        threshold: f64,
+    },
+    /// A fee payer's balance dropped below the minimum
+    LowBalance {
+        payer: String,
+        balance: u64,
+        threshold: u64,
+    },
+}
+
+impl std::fmt::Display for Alert {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::TreeFill {
+                merkle_tree,
+                num_minted,
+                capacity,
+                threshold,
+            } => write!(
+                f,
+                "Tree {merkle_tree} is above {:.0}% full ({num_minted}/{capacity} minted)",
+                threshold * 100.0
+            ),
+            Self::LowBalance {
+                payer,
+                balance,
+                threshold,
+            } => write!(
+                f,
+                "Fee payer {payer} balance {balance} lamports is below {threshold} lamports"
+            ),
+        }
+    }
+}
+
+/// Body of a webhook request, with a `text` field understood by common chat
+/// webhooks
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    text: String,
+    #[serde(flatten)]
+    alert: &'a Alert,
+}
+
+/// Progress of a running monitor
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum MonitorEvent {
+    /// The metrics endpoint is being served
+    Listening {
+        address: SocketAddr,
+        interval: u64,
+        trees: usize,
+        payers: usize,
+    },
+    /// An alert was fired
+    Alert(Alert),
+}
+
+impl Output for MonitorEvent {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        match self {
+            Self::Listening {
+                address,
+                interval,
+                trees,
+                payers,
+            } => writeln!(
+                out,
+                "Serving metrics for {trees} tree(s) and {payers} payer(s) on \
+                 http://{address}/metrics, polling every {interval}s"
+            ),
+            Self::Alert(a) => writeln!(out, "ALERT: {a}"),
+        }
+    }
+}
+
+/// Values read for a tree in a single poll
+#[derive(Debug, Clone, PartialEq)]
+struct TreeSample {
+    merkle_tree: Pubkey,
+    leaves_used: u32,
+    sequence_number: u64,
+    num_minted: u64,
+    capacity: u64,
+    buffer_utilization: Option<f64>,
+}
+
+/// Values read in a single poll
+#[derive(Debug, Default)]
+struct Sample {
+    trees: Vec<TreeSample>,
+    payers: Vec<(Pubkey, u64)>,
+    errors: usize,
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn fraction(n: u64, d: u64) -> f64 {
+    if d == 0 {
+        1.0
+    } else {
+        n as f64 / d as f64
+    }
+}
+
+fn write_metric<T: std::fmt::Display>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (String, T)>,
+) {
+    writeln!(out, "# HELP {name} {help}").unwrap_or_else(|_| unreachable!());
+    writeln!(out, "# TYPE {name} gauge").unwrap_or_else(|_| unreachable!());
+
+    for (labels, value) in samples {
+        writeln!(out, "{name}{labels} {value}").unwrap_or_else(|_| unreachable!());
+    }
+}
+
+impl Sample {
+    /// Render the sample in the Prometheus text exposition format
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let trees = |f: fn(&TreeSample) -> Option<String>| {
+            self.trees
+                .iter()
+                .filter_map(move |t| Some((format!("{{tree=\"{}\"}}", t.merkle_tree), f(t)?)))
+        };
+
+        write_metric(
+            &mut out,
+            "arborist_tree_leaves_used",
+            "Number of leaves appended to the tree",
+            trees(|t| Some(t.leaves_used.to_string())),
+        );
+        write_metric(
+            &mut out,
+            "arborist_tree_num_minted",
+            "Number of assets minted into the tree",
+            trees(|t| Some(t.num_minted.to_string())),
+        );
+        write_metric(
+            &mut out,
+            "arborist_tree_capacity_remaining",
+            "Number of assets that can still be minted into the tree",
+            trees(|t| Some(t.capacity.saturating_sub(t.num_minted).to_string())),
+        );
+        write_metric(
+            &mut out,
+            "arborist_tree_sequence_number",
+            "Number of changes made to the tree",
+            trees(|t| Some(t.sequence_number.to_string())),
+        );
+        write_metric(
+            &mut out,
+            "arborist_tree_buffer_utilization",
+            "Fraction of the tree's changelog buffer filled since the previous poll",
+            trees(|t| t.buffer_utilization.map(|u| u.to_string())),
+        );
+        write_metric(
+            &mut out,
+            "arborist_payer_balance_lamports",
+            "Balance of the fee payer",
+            self.payers
+                .iter()
+                .map(|(p, b)| (format!("{{payer=\"{p}\"}}"), b)),
+        );
+        write_metric(
+            &mut out,
+            "arborist_poll_errors",
+            "Number of accounts that could not be read in the last poll",
+            [(String::new(), self.errors)],
+        );
+
+        out
+    }
+}
+
+/// State carried between polls
+struct Poller<'a, C> {
+    client: &'a SolanaClient<C>,
+    output: OutputFormat,
+    trees: Vec<Pubkey>,
+    payers: Vec<Pubkey>,
+    alerts: AlertConfig,
+    sequence_numbers: HashMap<Pubkey, u64>,
+    filled: HashSet<Pubkey>,
+    low: HashSet<Pubkey>,
+}
+
+impl<C: ChainClient> Poller<'_, C> {
+    async fn sample_trees(&mut self, sample: &mut Sample) -> Result<()> {
+        for trees in self.trees.chunks(MAX_MULTIPLE_ACCOUNTS / 2) {
+            let addresses = trees
+                .iter()
+                .copied()
+                .chain(trees.iter().map(tree_config_address))
+                .collect::<Vec<_>>();
+            let accounts = self
+                .client
+                .multiple_accounts(&addresses)
+                .await
+                .context("Error getting tree accounts")
+                .code(ErrorCode::Rpc)?;
+            let (tree_accounts, config_accounts) = accounts.split_at(trees.len());
+
+            for ((merkle_tree, tree), config) in
+                trees.iter().zip(tree_accounts).zip(config_accounts)
+            {
+                let res = (|| {
+                    let tree = tree.as_ref().context("Merkle tree does not exist")?;
+                    let tree = decode_merkle_tree(&tree.data)?;
+                    let config = config.as_ref().context("Tree config does not exist")?;
+                    let config = decode_tree_config(&config.data)?;
+
+                    anyhow::Ok((tree, config))
+                })();
+                let (tree, config) = match res {
+                    Ok(t) => t,
+                    Err(e) => {
+                        self.output.print_error(
+                            &e.context(format!("Error reading Merkle tree {merkle_tree}")),
+                        );
+                        sample.errors += 1;
+                        continue;
+                    },
+                };
+
+                let prev = self
+                    .sequence_numbers
+                    .insert(*merkle_tree, tree.sequence_number);
+                let buffer_utilization = prev.map(|p| {
+                    fraction(
+                        tree.sequence_number.saturating_sub(p),
+                        tree.max_buffer_size.into(),
+                    )
+                    .min(1.0)
+                });
+
+                sample.trees.push(TreeSample {
+                    merkle_tree: *merkle_tree,
+                    leaves_used: tree.rightmost_index,
+                    sequence_number: tree.sequence_number,
+                    num_minted: config.num_minted,
+                    capacity: config.total_mint_capacity,
+                    buffer_utilization,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read every monitored account, returning the sample and any alerts that
+    /// were newly triggered
+    async fn poll(&mut self) -> (Sample, Vec<Alert>) {
+        let mut sample = Sample::default();
+
+        if let Err(e) = self.sample_trees(&mut sample).await {
+            self.output.print_error(&e);
+            sample.errors += self.trees.len();
+        }
+
+        for payer in &self.payers {
+            match self.client.balance(payer).await {
+                Ok(b) => sample.payers.push((*payer, b)),
+                Err(e) => {
+                    self.output.print_error(
+                        &anyhow!(e).context(format!("Error getting balance of {payer}")),
+                    );
+                    sample.errors += 1;
+                },
+            }
+        }
+
+        let alerts = self.alerts(&sample);
+        (sample, alerts)
+    }
+
+    /// Check a sample against the alert thresholds.  Each alert fires once
+    /// when its threshold is crossed, and again only after recovering.
+    fn alerts(&mut self, sample: &Sample) -> Vec<Alert> {
+        let mut alerts = vec![];
+
+        if let Some(threshold) = self.alerts.fill_threshold {
+            for t in &sample.trees {
+                let full = fraction(t.num_minted, t.capacity) >= threshold;
+
+                if !full {
+                    self.filled.remove(&t.merkle_tree);
+                } else if self.filled.insert(t.merkle_tree) {
+                    alerts.push(Alert::TreeFill {
+                        merkle_tree: t.merkle_tree.to_string(),
+                        num_minted: t.num_minted,
+                        capacity: t.capacity,
+                        threshold,
+                    });
+                }
+            }
+        }
+
+        if let Some(threshold) = self.alerts.min_payer_balance {
+            for &(payer, balance) in &sample.payers {
+                if balance >= threshold {
+                    self.low.remove(&payer);
+                } else if self.low.insert(payer) {
+                    alerts.push(Alert::LowBalance {
+                        payer: payer.to_string(),
+                        balance,
+                        threshold,
+                    });
+                }
+            }
+        }
+
+        alerts
+    }
+}
+
+/// Serve the most recently rendered metrics
+fn respond(req: &Request<Body>, metrics: &RwLock<String>) -> Response<Body> {
+    let mut res = Response::default();
+
+    if req.method() != Method::GET || req.uri().path() != "/metrics" {
+        *res.status_mut() = StatusCode::NOT_FOUND;
+        return res;
+    }
+
+    res.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    *res.body_mut() = metrics
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+        .into();
+    res
+}
+
+async fn send_webhook(http: &reqwest::Client, url: &str, alert: &Alert) -> Result<()> {
+    http.post(url)
+        .json(&WebhookPayload {
+            text: alert.to_string(),
+            alert,
+        })
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .with_context(|| format!("Error sending alert to webhook {url}"))?;
+
+    Ok(())
+}
+
+pub async fn monitor<C: ChainClient>(
+    client: &SolanaClient<C>,
+    project: &Project,
+    default_payer: impl FnOnce() -> Result<Pubkey>,
+    output: OutputFormat,
+    args: Monitor,
+) -> Result<()> {
+    let Monitor { config } = args;
+    let MonitorConfig {
+        listen,
+        interval,
+        trees,
+        payers,
+        alerts,
+    } = MonitorConfig::load(&config)?;
+
+    let trees = trees
+        .iter()
+        .map(|t| t.parse().unwrap_or_else(|e| match e {}))
+        .collect::<Vec<TreeArg>>();
+    let trees = project.trees(&trees)?;
+    let payers = if payers.is_empty() {
+        vec![default_payer()?]
+    } else {
+        payers
+            .iter()
+            .map(|p| {
+                p.parse()
+                    .with_context(|| format!("Invalid payer address {p:?} in monitor config"))
+                    .code(ErrorCode::Config)
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let metrics = Arc::new(RwLock::new(String::new()));
+    let server = {
+        let metrics = Arc::clone(&metrics);
+        hyper::Server::try_bind(&listen)
+            .with_context(|| format!("Error binding metrics endpoint to {listen}"))
+            .code(ErrorCode::Io)?
+            .serve(make_service_fn(move |_| {
+                let metrics = Arc::clone(&metrics);
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        let res = respond(&req, &metrics);
+                        async move { Ok::<_, Infallible>(res) }
+                    }))
+                }
+            }))
+    };
+
+    output.print(&MonitorEvent::Listening {
+        address: server.local_addr(),
+        interval,
+        trees: trees.len(),
+        payers: payers.len(),
+    })?;
+
+    let http = reqwest::Client::new();
+    let webhook = alerts.webhook.clone();
+    let mut poller = Poller {
+        client,
+        output,
+        trees,
+        payers,
+        alerts,
+        sequence_numbers: HashMap::new(),
+        filled: HashSet::new(),
+        low: HashSet::new(),
+    };
+
+    let poll = async {
+        let mut timer = tokio::time::interval(Duration::from_secs(interval.max(1)));
+
+        loop {
+            timer.tick().await;
+            let (sample, alerts) = poller.poll().await;
+            *metrics.write().unwrap_or_else(PoisonError::into_inner) = sample.render();
+
+            for alert in alerts {
+                output.print(&MonitorEvent::Alert(alert.clone()))?;
+
+                if let Some(url) = &webhook {
+                    if let Err(e) = send_webhook(&http, url, &alert).await {
+                        output.print_error(&e);
+                    }
+                }
+            }
+        }
+    };
+    futures_util::pin_mut!(poll);
+
+    match future::select(server, poll).await {
+        future::Either::Left((res, _)) => res
+            .context("Error serving metrics endpoint")
+            .code(ErrorCode::Io),
+        future::Either::Right((res, _)) => res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arborist::{accounts::TreeConfig, chain::MockChain};
+    use solana_sdk::account::Account;
+
+    use super::*;
+    use crate::test_support::{block_on, config_account, tree_account};
+
+    #[test]
+    fn parses_config() {
+        let config: MonitorConfig = toml::from_str(
+            r#"
+            trees = ["main"]
+
+            [alerts]
+            webhook = "https://example.com/hook"
+            fill_threshold = 0.9
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.listen, MonitorConfig::default_listen());
+        assert_eq!(config.interval, 30);
+        assert_eq!(config.trees, ["main"]);
+        assert_eq!(config.alerts.fill_threshold, Some(0.9));
+        assert_eq!(config.alerts.min_payer_balance, None);
+    }
+
+    #[test]
+    fn reports_metrics_and_alerts() {
+        let merkle_tree = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let config = TreeConfig {
+            tree_creator: payer,
+            tree_delegate: payer,
+            total_mint_capacity: 8,
+            num_minted: 6,
+            is_public: false,
+        };
+
+        let chain = MockChain::new();
+        chain.set_account(merkle_tree, tree_account(6, [0; 32], 6));
+        chain.set_account(
+            tree_config_address(&merkle_tree),
+            config_account(1, &config),
+        );
+        chain.set_account(payer, Account {
+            lamports: 500,
+            ..Account::default()
+        });
+
+        let client = SolanaClient::new(chain, false);
+        let mut poller = Poller {
+            client: &client,
+            output: OutputFormat::Json,
+            trees: vec![merkle_tree],
+            payers: vec![payer],
+            alerts: AlertConfig {
+                webhook: None,
+                fill_threshold: Some(0.75),
+                min_payer_balance: Some(1000),
+            },
+            sequence_numbers: HashMap::new(),
+            filled: HashSet::new(),
+            low: HashSet::new(),
+        };
+
+        let (sample, alerts) = block_on(poller.poll());
+        let metrics = sample.render();
+        for line in [
+            format!("arborist_tree_leaves_used{{tree=\"{merkle_tree}\"}} 6"),
+            format!("arborist_tree_capacity_remaining{{tree=\"{merkle_tree}\"}} 2"),
+            format!("arborist_tree_sequence_number{{tree=\"{merkle_tree}\"}} 6"),
+            format!("arborist_payer_balance_lamports{{payer=\"{payer}\"}} 500"),
+            "arborist_poll_errors 0".into(),
+        ] {
+            assert!(
+                metrics.lines().any(|l| l == line),
+                "{line} not in {metrics}"
+            );
+        }
+        // Utilization is only known once there is a previous poll
+        assert!(!metrics.contains("arborist_tree_buffer_utilization{"));
+        assert_eq!(alerts, vec![
+            Alert::TreeFill {
+                merkle_tree: merkle_tree.to_string(),
+                num_minted: 6,
+                capacity: 8,
+                threshold: 0.75,
+            },
+            Alert::LowBalance {
+                payer: payer.to_string(),
+                balance: 500,
+                threshold: 1000,
+            },
+        ]);
+
+        // Alerts are not repeated while their condition persists
+        client.set_account(merkle_tree, tree_account(10, [0; 32], 6));
+        let (sample, alerts) = block_on(poller.poll());
+        assert!(sample.render().lines().any(
+            |l| l == format!("arborist_tree_buffer_utilization{{tree=\"{merkle_tree}\"}} 0.5")
+        ));
+        assert_eq!(alerts, vec![]);
+    }
+}
@@ -0,0 +1,426 @@
+use anchor_lang::{
+    prelude::borsh::{self, BorshSerialize},
+    solana_program::hash::hash,
+};
+use anyhow::{bail, Context, Result};
+use arborist::{
+    chain::ChainClient,
+    client::{SolanaClient, TransactionOutcome},
+};
+use serde::Serialize;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey,
+    pubkey::Pubkey,
+    system_program,
+};
+
+use crate::{
+    cli::MultisigArgs,
+    output::{ErrorCode, Output, ResultExt},
+    signer::CommandSigners,
+};
+
+/// Address of the Squads v4 multisig program
+pub const SQUADS_PROGRAM_ID: Pubkey = pubkey!("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf");
+
+const SEED_PREFIX: &[u8] = b"multisig";
+const SEED_VAULT: &[u8] = b"vault";
+const SEED_TRANSACTION: &[u8] = b"transaction";
+const SEED_PROPOSAL: &[u8] = b"proposal";
+
+/// Offset of the `transaction_index` field in a multisig account, following
+/// the discriminator, create key, config authority, threshold, and time lock
+const TRANSACTION_INDEX_OFFSET: usize = 8 + 32 + 32 + 2 + 4;
+
+/// A Squads multisig whose vault acts as the authority for a command
+#[derive(Debug, Clone)]
+pub struct Multisig {
+    address: Pubkey,
+    vault_index: u8,
+    transaction_index: Option<u64>,
+    memo: Option<String>,
+}
+
+impl Multisig {
+    /// Read the multisig options of a command, if `--multisig` was given
+    #[must_use]
+    pub fn from_args(args: MultisigArgs) -> Option<Self> {
+        let MultisigArgs {
+            multisig,
+            vault_index,
+            transaction_index,
+            memo,
+        } = args;
+
+        multisig.map(|address| Self {
+            address,
+            vault_index,
+            transaction_index,
+            memo,
+        })
+    }
+
+    /// Address of the vault PDA that signs the proposed instructions
+    #[inline]
+    #[must_use]
+    pub fn vault(&self) -> Pubkey { vault_pda(&self.address, self.vault_index) }
+}
+
+fn vault_pda(multisig: &Pubkey, vault_index: u8) -> Pubkey {
+    Pubkey::find_program_address(
+        &[SEED_PREFIX, multisig.as_ref(), SEED_VAULT, &[vault_index]],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+fn transaction_pda(multisig: &Pubkey, transaction_index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            SEED_PREFIX,
+            multisig.as_ref(),
+            SEED_TRANSACTION,
+            &transaction_index.to_le_bytes(),
+        ],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+fn proposal_pda(multisig: &Pubkey, transaction_index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            SEED_PREFIX,
+            multisig.as_ref(),
+            SEED_TRANSACTION,
+            &transaction_index.to_le_bytes(),
+            SEED_PROPOSAL,
+        ],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+/// Compute the Anchor discriminator for an instruction or account
+fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let mut disc = [0; 8];
+    disc.copy_from_slice(&hash(format!("{namespace}:{name}").as_bytes()).to_bytes()[..8]);
+    disc
+}
+
+/// Serialize instructions into the compact `TransactionMessage` format stored
+/// in a Squads vault transaction, with the vault as the fee payer
+fn vault_transaction_message(vault: &Pubkey, instructions: &[Instruction]) -> Result<Vec<u8>> {
+    fn len<T: TryFrom<usize>>(n: usize) -> Result<T> {
+        n.try_into()
+            .ok()
+            .context("Too many items to serialize in vault transaction")
+    }
+
+    let message = Message::new(instructions, Some(vault));
+    let header = message.header;
+    let num_keys = message.account_keys.len();
+    let num_signers = header.num_required_signatures;
+
+    // Squads stores the number of writable accounts rather than readonly ones
+    let mut buf = vec![
+        num_signers,
+        num_signers - header.num_readonly_signed_accounts,
+        len::<u8>(num_keys)? - num_signers - header.num_readonly_unsigned_accounts,
+    ];
+
+    buf.push(len(num_keys)?);
+    for key in &message.account_keys {
+        buf.extend_from_slice(key.as_ref());
+    }
+
+    buf.push(len(message.instructions.len())?);
+    for ix in &message.instructions {
+        buf.push(ix.program_id_index);
+        buf.push(len(ix.accounts.len())?);
+        buf.extend_from_slice(&ix.accounts);
+        buf.extend_from_slice(&len::<u16>(ix.data.len())?.to_le_bytes());
+        buf.extend_from_slice(&ix.data);
+    }
+
+    // No address table lookups
+    buf.push(0);
+
+    Ok(buf)
+}
+
+#[derive(BorshSerialize)]
+struct VaultTransactionCreateArgs {
+    vault_index: u8,
+    ephemeral_signers: u8,
+    transaction_message: Vec<u8>,
+    memo: Option<String>,
+}
+
+#[derive(BorshSerialize)]
+struct ProposalCreateArgs {
+    transaction_index: u64,
+    draft: bool,
+}
+
+fn instruction_data(name: &str, args: &impl BorshSerialize) -> Vec<u8> {
+    let mut data = discriminator("global", name).to_vec();
+    args.serialize(&mut data)
+        .unwrap_or_else(|_| unreachable!("Serializing to a Vec cannot fail"));
+    data
+}
+
+/// Build the instructions creating a vault transaction and an active
+/// proposal to execute it
+fn proposal_instructions(
+    multisig: &Multisig,
+    transaction_index: u64,
+    creator: Pubkey,
+    rent_payer: Pubkey,
+    transaction_message: Vec<u8>,
+) -> [Instruction; 2] {
+    let Multisig {
+        address,
+        vault_index,
+        ref memo,
+        ..
+    } = *multisig;
+
+    [
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(address, false),
+                AccountMeta::new(transaction_pda(&address, transaction_index), false),
+                AccountMeta::new_readonly(creator, true),
+                AccountMeta::new(rent_payer, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: instruction_data("vault_transaction_create", &VaultTransactionCreateArgs {
+                vault_index,
+                ephemeral_signers: 0,
+                transaction_message,
+                memo: memo.clone(),
+            }),
+        },
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(address, false),
+                AccountMeta::new(proposal_pda(&address, transaction_index), false),
+                AccountMeta::new_readonly(creator, true),
+                AccountMeta::new(rent_payer, true),
+                AccountMeta::new_readonly(system_program::ID, false),
+            ],
+            data: instruction_data("proposal_create", &ProposalCreateArgs {
+                transaction_index,
+                draft: false,
+            }),
+        },
+    ]
+}
+
+/// Read the index of the next transaction for a multisig from the cluster
+async fn next_transaction_index<C: ChainClient>(
+    client: &SolanaClient<C>,
+    multisig: &Pubkey,
+) -> Result<u64> {
+    let account = client
+        .account(multisig)
+        .await
+        .with_context(|| format!("Error getting multisig account {multisig}"))
+        .code(ErrorCode::Rpc)?
+        .with_context(|| format!("Multisig account {multisig} does not exist"))?;
+
+    if account.owner != SQUADS_PROGRAM_ID
+        || !account
+            .data
+            .starts_with(&discriminator("account", "Multisig"))
+    {
+        bail!("{multisig} is not a Squads multisig account");
+    }
+
+    let index = account
+        .data
+        .get(TRANSACTION_INDEX_OFFSET..TRANSACTION_INDEX_OFFSET + 8)
+        .context("Multisig account data is too short")?;
+
+    Ok(u64::from_le_bytes(index.try_into().unwrap_or_else(|_| unreachable!())) + 1)
+}
+
+/// Result of proposing instructions to a multisig
+#[derive(Debug, Serialize)]
+pub struct ProposalResult {
+    #[serde(flatten)]
+    transaction: TransactionOutcome,
+    multisig: String,
+    vault: String,
+    transaction_index: u64,
+    vault_transaction: String,
+    proposal: String,
+    transaction_message: String,
+}
+
+/// Result of a command that may be sent directly or proposed to a multisig
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum AdminOutcome {
+    /// The instructions were sent directly
+    Transaction(TransactionOutcome),
+    /// The instructions were proposed to a multisig
+    Proposal(ProposalResult),
+}
+
+impl Output for AdminOutcome {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        match self {
+            Self::Transaction(t) => t.write_text(out),
+            Self::Proposal(ProposalResult {
+                transaction,
+                multisig,
+                vault,
+                transaction_index,
+                vault_transaction,
+                proposal,
+                transaction_message,
+            }) => {
+                transaction.write_text(out)?;
+                writeln!(out, "Multisig: {multisig}")?;
+                writeln!(out, "Vault: {vault}")?;
+                writeln!(out, "Transaction index: {transaction_index}")?;
+                writeln!(out, "Vault transaction: {vault_transaction}")?;
+                writeln!(out, "Proposal: {proposal}")?;
+                writeln!(out, "Transaction message (base64): {transaction_message}")
+            },
+        }
+    }
+}
+
+/// Send instructions signed by the authority, or if a multisig is given,
+/// propose them as a vault transaction created by the authority signer
+///
+/// # Errors
+/// This function fails if the multisig cannot be read or sending the
+/// transaction fails.
+pub async fn send_or_propose<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    multisig: Option<&Multisig>,
+    instructions: &[Instruction],
+) -> Result<AdminOutcome> {
+    let payer = signers.payer();
+
+    let Some(multisig) = multisig else {
+        return client
+            .send_transaction(instructions, Some(&payer), &signers.with(&[]))
+            .await
+            .map(AdminOutcome::Transaction)
+            .map_err(Into::into);
+    };
+
+    let vault = multisig.vault();
+    let message = vault_transaction_message(&vault, instructions)?;
+    let transaction_index = match multisig.transaction_index {
+        Some(i) => i,
+        None => next_transaction_index(client, &multisig.address).await?,
+    };
+
+    let transaction = client
+        .send_transaction(
+            &proposal_instructions(
+                multisig,
+                transaction_index,
+                signers.authority(),
+                payer,
+                message.clone(),
+            ),
+            Some(&payer),
+            &signers.with(&[]),
+        )
+        .await?;
+
+    Ok(AdminOutcome::Proposal(ProposalResult {
+        transaction,
+        multisig: multisig.address.to_string(),
+        vault: vault.to_string(),
+        transaction_index,
+        vault_transaction: transaction_pda(&multisig.address, transaction_index).to_string(),
+        proposal: proposal_pda(&multisig.address, transaction_index).to_string(),
+        transaction_message: base64::encode(message),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT_TRANSACTION_CREATE: [u8; 8] = [48, 250, 78, 168, 208, 226, 218, 211];
+    const PROPOSAL_CREATE: [u8; 8] = [220, 60, 73, 224, 30, 108, 79, 159];
+
+    fn key(b: u8) -> Pubkey { Pubkey::new_from_array([b; 32]) }
+
+    #[test]
+    fn computes_discriminators() {
+        assert_eq!(
+            discriminator("global", "vault_transaction_create"),
+            VAULT_TRANSACTION_CREATE
+        );
+        assert_eq!(discriminator("global", "proposal_create"), PROPOSAL_CREATE);
+    }
+
+    #[test]
+    fn serializes_proposal_instructions() {
+        let multisig = Multisig {
+            address: key(1),
+            vault_index: 0,
+            transaction_index: Some(5),
+            memo: None,
+        };
+        let vault = multisig.vault();
+        let inner = Instruction {
+            program_id: key(9),
+            accounts: vec![
+                AccountMeta::new(key(8), false),
+                AccountMeta::new_readonly(vault, true),
+            ],
+            data: vec![1, 2, 3],
+        };
+
+        let message = vault_transaction_message(&vault, &[inner]).unwrap();
+
+        // Header: one signer (the vault, writable as fee payer), and one
+        // writable non-signer
+        let mut expected = vec![1, 1, 1, 3];
+        expected.extend_from_slice(vault.as_ref());
+        expected.extend_from_slice(key(8).as_ref());
+        expected.extend_from_slice(key(9).as_ref());
+        // One instruction invoking account 2 with accounts 1 and 0, and a
+        // u16-prefixed data payload
+        expected.extend_from_slice(&[1, 2, 2, 1, 0, 3, 0, 1, 2, 3]);
+        // No address table lookups
+        expected.push(0);
+        assert_eq!(message, expected);
+
+        let [create, propose] =
+            proposal_instructions(&multisig, 5, key(2), key(3), message.clone());
+
+        let mut expected = VAULT_TRANSACTION_CREATE.to_vec();
+        expected.extend_from_slice(&[0, 0]);
+        expected.extend_from_slice(&u32::try_from(message.len()).unwrap().to_le_bytes());
+        expected.extend_from_slice(&message);
+        expected.push(0);
+        assert_eq!(create.program_id, SQUADS_PROGRAM_ID);
+        assert_eq!(create.data, expected);
+        assert_eq!(create.accounts[1].pubkey, transaction_pda(&key(1), 5));
+        assert!(create.accounts[2].is_signer && create.accounts[2].pubkey == key(2));
+        assert!(create.accounts[3].is_signer && create.accounts[3].is_writable);
+
+        let mut expected = PROPOSAL_CREATE.to_vec();
+        expected.extend_from_slice(&5_u64.to_le_bytes());
+        expected.push(0);
+        assert_eq!(propose.data, expected);
+        assert_eq!(propose.accounts[1].pubkey, proposal_pda(&key(1), 5));
+    }
+}
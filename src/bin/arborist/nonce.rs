@@ -0,0 +1,290 @@
+use anyhow::{Context, Result};
+use arborist::{
+    chain::ChainClient,
+    client::{SolanaClient, TransactionOutcome},
+};
+use serde::Serialize;
+use solana_sdk::{
+    nonce::{self, state::Data},
+    signature::Keypair,
+    signer::Signer,
+    system_instruction,
+};
+
+use crate::{
+    cli::{NonceAdvance, NonceCreate, NonceShow, NonceWithdraw},
+    output::Output,
+    signer::CommandSigners,
+};
+
+/// Result of the `nonce create` command
+#[derive(Debug, Serialize)]
+pub struct CreateResult {
+    #[serde(flatten)]
+    transaction: TransactionOutcome,
+    nonce_account: String,
+}
+
+impl Output for CreateResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        self.transaction.write_text(out)?;
+        writeln!(out, "Nonce account: {}", self.nonce_account)
+    }
+}
+
+/// Result of the `nonce show` command
+#[derive(Debug, Serialize)]
+pub struct ShowResult {
+    nonce_account: String,
+    authority: String,
+    blockhash: String,
+    lamports_per_signature: u64,
+    balance: u64,
+}
+
+impl Output for ShowResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        writeln!(out, "Nonce account: {}", self.nonce_account)?;
+        writeln!(out, "Authority: {}", self.authority)?;
+        writeln!(out, "Nonce blockhash: {}", self.blockhash)?;
+        writeln!(
+            out,
+            "Fee: {} lamports per signature",
+            self.lamports_per_signature
+        )?;
+        writeln!(out, "Balance: {} lamports", self.balance)
+    }
+}
+
+pub async fn create<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    args: NonceCreate,
+) -> Result<CreateResult> {
+    let NonceCreate {
+        authority,
+        lamports,
+    } = args;
+
+    let payer = signers.payer();
+    let nonce = Keypair::new();
+    let nonce_pubkey = nonce.try_pubkey().unwrap_or_else(|_| unreachable!());
+
+    let lamports = match lamports {
+        Some(l) => l,
+        None => client
+            .rent_exemption(nonce::State::size())
+            .await
+            .context("Error getting rent exemption balance for new nonce account")?,
+    };
+
+    let transaction = client
+        .send_transaction(
+            &system_instruction::create_nonce_account(
+                &payer,
+                &nonce_pubkey,
+                &authority.unwrap_or_else(|| signers.authority()),
+                lamports,
+            ),
+            Some(&payer),
+            &signers.with(&[&nonce]),
+        )
+        .await?;
+
+    Ok(CreateResult {
+        transaction,
+        nonce_account: nonce_pubkey.to_string(),
+    })
+}
+
+pub async fn show<C: ChainClient>(client: &SolanaClient<C>, args: NonceShow) -> Result<ShowResult> {
+    let NonceShow { account } = args;
+
+    let data = client.nonce_data(&account).await?;
+    let blockhash = data.blockhash();
+    let Data {
+        authority,
+        fee_calculator,
+        ..
+    } = data;
+    let balance = client
+        .balance(&account)
+        .await
+        .context("Error getting nonce account balance")?;
+
+    Ok(ShowResult {
+        nonce_account: account.to_string(),
+        authority: authority.to_string(),
+        blockhash: blockhash.to_string(),
+        lamports_per_signature: fee_calculator.lamports_per_signature,
+        balance,
+    })
+}
+
+pub async fn advance<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    args: NonceAdvance,
+) -> Result<TransactionOutcome> {
+    let NonceAdvance { account } = args;
+
+    client
+        .send_transaction(
+            &[system_instruction::advance_nonce_account(
+                &account,
+                &signers.authority(),
+            )],
+            Some(&signers.payer()),
+            &signers.with(&[]),
+        )
+        .await
+        .map_err(Into::into)
+}
+
+pub async fn withdraw<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &CommandSigners,
+    args: NonceWithdraw,
+) -> Result<TransactionOutcome> {
+    let NonceWithdraw {
+        account,
+        lamports,
+        recipient,
+    } = args;
+
+    client
+        .send_transaction(
+            &[system_instruction::withdraw_nonce_account(
+                &account,
+                &signers.authority(),
+                &recipient.unwrap_or_else(|| signers.payer()),
+                lamports,
+            )],
+            Some(&signers.payer()),
+            &signers.with(&[]),
+        )
+        .await
+        .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use arborist::{
+        chain::MockChain,
+        client::{ComputeBudget, DurableNonce, PriorityFee},
+    };
+    use solana_sdk::{
+        account::Account,
+        compute_budget,
+        hash::Hash,
+        nonce::state::{State, Versions},
+        pubkey::Pubkey,
+        system_program,
+    };
+
+    use super::*;
+    use crate::test_support::{block_on, command_signers};
+
+    fn nonce_account(state: State) -> Account {
+        Account::new_data(1_000_000, &Versions::new(state), &system_program::ID).unwrap()
+    }
+
+    #[test]
+    fn advances_nonce_before_other_instructions() {
+        let payer = Keypair::new();
+        let signers = command_signers(&payer);
+        let account = Pubkey::new_unique();
+        let data = Data::new(
+            payer.pubkey(),
+            nonce::state::DurableNonce::from_blockhash(&Hash::new_unique()),
+            5000,
+        );
+        let chain = MockChain::new();
+        chain.set_account(account, nonce_account(State::Initialized(data.clone())));
+
+        let client = SolanaClient::new(chain, false)
+            .compute_budget(ComputeBudget {
+                priority_fee: PriorityFee::Fixed(1),
+                unit_limit: Some(200_000),
+            })
+            .nonce(Some(DurableNonce {
+                account,
+                authority: None,
+            }));
+
+        let shown = block_on(show(&client, NonceShow { account })).unwrap();
+        assert_eq!(shown.authority, payer.pubkey().to_string());
+        assert_eq!(shown.blockhash, data.blockhash().to_string());
+        assert_eq!(shown.lamports_per_signature, 5000);
+
+        let recipient = Pubkey::new_unique();
+        block_on(withdraw(&client, &signers, NonceWithdraw {
+            account,
+            lamports: 1,
+            recipient: Some(recipient),
+        }))
+        .unwrap();
+
+        let sent = client.chain().sent();
+        assert_eq!(sent.len(), 1);
+        let message = &sent[0].message;
+        assert_eq!(*message.recent_blockhash(), data.blockhash());
+
+        let keys = message.static_account_keys();
+        let programs = message
+            .instructions()
+            .iter()
+            .map(|ix| keys[usize::from(ix.program_id_index)])
+            .collect::<Vec<_>>();
+        assert_eq!(programs, [
+            system_program::ID,
+            compute_budget::ID,
+            compute_budget::ID,
+            system_program::ID,
+        ]);
+
+        let advance = system_instruction::advance_nonce_account(&account, &payer.pubkey());
+        assert_eq!(message.instructions()[0].data, advance.data);
+    }
+
+    #[test]
+    fn rejects_unusable_nonce_accounts() {
+        let signers = command_signers(&Keypair::new());
+        let missing = Pubkey::new_unique();
+        let uninitialized = Pubkey::new_unique();
+        let chain = || {
+            let chain = MockChain::new();
+            chain.set_account(uninitialized, nonce_account(State::Uninitialized));
+            chain
+        };
+
+        let advance_with = |account| {
+            let client = SolanaClient::new(chain(), false).nonce(Some(DurableNonce {
+                account,
+                authority: None,
+            }));
+            let err = block_on(advance(&client, &signers, NonceAdvance {
+                account: Pubkey::new_unique(),
+            }))
+            .unwrap_err();
+            assert!(client.chain().sent().is_empty());
+
+            err
+        };
+
+        assert!(matches!(
+            advance_with(missing).downcast_ref(),
+            Some(arborist::Error::AccountNotFound(a)) if *a == missing
+        ));
+        assert!(matches!(
+            advance_with(uninitialized).downcast_ref(),
+            Some(arborist::Error::InvalidAccountData { kind: "nonce", .. })
+        ));
+
+        let client = SolanaClient::new(chain(), false);
+        assert!(block_on(show(&client, NonceShow {
+            account: uninitialized,
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Context, Result};
+use arborist::{
+    chain::ChainClient,
+    client::{SolanaClient, TransactionOutcome},
+};
+use solana_sdk::{signature::Signature, signer::Signer, transaction::VersionedTransaction};
+
+use crate::{
+    cli::Submit,
+    output::{ErrorCode, ResultExt},
+};
+
+/// Decode a serialized transaction encoded as either base58 or base64
+///
+/// # Errors
+/// This function fails if the input is not a valid transaction in either
+/// encoding.
+pub fn decode_transaction(encoded: &str) -> Result<VersionedTransaction> {
+    let encoded = encoded.trim();
+
+    [
+        bs58::decode(encoded).into_vec().ok(),
+        base64::decode(encoded).ok(),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|b| bincode::deserialize(&b).ok())
+    .context("Transaction is not a valid base58- or base64-encoded transaction")
+    .code(ErrorCode::InvalidArgument)
+}
+
+pub async fn submit<C: ChainClient>(
+    client: &SolanaClient<C>,
+    signers: &[Box<dyn Signer>],
+    args: Submit,
+) -> Result<TransactionOutcome> {
+    let Submit { transaction } = args;
+
+    let mut txn = decode_transaction(&transaction)?;
+    txn.sanitize(true)
+        .context("Transaction is malformed")
+        .code(ErrorCode::InvalidArgument)?;
+
+    let message = txn.message.serialize();
+    let num_signers = usize::from(txn.message.header().num_required_signatures);
+    let keys = &txn.message.static_account_keys()[..num_signers];
+
+    for signer in signers {
+        let pubkey = signer
+            .try_pubkey()
+            .context("Error getting signer public key")
+            .code(ErrorCode::Signer)?;
+        let idx = keys
+            .iter()
+            .position(|k| *k == pubkey)
+            .with_context(|| format!("{pubkey} is not a required signer of this transaction"))
+            .code(ErrorCode::InvalidArgument)?;
+
+        // Presigners verify their signature against the message when signing
+        txn.signatures[idx] = signer
+            .try_sign_message(&message)
+            .with_context(|| format!("Invalid signature for {pubkey}"))
+            .code(ErrorCode::Signer)?;
+    }
+
+    let absent = keys
+        .iter()
+        .zip(&txn.signatures)
+        .filter(|(_, s)| **s == Signature::default())
+        .map(|(k, _)| k.to_string())
+        .collect::<Vec<_>>();
+
+    if !absent.is_empty() {
+        return Err(anyhow!(
+            "Transaction is missing signatures for {}",
+            absent.join(", ")
+        ))
+        .code(ErrorCode::Signer);
+    }
+
+    if txn.verify_with_results().iter().any(|v| !v) {
+        return Err(anyhow!("Transaction contains an invalid signature")).code(ErrorCode::Signer);
+    }
+
+    client.submit_transaction(&txn).await.map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use arborist::chain::MockChain;
+    use solana_sdk::{
+        hash::Hash, message::Message, pubkey::Pubkey, signature::Keypair, system_instruction,
+        transaction::Transaction,
+    };
+
+    use super::*;
+    use crate::test_support::block_on;
+
+    #[test]
+    fn rejects_malformed_transactions() {
+        let payer = Keypair::new();
+        let message = Message::new(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &Pubkey::new_unique(),
+                1,
+            )],
+            Some(&payer.pubkey()),
+        );
+        let mut txn =
+            VersionedTransaction::from(Transaction::new(&[&payer], message, Hash::default()));
+        txn.signatures.clear();
+
+        let client = SolanaClient::new(MockChain::new(), false);
+        let err = block_on(submit(&client, &[Box::new(payer)], Submit {
+            transaction: base64::encode(bincode::serialize(&txn).unwrap()),
+        }))
+        .unwrap_err();
+
+        assert_eq!(ErrorCode::of(&err), ErrorCode::InvalidArgument);
+        assert!(client.chain().sent().is_empty());
+    }
+}
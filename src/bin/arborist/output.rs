@@ -0,0 +1,487 @@
+use std::{fmt, io::Write};
+
+use anyhow::{bail, Context, Result};
+use arborist::client::{AccountChange, SignOnly, Simulation, TransactionOutcome};
+use serde::Serialize;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_sdk::signer::SignerError;
+
+/// Format used for printing command results and errors
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    #[value(alias = "table")]
+    Text,
+    /// Pretty-printed JSON
+    Json,
+    /// JSON, printed on a single line
+    JsonCompact,
+    /// Comma-separated values, only supported for tabular results
+    Csv,
+}
+
+/// A command result that can be printed in any [`OutputFormat`]
+pub trait Output: Serialize {
+    /// Write a human-readable representation of this result
+    ///
+    /// # Errors
+    /// This function fails if writing to `out` fails.
+    fn write_text(&self, out: &mut dyn Write) -> std::io::Result<()>;
+
+    /// Write this result as CSV, if it is tabular
+    ///
+    /// # Errors
+    /// This function fails if the result is not tabular or writing to `out`
+    /// fails.
+    fn write_csv(&self, out: &mut dyn Write) -> Result<()> {
+        let _ = out;
+        bail!("CSV output is not supported for this command");
+    }
+}
+
+impl OutputFormat {
+    #[inline]
+    #[must_use]
+    pub fn is_text(self) -> bool { self == Self::Text }
+
+    /// Print a command result to stdout
+    ///
+    /// # Errors
+    /// This function fails if the result cannot be serialized in this format
+    /// or writing to stdout fails.
+    pub fn print(self, output: &impl Output) -> Result<()> {
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+
+        self.write(output, &mut stdout)?;
+        stdout.flush().context("Error flushing output")
+    }
+
+    /// Print an error to stderr
+    pub fn print_error(self, err: &anyhow::Error) {
+        let stderr = std::io::stderr();
+        self.write_error(err, &mut stderr.lock()).ok();
+    }
+
+    fn write(self, output: &impl Output, out: &mut dyn Write) -> Result<()> {
+        match self {
+            Self::Text => output.write_text(out)?,
+            Self::Json => {
+                serde_json::to_writer_pretty(&mut *out, output)
+                    .context("Error serializing output to JSON")?;
+                writeln!(out)?;
+            },
+            Self::JsonCompact => {
+                serde_json::to_writer(&mut *out, output)
+                    .context("Error serializing output to JSON")?;
+                writeln!(out)?;
+            },
+            Self::Csv => output.write_csv(out)?,
+        }
+
+        Ok(())
+    }
+
+    fn write_error(self, err: &anyhow::Error, out: &mut dyn Write) -> std::io::Result<()> {
+        let report = || ErrorReport {
+            error: ErrorBody {
+                code: ErrorCode::of(err),
+                message: err.to_string(),
+                causes: err.chain().skip(1).map(ToString::to_string).collect(),
+            },
+        };
+
+        match self {
+            Self::Text | Self::Csv => writeln!(out, "ERROR: {err:?}"),
+            Self::Json => writeln!(
+                out,
+                "{}",
+                serde_json::to_string_pretty(&report()).unwrap_or_else(|_| unreachable!())
+            ),
+            Self::JsonCompact => writeln!(
+                out,
+                "{}",
+                serde_json::to_string(&report()).unwrap_or_else(|_| unreachable!())
+            ),
+        }
+    }
+}
+
+impl Output for TransactionOutcome {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        match self {
+            Self::Confirmed { signature } => {
+                writeln!(out, "Success! Transaction signature: {signature}")
+            },
+            Self::Simulated(Simulation {
+                units_consumed,
+                logs,
+                account_changes,
+            }) => {
+                writeln!(out, "Simulation succeeded; no transaction was sent.")?;
+
+                if let Some(units) = units_consumed {
+                    writeln!(out, "Compute units consumed: {units}")?;
+                }
+
+                writeln!(out, "Program logs:")?;
+                for log in logs {
+                    writeln!(out, "  {log}")?;
+                }
+
+                writeln!(out, "Account changes:")?;
+                for AccountChange {
+                    address,
+                    lamports_before,
+                    lamports_after,
+                    data_len_before,
+                    data_len_after,
+                    owner_before,
+                    owner_after,
+                } in account_changes
+                {
+                    let owner = |o: &Option<String>| o.clone().unwrap_or_else(|| "none".into());
+
+                    writeln!(
+                        out,
+                        "  {address}: lamports {lamports_before} -> {lamports_after}, data \
+                         {data_len_before} -> {data_len_after} bytes, owner {} -> {}",
+                        owner(owner_before),
+                        owner(owner_after),
+                    )?;
+                }
+
+                Ok(())
+            },
+            Self::SignOnly(SignOnly {
+                blockhash,
+                signers,
+                absent_signers,
+                transaction_base58,
+                transaction_base64,
+            }) => {
+                writeln!(out, "Blockhash: {blockhash}")?;
+
+                writeln!(out, "Signers (Pubkey=Signature):")?;
+                for signer in signers {
+                    writeln!(out, "  {signer}")?;
+                }
+
+                if !absent_signers.is_empty() {
+                    writeln!(out, "Absent Signers (Pubkey):")?;
+                    for signer in absent_signers {
+                        writeln!(out, "  {signer}")?;
+                    }
+                }
+
+                writeln!(out, "Transaction (base58): {transaction_base58}")?;
+                writeln!(out, "Transaction (base64): {transaction_base64}")
+            },
+        }
+    }
+}
+
+/// Result of a command that may send several transactions
+#[derive(Debug, Serialize)]
+pub struct TransactionsResult {
+    pub transactions: Vec<TransactionOutcome>,
+}
+
+impl Output for TransactionsResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        for txn in &self.transactions {
+            txn.write_text(out)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Stable, machine-readable error codes reported with structured errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCode {
+    /// A command-line argument or combination of arguments was invalid
+    InvalidArgument,
+    /// The Solana CLI or Arborist configuration could not be loaded
+    Config,
+    /// A signer could not be loaded or failed to sign
+    Signer,
+    /// An RPC request failed
+    Rpc,
+    /// A transaction was submitted but failed to execute
+    TransactionFailed,
+    /// A transaction was not processed before its blockhash expired, and was
+    /// not retried any further
+    TransactionExpired,
+    /// The status of a submitted transaction could not be determined
+    TransactionStatusUnknown,
+    /// A request to the DAS API failed
+    Das,
+    /// A local I/O operation failed
+    Io,
+    /// An unclassified error occurred
+    Unknown,
+}
+
+impl ErrorCode {
+    /// Determine the code for an error by inspecting its chain of causes
+    #[must_use]
+    pub fn of(err: &anyhow::Error) -> Self {
+        if let Some(c) = err.chain().find_map(|e| e.downcast_ref::<Coded>()) {
+            return c.code;
+        }
+
+        for err in err.chain() {
+            if let Some(e) = err.downcast_ref::<arborist::Error>() {
+                match e {
+                    arborist::Error::TransactionFailed { .. } => return Self::TransactionFailed,
+                    arborist::Error::Expired { .. } => return Self::TransactionExpired,
+                    arborist::Error::StatusUnknown { .. } => {
+                        return Self::TransactionStatusUnknown
+                    },
+                    arborist::Error::MissingSigners(_) | arborist::Error::Signer(_) => {
+                        return Self::Signer
+                    },
+                    arborist::Error::Rpc(e) => return Self::of_client_error(e),
+                    _ => (),
+                }
+            }
+
+            if let Some(e) = err.downcast_ref::<ClientError>() {
+                return Self::of_client_error(e);
+            }
+
+            if err.is::<SignerError>() {
+                return Self::Signer;
+            }
+
+            if err.is::<reqwest::Error>() {
+                return Self::Das;
+            }
+
+            if err.is::<std::io::Error>() {
+                return Self::Io;
+            }
+        }
+
+        Self::Unknown
+    }
+
+    fn of_client_error(err: &ClientError) -> Self {
+        match err.kind() {
+            k if k.get_transaction_error().is_some() => Self::TransactionFailed,
+            ClientErrorKind::SigningError(_) => Self::Signer,
+            _ => Self::Rpc,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorReport {
+    error: ErrorBody,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: ErrorCode,
+    message: String,
+    causes: Vec<String>,
+}
+
+/// Transparent wrapper tagging an error with an [`ErrorCode`]
+#[derive(Debug)]
+struct Coded {
+    code: ErrorCode,
+    inner: anyhow::Error,
+}
+
+impl fmt::Display for Coded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { fmt::Display::fmt(&self.inner, f) }
+}
+
+impl std::error::Error for Coded {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { self.inner.source() }
+}
+
+/// Extension trait for tagging errors with an [`ErrorCode`]
+pub trait ResultExt<T> {
+    /// Tag the error variant of this result with the given code, without
+    /// altering its message
+    ///
+    /// # Errors
+    /// This function fails if `self` is an error.
+    fn code(self, code: ErrorCode) -> Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> ResultExt<T> for Result<T, E> {
+    fn code(self, code: ErrorCode) -> Result<T> {
+        self.map_err(|e| {
+            Coded {
+                code,
+                inner: e.into(),
+            }
+            .into()
+        })
+    }
+}
+
+/// Write a set of rows as an aligned plain-text table
+///
+/// # Errors
+/// This function fails if writing to `out` fails.
+pub fn write_table<const N: usize>(
+    headers: [&str; N],
+    rows: impl IntoIterator<Item = [String; N]>,
+    out: &mut dyn Write,
+) -> std::io::Result<()> {
+    use std::fmt::Write as _;
+
+    let rows = rows.into_iter().collect::<Vec<_>>();
+
+    let mut widths = headers.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut line = String::new();
+    for row in std::iter::once(headers.map(String::from)).chain(rows) {
+        line.clear();
+
+        for (i, (cell, width)) in row.iter().zip(widths).enumerate() {
+            if i > 0 {
+                line.push_str("  ");
+            }
+
+            write!(line, "{cell:width$}").unwrap_or_else(|_| unreachable!());
+        }
+
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use solana_sdk::transaction::TransactionError;
+
+    use super::*;
+
+    fn error_output(format: OutputFormat, err: &anyhow::Error) -> String {
+        let mut out = vec![];
+        format.write_error(err, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn prints_error_envelope() {
+        let err = Err::<(), _>(anyhow!("Account not found"))
+            .context("Error loading tree")
+            .code(ErrorCode::InvalidArgument)
+            .unwrap_err();
+
+        assert_eq!(
+            error_output(OutputFormat::JsonCompact, &err),
+            "{\"error\":{\"code\":\"invalid-argument\",\"message\":\"Error loading \
+             tree\",\"causes\":[\"Account not found\"]}}\n"
+        );
+
+        let pretty: serde_json::Value =
+            serde_json::from_str(&error_output(OutputFormat::Json, &err)).unwrap();
+        assert_eq!(
+            pretty,
+            serde_json::json!({
+                "error": {
+                    "code": "invalid-argument",
+                    "message": "Error loading tree",
+                    "causes": ["Account not found"],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn error_codes_are_stable() {
+        let codes = [
+            (ErrorCode::InvalidArgument, "invalid-argument"),
+            (ErrorCode::Config, "config"),
+            (ErrorCode::Signer, "signer"),
+            (ErrorCode::Rpc, "rpc"),
+            (ErrorCode::TransactionFailed, "transaction-failed"),
+            (ErrorCode::TransactionExpired, "transaction-expired"),
+            (ErrorCode::TransactionStatusUnknown, "transaction-status-unknown"),
+            (ErrorCode::Das, "das"),
+            (ErrorCode::Io, "io"),
+            (ErrorCode::Unknown, "unknown"),
+        ];
+
+        for (code, name) in codes {
+            assert_eq!(serde_json::to_value(code).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let failed = ClientError::from(TransactionError::InsufficientFundsForFee);
+        let io = || std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+
+        let cases = [
+            (anyhow::Error::from(failed), ErrorCode::TransactionFailed),
+            (
+                arborist::Error::Rpc(Box::new(ClientError::from(io()))).into(),
+                ErrorCode::Rpc,
+            ),
+            (
+                arborist::Error::Expired { attempts: 3 }.into(),
+                ErrorCode::TransactionExpired,
+            ),
+            (
+                arborist::Error::MissingSigners(vec![]).into(),
+                ErrorCode::Signer,
+            ),
+            (
+                anyhow::Error::from(io()).context("Error reading snapshot"),
+                ErrorCode::Io,
+            ),
+            (anyhow!("Something else"), ErrorCode::Unknown),
+        ];
+
+        for (err, code) in cases {
+            assert_eq!(ErrorCode::of(&err), code, "{err:#}");
+        }
+    }
+
+    #[test]
+    fn compact_json_is_one_line() {
+        let result = TransactionsResult {
+            transactions: vec![
+                TransactionOutcome::Confirmed {
+                    signature: "first".into(),
+                },
+                TransactionOutcome::Confirmed {
+                    signature: "second".into(),
+                },
+            ],
+        };
+
+        let mut out = vec![];
+        OutputFormat::JsonCompact.write(&result, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(out.lines().count(), 1);
+        assert!(out.ends_with('\n'));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&out).unwrap(),
+            serde_json::to_value(&result).unwrap()
+        );
+
+        let mut out = vec![];
+        OutputFormat::Json.write(&result, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().lines().count() > 1);
+    }
+}
@@ -0,0 +1,455 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    cli::{ConfigGet, ConfigSet, TreeArg},
+    output::{ErrorCode, Output, ResultExt},
+};
+
+/// Default name of the project configuration file, read from the current
+/// directory
+pub const DEFAULT_PATH: &str = "arborist.toml";
+
+/// Contents of an `arborist.toml` project configuration file
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Name of the profile to use when `--profile` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// Named sets of connection and signing defaults
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+
+    /// Merkle trees that can be referred to by name with `--tree`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub trees: BTreeMap<String, NamedTree>,
+}
+
+/// Defaults for global options, overridden by any options given on the
+/// command line
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// RPC endpoint URL or moniker, as with `--url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// RPC commitment level, as with `--commitment`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+
+    /// Signer source for the default keypair, as with `--keypair`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keypair: Option<String>,
+
+    /// DAS API endpoint URL, as with `--das-url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub das_url: Option<String>,
+
+    /// Priority fee in micro-lamports per compute unit, as with
+    /// `--priority-fee`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_fee: Option<u64>,
+}
+
+/// A Merkle tree registered under a name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamedTree {
+    /// Address of the Merkle tree account
+    pub address: String,
+
+    /// Depth of the tree, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<u8>,
+
+    /// Buffer size of the tree, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffer_size: Option<u16>,
+
+    /// Canopy depth of the tree, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canopy_depth: Option<u8>,
+}
+
+/// A loaded project configuration, along with the path it was read from
+#[derive(Debug)]
+pub struct Project {
+    path: PathBuf,
+    config: ProjectConfig,
+}
+
+impl Project {
+    /// Load the project configuration at `path`, or an empty configuration if
+    /// the file does not exist
+    ///
+    /// # Errors
+    /// This function fails if the file exists but cannot be read or parsed.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let config = match std::fs::read_to_string(&path) {
+            Ok(s) => toml::from_str(&s)
+                .with_context(|| format!("Error parsing project config {}", path.display()))
+                .code(ErrorCode::Config)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ProjectConfig::default(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Error reading project config {}", path.display()))
+                    .code(ErrorCode::Config);
+            },
+        };
+
+        Ok(Self { path, config })
+    }
+
+    /// Select the profile named by `name`, or by the configuration's default
+    /// profile if `name` is not given.  Returns an empty profile if neither is
+    /// set.
+    ///
+    /// # Errors
+    /// This function fails if the named profile does not exist.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let Some(name) = name.or(self.config.profile.as_deref()) else {
+            return Ok(Profile::default());
+        };
+
+        self.config
+            .profiles
+            .get(name)
+            .cloned()
+            .with_context(|| {
+                format!(
+                    "No profile named {name:?} in project config {}",
+                    self.path.display()
+                )
+            })
+            .code(ErrorCode::Config)
+    }
+
+    /// Resolve a tree given by address or by name
+    ///
+    /// # Errors
+    /// This function fails if the name is not registered or its address is
+    /// invalid.
+    pub fn tree(&self, tree: &TreeArg) -> Result<Pubkey> {
+        let name = match tree {
+            TreeArg::Address(a) => return Ok(*a),
+            TreeArg::Name(n) => n,
+        };
+
+        let NamedTree { address, .. } = self
+            .config
+            .trees
+            .get(name)
+            .with_context(|| {
+                format!(
+                    "{name:?} is neither a valid address nor a tree named in project config {}",
+                    self.path.display()
+                )
+            })
+            .code(ErrorCode::InvalidArgument)?;
+
+        address
+            .parse()
+            .with_context(|| format!("Invalid address for tree {name:?} in project config"))
+            .code(ErrorCode::Config)
+    }
+
+    /// Resolve a list of trees given by address or by name
+    ///
+    /// # Errors
+    /// This function fails if any tree cannot be resolved.
+    pub fn trees<'a>(&self, trees: impl IntoIterator<Item = &'a TreeArg>) -> Result<Vec<Pubkey>> {
+        trees.into_iter().map(|t| self.tree(t)).collect()
+    }
+
+    /// Whether a tree is registered under a name
+    #[must_use]
+    pub fn has_tree(&self, name: &str) -> bool { self.config.trees.contains_key(name) }
+
+    /// Register a tree under a name and save the configuration
+    ///
+    /// # Errors
+    /// This function fails if the name is already in use or the
+    /// configuration cannot be saved.
+    pub fn register_tree(&mut self, name: String, tree: NamedTree) -> Result<()> {
+        if self.config.trees.contains_key(&name) {
+            return Err(anyhow!(
+                "A tree named {name:?} already exists in project config {}",
+                self.path.display()
+            ))
+            .code(ErrorCode::InvalidArgument);
+        }
+
+        self.config.trees.insert(name, tree);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let toml =
+            toml::to_string_pretty(&self.config).context("Error serializing project config")?;
+
+        std::fs::write(&self.path, toml)
+            .with_context(|| format!("Error writing project config {}", self.path.display()))
+            .code(ErrorCode::Io)
+    }
+}
+
+/// Flatten a TOML table into dotted keys and their values
+fn flatten(prefix: Option<&str>, table: &toml::Table, out: &mut Vec<(String, toml::Value)>) {
+    for (key, value) in table {
+        let key = prefix.map_or_else(|| key.clone(), |p| format!("{p}.{key}"));
+
+        match value {
+            toml::Value::Table(t) => flatten(Some(&key), t, out),
+            v => out.push((key, v.clone())),
+        }
+    }
+}
+
+fn to_table(config: &ProjectConfig) -> Result<toml::Table> {
+    match toml::Value::try_from(config).context("Error serializing project config")? {
+        toml::Value::Table(t) => Ok(t),
+        _ => unreachable!("Project config did not serialize to a table"),
+    }
+}
+
+/// A single configuration value
+#[derive(Debug, Serialize)]
+pub struct ConfigEntry {
+    key: String,
+    value: toml::Value,
+}
+
+impl Output for ConfigEntry {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        writeln!(out, "{} = {}", self.key, self.value)
+    }
+}
+
+/// Result of the `config list` command
+#[derive(Debug, Serialize)]
+pub struct ListResult {
+    path: String,
+    entries: Vec<ConfigEntry>,
+}
+
+impl Output for ListResult {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        writeln!(out, "Project config: {}", self.path)?;
+
+        for entry in &self.entries {
+            entry.write_text(out)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn get(project: &Project, args: ConfigGet) -> Result<ConfigEntry> {
+    let ConfigGet { key } = args;
+
+    let mut value = toml::Value::Table(to_table(&project.config)?);
+    for part in key.split('.') {
+        value = value
+            .get(part)
+            .cloned()
+            .with_context(|| format!("{key} is not set in project config"))
+            .code(ErrorCode::InvalidArgument)?;
+    }
+
+    Ok(ConfigEntry { key, value })
+}
+
+pub fn set(project: &mut Project, args: ConfigSet) -> Result<ConfigEntry> {
+    let ConfigSet { key, value } = args;
+
+    // Accept any TOML literal (e.g. a number), falling back to a bare string
+    let value = format!("v = {value}")
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or(toml::Value::String(value));
+
+    let mut table = to_table(&project.config)?;
+    let mut parts = key.split('.').collect::<Vec<_>>();
+    let Some(last) = parts.pop().filter(|l| !l.is_empty()) else {
+        bail!("Invalid config key {key:?}");
+    };
+
+    let mut current = &mut table;
+    for part in parts {
+        current = current
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .with_context(|| format!("{part} in {key} is not a table"))
+            .code(ErrorCode::InvalidArgument)?;
+    }
+    current.insert(last.to_owned(), value.clone());
+
+    project.config = toml::Value::Table(table)
+        .try_into()
+        .with_context(|| format!("Invalid value for {key}"))
+        .code(ErrorCode::InvalidArgument)?;
+    project.save()?;
+
+    Ok(ConfigEntry { key, value })
+}
+
+pub fn list(project: &Project) -> Result<ListResult> {
+    let mut entries = vec![];
+    flatten(None, &to_table(&project.config)?, &mut entries);
+
+    Ok(ListResult {
+        path: project.path.display().to_string(),
+        entries: entries
+            .into_iter()
+            .map(|(key, value)| ConfigEntry { key, value })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    const CONFIG: &str = r#"
+profile = "devnet"
+
+[profiles.devnet]
+url = "devnet"
+priority_fee = 10
+
+[profiles.mainnet]
+url = "mainnet-beta"
+das_url = "https://das.example.com"
+
+[trees.drop]
+address = "8cS8HnB1o8nYdHM9QDHyUQdYmA2cN7mX2pNX6bq6jRdE"
+depth = 14
+
+[trees.broken]
+address = "not-an-address"
+"#;
+
+    fn project(dir: &TempDir) -> Project {
+        std::fs::write(dir.path().join(DEFAULT_PATH), CONFIG).unwrap();
+        dir.project()
+    }
+
+    fn tree(s: &str) -> TreeArg { s.parse().unwrap_or_else(|_| unreachable!()) }
+
+    #[test]
+    fn selects_profiles() {
+        let dir = TempDir::new("project");
+        let project = project(&dir);
+
+        let devnet = project.profile(None).unwrap();
+        assert_eq!(devnet.url.as_deref(), Some("devnet"));
+        assert_eq!(devnet.priority_fee, Some(10));
+
+        let mainnet = project.profile(Some("mainnet")).unwrap();
+        assert_eq!(mainnet.url.as_deref(), Some("mainnet-beta"));
+        assert_eq!(mainnet.das_url.as_deref(), Some("https://das.example.com"));
+
+        let err = project.profile(Some("testnet")).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), ErrorCode::Config);
+
+        let empty = TempDir::new("project").project();
+        assert!(empty.profile(None).unwrap().url.is_none());
+    }
+
+    #[test]
+    fn resolves_named_trees() {
+        let dir = TempDir::new("project");
+        let project = project(&dir);
+        let address = "8cS8HnB1o8nYdHM9QDHyUQdYmA2cN7mX2pNX6bq6jRdE".parse().unwrap();
+        let other = Pubkey::new_unique();
+
+        assert_eq!(project.tree(&tree("drop")).unwrap(), address);
+        assert_eq!(
+            project
+                .trees(&[tree("drop"), tree(&other.to_string())])
+                .unwrap(),
+            [address, other]
+        );
+
+        let err = project.tree(&tree("missing")).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), ErrorCode::InvalidArgument);
+        let err = project.tree(&tree("broken")).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), ErrorCode::Config);
+    }
+
+    #[test]
+    fn saves_registered_trees() {
+        let dir = TempDir::new("project");
+        let mut project = project(&dir);
+        let address = Pubkey::new_unique();
+        let named = NamedTree {
+            address: address.to_string(),
+            depth: Some(3),
+            buffer_size: Some(8),
+            canopy_depth: Some(0),
+        };
+
+        project.register_tree("sapling".into(), named.clone()).unwrap();
+        let err = project.register_tree("drop".into(), named).unwrap_err();
+        assert_eq!(ErrorCode::of(&err), ErrorCode::InvalidArgument);
+
+        let reloaded = dir.project();
+        assert!(reloaded.has_tree("sapling"));
+        assert_eq!(reloaded.tree(&tree("sapling")).unwrap(), address);
+        assert_eq!(
+            get(&reloaded, ConfigGet {
+                key: "trees.sapling.buffer_size".into(),
+            })
+            .unwrap()
+            .value,
+            toml::Value::Integer(8)
+        );
+        assert_eq!(reloaded.profile(None).unwrap().priority_fee, Some(10));
+    }
+
+    #[test]
+    fn sets_values_round_trip() {
+        let dir = TempDir::new("project");
+        let mut project = project(&dir);
+
+        set(&mut project, ConfigSet {
+            key: "profiles.mainnet.priority_fee".into(),
+            value: "250".into(),
+        })
+        .unwrap();
+        set(&mut project, ConfigSet {
+            key: "profile".into(),
+            value: "mainnet".into(),
+        })
+        .unwrap();
+        assert!(set(&mut project, ConfigSet {
+            key: "profiles.mainnet.unknown".into(),
+            value: "1".into(),
+        })
+        .is_err());
+
+        let reloaded = dir.project();
+        let profile = reloaded.profile(None).unwrap();
+        assert_eq!(profile.url.as_deref(), Some("mainnet-beta"));
+        assert_eq!(profile.priority_fee, Some(250));
+
+        let keys = list(&reloaded)
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| e.key)
+            .collect::<Vec<_>>();
+        assert!(keys.contains(&"profiles.mainnet.priority_fee".to_owned()));
+        assert!(keys.contains(&"trees.drop.depth".to_owned()));
+    }
+}
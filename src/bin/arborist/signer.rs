@@ -0,0 +1,746 @@
+//! Copied from `solana-clap-v3-utils`.
+
+use std::{
+    convert::TryFrom,
+    error,
+    io::{stdin, stdout, Write},
+    process::exit,
+    str::FromStr,
+};
+
+use bip39::{Language, Mnemonic, Seed};
+use rpassword::prompt_password;
+use solana_clap_v3_utils::{
+    input_parsers::STDOUT_OUTFILE_TOKEN,
+    keypair::{ASK_KEYWORD, SKIP_SEED_PHRASE_VALIDATION_ARG},
+};
+use solana_remote_wallet::locator::{
+    Locator as RemoteWalletLocator, LocatorError as RemoteWalletLocatorError,
+};
+use solana_sdk::{
+    derivation_path::{DerivationPath, DerivationPathError},
+    pubkey::Pubkey,
+    signature::{
+        generate_seed_from_seed_phrase_and_passphrase, keypair_from_seed,
+        keypair_from_seed_and_derivation_path, keypair_from_seed_phrase_and_passphrase,
+        read_keypair, read_keypair_file, Keypair, NullSigner, Presigner, Signature, Signer,
+    },
+};
+use thiserror::Error;
+
+use crate::cli::SignerRoleArgs;
+
+#[derive(Default, clap::Args)]
+#[allow(clippy::module_name_repetitions)]
+pub struct SignerArgs {
+    #[arg(
+        long = SKIP_SEED_PHRASE_VALIDATION_ARG.long,
+        help = SKIP_SEED_PHRASE_VALIDATION_ARG.help,
+        global = true,
+    )]
+    skip_seed_phrase_validation: bool,
+
+    /// Confirm key on device; only relevant if using remote wallet
+    #[arg(long = "confirm-key")]
+    confirm_pubkey: bool,
+}
+
+struct SignerSource {
+    kind: SignerSourceKind,
+    derivation_path: Option<DerivationPath>,
+    legacy: bool,
+}
+
+impl SignerSource {
+    fn new(kind: SignerSourceKind) -> Self {
+        Self {
+            kind,
+            derivation_path: None,
+            legacy: false,
+        }
+    }
+
+    fn new_legacy(kind: SignerSourceKind) -> Self {
+        Self {
+            kind,
+            derivation_path: None,
+            legacy: true,
+        }
+    }
+}
+
+const SIGNER_SOURCE_PROMPT: &str = "prompt";
+const SIGNER_SOURCE_FILEPATH: &str = "file";
+const SIGNER_SOURCE_USB: &str = "usb";
+const SIGNER_SOURCE_STDIN: &str = "stdin";
+const SIGNER_SOURCE_PUBKEY: &str = "pubkey";
+const SIGNER_SOURCE_PRESIGNER: &str = "presigner";
+const SIGNER_SOURCE_KEYSTORE: &str = "keystore";
+const SIGNER_SOURCE_ENV: &str = "env";
+const SIGNER_SOURCE_BASE58: &str = "base58";
+const SIGNER_SOURCE_JSON: &str = "json";
+
+enum SignerSourceKind {
+    Prompt,
+    Filepath(String),
+    Usb(RemoteWalletLocator),
+    Stdin,
+    Pubkey(Pubkey),
+    Presigner(Pubkey, Signature),
+    Keystore(String),
+    Env(String),
+    Base58(String),
+    Json(String),
+}
+
+impl AsRef<str> for SignerSourceKind {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Prompt => SIGNER_SOURCE_PROMPT,
+            Self::Filepath(_) => SIGNER_SOURCE_FILEPATH,
+            Self::Usb(_) => SIGNER_SOURCE_USB,
+            Self::Stdin => SIGNER_SOURCE_STDIN,
+            Self::Pubkey(_) => SIGNER_SOURCE_PUBKEY,
+            Self::Presigner(..) => SIGNER_SOURCE_PRESIGNER,
+            Self::Keystore(_) => SIGNER_SOURCE_KEYSTORE,
+            Self::Env(_) => SIGNER_SOURCE_ENV,
+            Self::Base58(_) => SIGNER_SOURCE_BASE58,
+            Self::Json(_) => SIGNER_SOURCE_JSON,
+        }
+    }
+}
+
+impl std::fmt::Debug for SignerSourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { f.write_str(self.as_ref()) }
+}
+
+#[derive(Debug, Error)]
+enum SignerSourceError {
+    #[error("unrecognized signer source")]
+    UnrecognizedSource,
+    #[error(transparent)]
+    RemoteWalletLocatorError(#[from] RemoteWalletLocatorError),
+    #[error(transparent)]
+    DerivationPathError(#[from] DerivationPathError),
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
+
+fn parse_signer_source<S: AsRef<str>>(source: S) -> Result<SignerSource, SignerSourceError> {
+    let source = source.as_ref();
+    let source = {
+        #[cfg(target_family = "windows")]
+        {
+            // trim matched single-quotes since cmd.exe won't
+            let mut source = source;
+            while let Some(trimmed) = source.strip_prefix('\'') {
+                source = if let Some(trimmed) = trimmed.strip_suffix('\'') {
+                    trimmed
+                } else {
+                    break;
+                }
+            }
+            source.replace("\\", "/")
+        }
+        #[cfg(not(target_family = "windows"))]
+        {
+            source.to_string()
+        }
+    };
+    if let Some(kind) = parse_inline_source(&source) {
+        return Ok(SignerSource::new(kind));
+    }
+    match uriparse::URIReference::try_from(source.as_str()) {
+        Err(_) => Err(SignerSourceError::UnrecognizedSource),
+        Ok(uri) => {
+            if let Some(scheme) = uri.scheme() {
+                let scheme = scheme.as_str().to_ascii_lowercase();
+                match scheme.as_str() {
+                    SIGNER_SOURCE_PROMPT => Ok(SignerSource {
+                        kind: SignerSourceKind::Prompt,
+                        derivation_path: DerivationPath::from_uri_any_query(&uri)?,
+                        legacy: false,
+                    }),
+                    SIGNER_SOURCE_FILEPATH => Ok(SignerSource::new(SignerSourceKind::Filepath(
+                        uri.path().to_string(),
+                    ))),
+                    SIGNER_SOURCE_USB => Ok(SignerSource {
+                        kind: SignerSourceKind::Usb(RemoteWalletLocator::new_from_uri(&uri)?),
+                        derivation_path: DerivationPath::from_uri_key_query(&uri)?,
+                        legacy: false,
+                    }),
+                    SIGNER_SOURCE_STDIN => Ok(SignerSource::new(SignerSourceKind::Stdin)),
+                    SIGNER_SOURCE_KEYSTORE => Ok(SignerSource::new(SignerSourceKind::Keystore(
+                        uri.path().to_string(),
+                    ))),
+                    _ => {
+                        #[cfg(target_family = "windows")]
+                        // On Windows, an absolute path's drive letter will be parsed as the URI
+                        // scheme. Assume a filepath source in case of a single character shceme.
+                        if scheme.len() == 1 {
+                            return Ok(SignerSource::new(SignerSourceKind::Filepath(source)));
+                        }
+                        Err(SignerSourceError::UnrecognizedSource)
+                    },
+                }
+            } else {
+                match source.as_str() {
+                    STDOUT_OUTFILE_TOKEN => Ok(SignerSource::new(SignerSourceKind::Stdin)),
+                    ASK_KEYWORD => Ok(SignerSource::new_legacy(SignerSourceKind::Prompt)),
+                    _ => match Pubkey::from_str(source.as_str()) {
+                        Ok(pubkey) => Ok(SignerSource::new(SignerSourceKind::Pubkey(pubkey))),
+                        Err(_) => match parse_presigner(source.as_str()) {
+                            Ok((pubkey, sig)) => {
+                                Ok(SignerSource::new(SignerSourceKind::Presigner(pubkey, sig)))
+                            },
+                            Err(_) => std::fs::metadata(source.as_str())
+                                .map(|_| SignerSource::new(SignerSourceKind::Filepath(source)))
+                                .map_err(Into::into),
+                        },
+                    },
+                }
+            }
+        },
+    }
+}
+
+/// Parse sources whose contents are not necessarily valid URI paths, such as
+/// an inline JSON keypair
+fn parse_inline_source(source: &str) -> Option<SignerSourceKind> {
+    let (scheme, rest) = source.split_once(':')?;
+    match scheme.to_ascii_lowercase().as_str() {
+        SIGNER_SOURCE_ENV => Some(SignerSourceKind::Env(rest.to_owned())),
+        SIGNER_SOURCE_BASE58 => Some(SignerSourceKind::Base58(rest.to_owned())),
+        SIGNER_SOURCE_JSON => Some(SignerSourceKind::Json(rest.to_owned())),
+        _ => None,
+    }
+}
+
+/// Decode a keypair from its 64 secret key bytes.  Errors deliberately omit
+/// the input, since it is secret.
+fn keypair_from_bytes(bytes: &[u8]) -> Result<Keypair, String> {
+    if bytes.len() != 64 {
+        return Err(format!("expected 64 bytes, found {}", bytes.len()));
+    }
+
+    Keypair::from_bytes(bytes).map_err(|_| "not a valid ed25519 keypair".to_owned())
+}
+
+fn keypair_from_base58(secret: &str) -> Result<Keypair, String> {
+    let bytes = bs58::decode(secret.trim())
+        .into_vec()
+        .map_err(|_| "not a valid base58 string".to_owned())?;
+    keypair_from_bytes(&bytes)
+}
+
+fn keypair_from_json(secret: &str) -> Result<Keypair, String> {
+    let bytes: Vec<u8> =
+        serde_json::from_str(secret).map_err(|_| "not a JSON array of bytes".to_owned())?;
+    keypair_from_bytes(&bytes)
+}
+
+fn prompt_passphrase(prompt: &str) -> Result<String, Box<dyn error::Error>> {
+    let passphrase = prompt_password(prompt)?;
+    if !passphrase.is_empty() {
+        let confirmed = rpassword::prompt_password("Enter same passphrase again: ")?;
+        if confirmed != passphrase {
+            return Err("Passphrases did not match".into());
+        }
+    }
+    Ok(passphrase)
+}
+
+/// Options controlling which signer sources [`signer_from_path`] accepts
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct SignerFromPathConfig {
+    /// Accept a bare public key, producing a signer that leaves its signature
+    /// absent.  Only useful when signing transactions offline.
+    pub allow_null_signer: bool,
+}
+
+/// Load a signer from any supported source: a keypair file, `stdin`, a seed
+/// phrase prompt, an encrypted `keystore:` file, an `env:` variable, an inline
+/// `base58:` or `json:` secret key, a `usb://` remote wallet, a
+/// `PUBKEY=SIGNATURE` presigner, or (if allowed by `config`) a bare public key
+pub(crate) fn signer_from_path(
+    args: &SignerArgs,
+    path: &str,
+    keypair_name: &str,
+    config: SignerFromPathConfig,
+) -> Result<Box<dyn Signer>, Box<dyn error::Error>> {
+    let source = parse_signer_source(path)?;
+    match source.kind {
+        SignerSourceKind::Usb(locator) => {
+            remote_signer(args, locator, source.derivation_path, keypair_name)
+        },
+        SignerSourceKind::Pubkey(pubkey) if config.allow_null_signer => {
+            Ok(Box::new(NullSigner::new(&pubkey)))
+        },
+        SignerSourceKind::Pubkey(pubkey) => Err(std::io::Error::other(format!(
+            "signer of type `pubkey` ({pubkey}) can only be used with --sign-only"
+        ))
+        .into()),
+        SignerSourceKind::Presigner(pubkey, signature) => {
+            Ok(Box::new(Presigner::new(&pubkey, &signature)))
+        },
+        _ => Ok(Box::new(keypair_from_source(args, source, keypair_name)?)),
+    }
+}
+
+#[cfg(feature = "remote-wallet")]
+fn remote_signer(
+    args: &SignerArgs,
+    locator: RemoteWalletLocator,
+    derivation_path: Option<DerivationPath>,
+    keypair_name: &str,
+) -> Result<Box<dyn Signer>, Box<dyn error::Error>> {
+    use solana_remote_wallet::{
+        remote_keypair::generate_remote_keypair, remote_wallet::maybe_wallet_manager,
+    };
+
+    let wallet_manager = maybe_wallet_manager()?
+        .ok_or_else(|| std::io::Error::other(format!("no remote wallet found for {locator}")))?;
+
+    Ok(Box::new(generate_remote_keypair(
+        locator,
+        derivation_path.unwrap_or_default(),
+        &wallet_manager,
+        args.confirm_pubkey,
+        keypair_name,
+    )?))
+}
+
+#[cfg(not(feature = "remote-wallet"))]
+#[allow(clippy::needless_pass_by_value)]
+fn remote_signer(
+    _args: &SignerArgs,
+    locator: RemoteWalletLocator,
+    _derivation_path: Option<DerivationPath>,
+    _keypair_name: &str,
+) -> Result<Box<dyn Signer>, Box<dyn error::Error>> {
+    Err(std::io::Error::other(format!(
+        "signer of type `usb` ({locator}) requires arborist to be built with the `remote-wallet` \
+         feature"
+    ))
+    .into())
+}
+
+/// Load a keypair from a source holding a secret key: a keypair file,
+/// `stdin`, a seed phrase prompt, an encrypted keystore, an environment
+/// variable, or an inline secret key
+pub(crate) fn keypair_from_path(
+    args: &SignerArgs,
+    path: &str,
+    keypair_name: &str,
+) -> Result<Keypair, Box<dyn error::Error>> {
+    keypair_from_source(args, parse_signer_source(path)?, keypair_name)
+}
+
+fn keypair_from_source(
+    args: &SignerArgs,
+    source: SignerSource,
+    keypair_name: &str,
+) -> Result<Keypair, Box<dyn error::Error>> {
+    let SignerSource {
+        kind,
+        derivation_path,
+        legacy,
+    } = source;
+    match kind {
+        SignerSourceKind::Prompt => Ok(keypair_from_seed_phrase(
+            args,
+            keypair_name,
+            derivation_path,
+            legacy,
+        )?),
+        SignerSourceKind::Filepath(path) => match read_keypair_file(&path) {
+            Err(e) => Err(std::io::Error::other(format!(
+                "could not read keypair file \"{path}\". Run \"solana-keygen new\" to create a \
+                 keypair file: {e}",
+            ))
+            .into()),
+            Ok(file) => Ok(file),
+        },
+        SignerSourceKind::Stdin => {
+            let mut stdin = std::io::stdin();
+            Ok(read_keypair(&mut stdin)?)
+        },
+        SignerSourceKind::Keystore(path) => {
+            crate::keystore::read_keystore(&path, keypair_name).map_err(|e| format!("{e:#}").into())
+        },
+        SignerSourceKind::Env(var) => {
+            // Don't use the VarError message, since it includes non-Unicode
+            // values verbatim
+            let secret = std::env::var(&var).map_err(|e| match e {
+                std::env::VarError::NotPresent => {
+                    format!("environment variable {var} for signer source `env:{var}` is not set")
+                },
+                std::env::VarError::NotUnicode(_) => format!(
+                    "environment variable {var} for signer source `env:{var}` is not valid Unicode"
+                ),
+            })?;
+
+            if secret.trim_start().starts_with('[') {
+                keypair_from_json(&secret)
+            } else {
+                keypair_from_base58(&secret)
+            }
+            .map_err(|e| format!("invalid keypair in signer source `env:{var}`: {e}").into())
+        },
+        SignerSourceKind::Base58(secret) => keypair_from_base58(&secret)
+            .map_err(|e| format!("invalid keypair in signer source `base58:`: {e}").into()),
+        SignerSourceKind::Json(secret) => keypair_from_json(&secret)
+            .map_err(|e| format!("invalid keypair in signer source `json:`: {e}").into()),
+        SignerSourceKind::Usb(_)
+        | SignerSourceKind::Pubkey(_)
+        | SignerSourceKind::Presigner(..) => Err(std::io::Error::other(format!(
+            "signer of type `{kind:?}` does not support Keypair output"
+        ))
+        .into()),
+    }
+}
+
+/// Unique signers, along with the public key of each requested signer
+type LoadedSigners = (Vec<Box<dyn Signer>>, Vec<Pubkey>);
+
+/// Load a list of named signers, deduplicated by public key
+pub(crate) fn signers_from_paths<'a>(
+    args: &SignerArgs,
+    paths: impl IntoIterator<Item = (&'a str, &'a str)>,
+    config: SignerFromPathConfig,
+) -> Result<LoadedSigners, Box<dyn error::Error>> {
+    let mut loaded: Vec<(&str, Pubkey)> = vec![];
+    let mut signers: Vec<Box<dyn Signer>> = vec![];
+    let mut pubkeys = vec![];
+
+    for (path, keypair_name) in paths {
+        // Avoid prompting for the same seed phrase or device twice
+        if let Some(&(_, pubkey)) = loaded.iter().find(|(p, _)| *p == path) {
+            pubkeys.push(pubkey);
+            continue;
+        }
+
+        let signer = signer_from_path(args, path, keypair_name, config)?;
+        let pubkey = signer.try_pubkey()?;
+        loaded.push((path, pubkey));
+        pubkeys.push(pubkey);
+
+        if !signers.iter().any(|s| s.pubkey() == pubkey) {
+            signers.push(signer);
+        }
+    }
+
+    Ok((signers, pubkeys))
+}
+
+/// Signers for a single command, resolved from the named signer arguments
+#[allow(clippy::module_name_repetitions)]
+pub struct CommandSigners {
+    signers: Vec<Box<dyn Signer>>,
+    payer: Pubkey,
+    authority: Pubkey,
+    leaf_owner: Pubkey,
+}
+
+impl CommandSigners {
+    /// Load the payer, authority, leaf owner, and any additional signers.
+    /// The payer and authority default to `default_path`, and the leaf owner
+    /// defaults to the authority.
+    pub(crate) fn load(
+        args: &SignerArgs,
+        roles: &SignerRoleArgs,
+        default_path: &str,
+        config: SignerFromPathConfig,
+    ) -> Result<Self, Box<dyn error::Error>> {
+        let SignerRoleArgs {
+            payer,
+            authority,
+            leaf_owner,
+            signers,
+        } = roles;
+
+        let authority = authority.as_deref().unwrap_or(default_path);
+        let (signers, pubkeys) = signers_from_paths(
+            args,
+            [
+                (payer.as_deref().unwrap_or(default_path), "payer"),
+                (authority, "authority"),
+                (leaf_owner.as_deref().unwrap_or(authority), "leaf owner"),
+            ]
+            .into_iter()
+            .chain(signers.iter().map(|s| (s.as_str(), "signer"))),
+            config,
+        )?;
+
+        Ok(Self {
+            signers,
+            payer: pubkeys[0],
+            authority: pubkeys[1],
+            leaf_owner: pubkeys[2],
+        })
+    }
+
+    /// Public key of the signer paying transaction fees and rent
+    #[inline]
+    #[must_use]
+    pub fn payer(&self) -> Pubkey { self.payer }
+
+    /// Public key of the signer acting as the authority of the account being
+    /// managed
+    #[inline]
+    #[must_use]
+    pub fn authority(&self) -> Pubkey { self.authority }
+
+    /// Public key of the signer owning the leaves being operated on
+    #[inline]
+    #[must_use]
+    pub fn leaf_owner(&self) -> Pubkey { self.leaf_owner }
+
+    /// All loaded signers, along with any additional signers such as newly
+    /// generated account keypairs
+    #[must_use]
+    pub fn with<'a>(&'a self, extra: &[&'a dyn Signer]) -> Vec<&'a dyn Signer> {
+        self.signers
+            .iter()
+            .map(AsRef::as_ref)
+            .chain(extra.iter().copied())
+            .collect()
+    }
+}
+
+/// Parse a `PUBKEY=SIGNATURE` pair, as printed by `--sign-only`
+fn parse_presigner(s: &str) -> Result<(Pubkey, Signature), String> {
+    let (pubkey, sig) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected PUBKEY=SIGNATURE, found {s:?}"))?;
+    let pubkey = Pubkey::from_str(pubkey).map_err(|e| format!("invalid pubkey {pubkey:?}: {e}"))?;
+    let sig = Signature::from_str(sig).map_err(|e| format!("invalid signature {sig:?}: {e}"))?;
+
+    Ok((pubkey, sig))
+}
+
+fn keypair_from_seed_phrase(
+    args: &SignerArgs,
+    keypair_name: &str,
+    derivation_path: Option<DerivationPath>,
+    legacy: bool,
+) -> Result<Keypair, Box<dyn error::Error>> {
+    let seed_phrase = prompt_password(format!("[{keypair_name}] seed phrase: "))?;
+    let seed_phrase = seed_phrase.trim();
+    let passphrase_prompt = format!(
+        "[{keypair_name}] If this seed phrase has an associated passphrase, enter it now. \
+         Otherwise, press ENTER to continue: ",
+    );
+
+    let keypair = if args.skip_seed_phrase_validation {
+        let passphrase = prompt_passphrase(&passphrase_prompt)?;
+        if legacy {
+            keypair_from_seed_phrase_and_passphrase(seed_phrase, &passphrase)?
+        } else {
+            let seed = generate_seed_from_seed_phrase_and_passphrase(seed_phrase, &passphrase);
+            keypair_from_seed_and_derivation_path(&seed, derivation_path)?
+        }
+    } else {
+        let sanitized = sanitize_seed_phrase(seed_phrase);
+        let parse_language_fn = || {
+            for language in &[
+                Language::English,
+                Language::ChineseSimplified,
+                Language::ChineseTraditional,
+                Language::Japanese,
+                Language::Spanish,
+                Language::Korean,
+                Language::French,
+                Language::Italian,
+            ] {
+                if let Ok(mnemonic) = Mnemonic::from_phrase(&sanitized, *language) {
+                    return Ok(mnemonic);
+                }
+            }
+            Err("Can't get mnemonic from seed phrases")
+        };
+        let mnemonic = parse_language_fn()?;
+        let passphrase = prompt_passphrase(&passphrase_prompt)?;
+        let seed = Seed::new(&mnemonic, &passphrase);
+        if legacy {
+            keypair_from_seed(seed.as_bytes())?
+        } else {
+            keypair_from_seed_and_derivation_path(seed.as_bytes(), derivation_path)?
+        }
+    };
+
+    if args.confirm_pubkey {
+        let pubkey = keypair.pubkey();
+        print!("Recovered pubkey `{pubkey:?}`. Continue? (y/n): ");
+        let _ignored = stdout().flush();
+        let mut input = String::new();
+        stdin().read_line(&mut input).expect("Unexpected input");
+        if input.to_lowercase().trim() != "y" {
+            println!("Exiting");
+            exit(1);
+        }
+    }
+
+    Ok(keypair)
+}
+
+fn sanitize_seed_phrase(seed_phrase: &str) -> String {
+    seed_phrase
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signature::write_keypair_file;
+
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn load(path: &str, allow_null_signer: bool) -> Result<Box<dyn Signer>, Box<dyn error::Error>> {
+        signer_from_path(&SignerArgs::default(), path, "test", SignerFromPathConfig {
+            allow_null_signer,
+        })
+    }
+
+    #[test]
+    fn parses_signer_sources() {
+        let pubkey = Pubkey::new_unique();
+        let sig = Signature::new(&[7; 64]);
+
+        assert!(matches!(
+            parse_signer_source(pubkey.to_string()).unwrap().kind,
+            SignerSourceKind::Pubkey(p) if p == pubkey
+        ));
+        assert!(matches!(
+            parse_signer_source(format!("{pubkey}={sig}")).unwrap().kind,
+            SignerSourceKind::Presigner(p, s) if p == pubkey && s == sig
+        ));
+        assert!(matches!(
+            parse_signer_source("-").unwrap().kind,
+            SignerSourceKind::Stdin
+        ));
+        assert!(matches!(
+            parse_signer_source("stdin:").unwrap().kind,
+            SignerSourceKind::Stdin
+        ));
+        assert!(matches!(
+            parse_signer_source("prompt:").unwrap().kind,
+            SignerSourceKind::Prompt
+        ));
+        assert!(matches!(
+            parse_signer_source("usb://ledger").unwrap().kind,
+            SignerSourceKind::Usb(_)
+        ));
+        assert!(matches!(
+            parse_signer_source("keystore:/tmp/id.json").unwrap().kind,
+            SignerSourceKind::Keystore(p) if p == "/tmp/id.json"
+        ));
+        assert!(matches!(
+            parse_signer_source("env:ARBORIST_KEYPAIR").unwrap().kind,
+            SignerSourceKind::Env(v) if v == "ARBORIST_KEYPAIR"
+        ));
+        assert!(matches!(
+            parse_signer_source("json:[1, 2, 3]").unwrap().kind,
+            SignerSourceKind::Json(s) if s == "[1, 2, 3]"
+        ));
+        assert!(matches!(
+            parse_signer_source("file:/does/not/exist").unwrap().kind,
+            SignerSourceKind::Filepath(p) if p == "/does/not/exist"
+        ));
+        assert!(parse_signer_source("/does/not/exist").is_err());
+    }
+
+    #[test]
+    fn loads_keypair_file() {
+        let keypair = Keypair::new();
+        let dir = TempDir::new("signer");
+        let path = dir.path().join("keypair.json");
+        write_keypair_file(&keypair, &path).unwrap();
+
+        let signer = load(path.to_str().unwrap(), false).unwrap();
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+        assert_eq!(signer.sign_message(b"msg"), keypair.sign_message(b"msg"));
+    }
+
+    #[test]
+    fn loads_presigner() {
+        let keypair = Keypair::new();
+        let sig = keypair.sign_message(b"msg");
+        let signer = load(&format!("{}={sig}", keypair.pubkey()), false).unwrap();
+
+        assert_eq!(signer.pubkey(), keypair.pubkey());
+        assert_eq!(signer.try_sign_message(b"msg").unwrap(), sig);
+        assert!(signer.try_sign_message(b"other").is_err());
+    }
+
+    #[test]
+    fn loads_null_signer_only_when_allowed() {
+        let pubkey = Pubkey::new_unique();
+
+        let signer = load(&pubkey.to_string(), true).unwrap();
+        assert_eq!(signer.pubkey(), pubkey);
+        assert_eq!(signer.sign_message(b"msg"), Signature::default());
+
+        assert!(load(&pubkey.to_string(), false).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_presigner() {
+        assert!(parse_presigner("not-a-presigner").is_err());
+        assert!(parse_presigner(&format!("{}=garbage", Pubkey::new_unique())).is_err());
+        assert!(load("garbage=garbage", false).is_err());
+    }
+
+    #[test]
+    fn loads_inline_keypairs() {
+        let keypair = Keypair::new();
+        let json = serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap();
+        let var = format!("ARBORIST_TEST_KEYPAIR_{}", keypair.pubkey());
+        std::env::set_var(&var, keypair.to_base58_string());
+
+        for source in [
+            format!("base58:{}", keypair.to_base58_string()),
+            format!("json:{json}"),
+            format!("env:{var}"),
+        ] {
+            assert_eq!(load(&source, false).unwrap().pubkey(), keypair.pubkey());
+        }
+
+        std::env::set_var(&var, &json);
+        assert_eq!(
+            load(&format!("env:{var}"), false).unwrap().pubkey(),
+            keypair.pubkey()
+        );
+        std::env::remove_var(&var);
+    }
+
+    #[test]
+    fn inline_keypair_errors_omit_secrets() {
+        let secret = "3ZmXhFDcRmNTPL7iH5Mwa6DSCYhVSDJdr4yVRHi1Z4rK";
+        let err = load(&format!("base58:{secret}"), false)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("base58:"));
+        assert!(!err.contains(secret));
+
+        let err = load("json:[1,2,300]", false).err().unwrap().to_string();
+        assert!(err.contains("json:"));
+        assert!(!err.contains("300"));
+
+        let err = load("env:ARBORIST_TEST_UNSET_VARIABLE", false)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("ARBORIST_TEST_UNSET_VARIABLE"));
+    }
+
+    #[cfg(not(feature = "remote-wallet"))]
+    #[test]
+    fn rejects_usb_without_feature() {
+        let err = load("usb://ledger", false).err().unwrap();
+        assert!(err.to_string().contains("remote-wallet"));
+    }
+}
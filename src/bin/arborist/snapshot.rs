@@ -0,0 +1,392 @@
+use std::{
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use arborist::{
+    accounts::decode_tree_config,
+    chain::ChainClient,
+    client::SolanaClient,
+    snapshot::{LeafState, MerkleTreeState, TreeSnapshot},
+    tree::tree_config_address,
+};
+use serde::Serialize;
+
+use crate::{
+    cli::{ExportTree, InspectSnapshot},
+    das::{self, DasClient},
+    output::{self, ErrorCode, Output, ResultExt},
+    project::Project,
+};
+
+/// Result of the `export-tree` command
+#[derive(Debug, Serialize)]
+pub struct ExportResult {
+    file: String,
+    merkle_tree: String,
+    slot: u64,
+    sequence_number: u64,
+    leaves: Option<usize>,
+}
+
+impl Output for ExportResult {
+    fn write_text(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(
+            out,
+            "Wrote snapshot of {} to {}",
+            self.merkle_tree, self.file
+        )?;
+        writeln!(out, "Slot: {}", self.slot)?;
+        writeln!(out, "Sequence number: {}", self.sequence_number)?;
+        match self.leaves {
+            Some(n) => writeln!(out, "Leaves: {n}"),
+            None => writeln!(out, "Leaves: not exported"),
+        }
+    }
+}
+
+/// The Bubblegum configuration stored in a snapshot
+#[derive(Debug, Serialize)]
+struct ConfigSummary {
+    tree_creator: String,
+    tree_delegate: String,
+    total_mint_capacity: u64,
+    num_minted: u64,
+    is_public: bool,
+}
+
+/// A single leaf stored in a snapshot
+#[derive(Debug, Serialize)]
+struct LeafRow {
+    index: u32,
+    asset_id: String,
+    owner: String,
+    delegate: String,
+    data_hash: String,
+    creator_hash: String,
+    leaf_hash: String,
+}
+
+impl From<&LeafState> for LeafRow {
+    fn from(leaf: &LeafState) -> Self {
+        let LeafState {
+            index,
+            nonce: _,
+            asset_id,
+            owner,
+            delegate,
+            data_hash,
+            creator_hash,
+            leaf_hash,
+        } = *leaf;
+
+        Self {
+            index,
+            asset_id: asset_id.to_string(),
+            owner: owner.to_string(),
+            delegate: delegate.to_string(),
+            data_hash: bs58::encode(data_hash).into_string(),
+            creator_hash: bs58::encode(creator_hash).into_string(),
+            leaf_hash: bs58::encode(leaf_hash).into_string(),
+        }
+    }
+}
+
+/// Result of the `inspect-snapshot` command
+#[derive(Debug, Serialize)]
+pub struct InspectResult {
+    merkle_tree: String,
+    slot: u64,
+    exported_at: i64,
+    authority: String,
+    max_depth: u32,
+    max_buffer_size: u32,
+    canopy_depth: u32,
+    creation_slot: u64,
+    sequence_number: u64,
+    rightmost_index: u32,
+    root: String,
+    change_logs: u64,
+    tree_config: Option<ConfigSummary>,
+    leaf_count: Option<usize>,
+    leaves_match_root: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    leaves: Option<Vec<LeafRow>>,
+}
+
+impl Output for InspectResult {
+    fn write_text(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(out, "Merkle tree: {}", self.merkle_tree)?;
+        writeln!(out, "Slot: {}", self.slot)?;
+        writeln!(out, "Exported at: {}", self.exported_at)?;
+        writeln!(out, "Authority: {}", self.authority)?;
+        writeln!(out, "Max depth: {}", self.max_depth)?;
+        writeln!(out, "Max buffer size: {}", self.max_buffer_size)?;
+        writeln!(out, "Canopy depth: {}", self.canopy_depth)?;
+        writeln!(out, "Creation slot: {}", self.creation_slot)?;
+        writeln!(out, "Sequence number: {}", self.sequence_number)?;
+        writeln!(out, "Rightmost index: {}", self.rightmost_index)?;
+        writeln!(out, "Root: {}", self.root)?;
+        writeln!(out, "Change logs: {}", self.change_logs)?;
+
+        if let Some(ConfigSummary {
+            tree_creator,
+            tree_delegate,
+            total_mint_capacity,
+            num_minted,
+            is_public,
+        }) = &self.tree_config
+        {
+            writeln!(out, "Tree creator: {tree_creator}")?;
+            writeln!(out, "Tree delegate: {tree_delegate}")?;
+            writeln!(out, "Minted: {num_minted} of {total_mint_capacity}")?;
+            writeln!(out, "Public: {is_public}")?;
+        }
+
+        match (self.leaf_count, self.leaves_match_root) {
+            (Some(n), Some(true)) => writeln!(out, "Leaves: {n} (match root)")?,
+            (Some(n), _) => writeln!(out, "Leaves: {n} (do not match root)")?,
+            (None, _) => writeln!(out, "Leaves: not exported")?,
+        }
+
+        if let Some(leaves) = &self.leaves {
+            writeln!(out)?;
+            output::write_table(
+                ["INDEX", "ASSET", "OWNER", "DELEGATE", "LEAF HASH"],
+                leaves.iter().map(|l| {
+                    [
+                        l.index.to_string(),
+                        l.asset_id.clone(),
+                        l.owner.clone(),
+                        l.delegate.clone(),
+                        l.leaf_hash.clone(),
+                    ]
+                }),
+                out,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn write_csv(&self, out: &mut dyn Write) -> Result<()> {
+        let leaves = self
+            .leaves
+            .as_ref()
+            .context("CSV output requires --leaves")
+            .code(ErrorCode::InvalidArgument)?;
+        let mut csv = csv::Writer::from_writer(out);
+
+        for row in leaves {
+            csv.serialize(row)
+                .context("Error serializing leaves to CSV")?;
+        }
+
+        csv.flush().context("Error writing CSV output")
+    }
+}
+
+pub async fn export_tree<C: ChainClient>(
+    client: &SolanaClient<C>,
+    das: &DasClient,
+    project: &Project,
+    args: ExportTree,
+) -> Result<ExportResult> {
+    let ExportTree {
+        merkle_tree,
+        out,
+        no_leaves,
+    } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+
+    let slot = client
+        .slot(client.commitment())
+        .await
+        .context("Error getting current slot")
+        .code(ErrorCode::Rpc)?;
+
+    let config_address = tree_config_address(&merkle_tree);
+    let [tree, config]: [_; 2] = client
+        .multiple_accounts(&[merkle_tree, config_address])
+        .await
+        .with_context(|| format!("Error getting Merkle tree {merkle_tree}"))
+        .code(ErrorCode::Rpc)?
+        .try_into()
+        .map_err(|_| anyhow!("Unexpected number of accounts returned"))
+        .code(ErrorCode::Rpc)?;
+
+    let tree = tree
+        .with_context(|| format!("Merkle tree {merkle_tree} does not exist"))
+        .code(ErrorCode::InvalidArgument)?;
+    let tree = MerkleTreeState::decode(&tree.data)
+        .with_context(|| format!("Error decoding Merkle tree {merkle_tree}"))
+        .code(ErrorCode::InvalidArgument)?;
+
+    // Trees created directly through the account compression program have no
+    // Bubblegum configuration
+    let tree_config = config
+        .filter(|a| a.owner == mpl_bubblegum::ID)
+        .map(|a| decode_tree_config(&a.data))
+        .transpose()
+        .with_context(|| format!("Error decoding tree config {config_address}"))
+        .code(ErrorCode::InvalidArgument)?
+        .map(Into::into);
+
+    let leaves =
+        if no_leaves {
+            None
+        } else {
+            Some(das::tree_leaves(das, &merkle_tree).await.context(
+                "Error fetching leaves from the DAS API (pass --no-leaves to skip them)",
+            )?)
+        };
+
+    let exported_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX));
+
+    let snapshot = TreeSnapshot {
+        merkle_tree,
+        slot,
+        exported_at,
+        tree,
+        tree_config,
+        leaves,
+    };
+
+    let bytes = snapshot.to_bytes().context("Error encoding snapshot")?;
+    std::fs::write(&out, bytes)
+        .with_context(|| format!("Error writing snapshot to {}", out.display()))
+        .code(ErrorCode::Io)?;
+
+    Ok(ExportResult {
+        file: out.display().to_string(),
+        merkle_tree: merkle_tree.to_string(),
+        slot,
+        sequence_number: snapshot.tree.sequence_number,
+        leaves: snapshot.leaves.as_ref().map(Vec::len),
+    })
+}
+
+pub fn inspect_snapshot(args: InspectSnapshot) -> Result<InspectResult> {
+    let InspectSnapshot { file, leaves } = args;
+
+    let bytes = std::fs::read(&file)
+        .with_context(|| format!("Error reading snapshot {}", file.display()))
+        .code(ErrorCode::Io)?;
+    let snapshot = TreeSnapshot::from_bytes(&bytes)
+        .with_context(|| format!("Error reading snapshot {}", file.display()))
+        .code(ErrorCode::InvalidArgument)?;
+
+    let root = snapshot.tree.root().unwrap_or_else(|| unreachable!());
+    let leaves_match_root = snapshot.leaf_root().map(|r| r == root);
+    let TreeSnapshot {
+        merkle_tree,
+        slot,
+        exported_at,
+        tree,
+        tree_config,
+        leaves: snapshot_leaves,
+    } = snapshot;
+
+    Ok(InspectResult {
+        merkle_tree: merkle_tree.to_string(),
+        slot,
+        exported_at,
+        authority: tree.authority.to_string(),
+        max_depth: tree.max_depth,
+        max_buffer_size: tree.max_buffer_size,
+        canopy_depth: tree.canopy_depth(),
+        creation_slot: tree.creation_slot,
+        sequence_number: tree.sequence_number,
+        rightmost_index: tree.rightmost_proof.index,
+        root: bs58::encode(root).into_string(),
+        change_logs: tree.buffer_size,
+        tree_config: tree_config.map(|c| ConfigSummary {
+            tree_creator: c.tree_creator.to_string(),
+            tree_delegate: c.tree_delegate.to_string(),
+            total_mint_capacity: c.total_mint_capacity,
+            num_minted: c.num_minted,
+            is_public: c.is_public,
+        }),
+        leaf_count: snapshot_leaves.as_ref().map(Vec::len),
+        leaves_match_root,
+        leaves: snapshot_leaves
+            .filter(|_| leaves)
+            .map(|l| l.iter().map(LeafRow::from).collect()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use arborist::{accounts::TreeConfig, chain::MockChain};
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::{
+        cli::TreeArg,
+        test_support::{block_on, config_account, tree_account, TempDir},
+    };
+
+    #[test]
+    fn exports_and_inspects_snapshot() {
+        let merkle_tree = Pubkey::new_unique();
+        let tree_creator = Pubkey::new_unique();
+        let dir = TempDir::new("snapshot");
+        let file = dir.path().join("tree.arb");
+
+        let client = SolanaClient::new(MockChain::new(), false);
+        client.set_account(merkle_tree, tree_account(4, [7; 32], 4));
+        client.set_account(
+            tree_config_address(&merkle_tree),
+            config_account(1, &TreeConfig {
+                tree_creator,
+                tree_delegate: tree_creator,
+                total_mint_capacity: 8,
+                num_minted: 4,
+                is_public: false,
+            }),
+        );
+
+        let exported = block_on(export_tree(
+            &client,
+            &DasClient::new("http://127.0.0.1:1".into()),
+            &dir.project(),
+            ExportTree {
+                merkle_tree: TreeArg::Address(merkle_tree),
+                out: file.clone(),
+                no_leaves: true,
+            },
+        ))
+        .unwrap();
+        assert_eq!(exported.sequence_number, 4);
+        assert_eq!(exported.leaves, None);
+
+        let inspected = inspect_snapshot(InspectSnapshot {
+            file: file.clone(),
+            leaves: true,
+        })
+        .unwrap();
+        assert_eq!(inspected.merkle_tree, merkle_tree.to_string());
+        assert_eq!(inspected.root, bs58::encode([7; 32]).into_string());
+        assert_eq!(inspected.rightmost_index, 4);
+        assert_eq!(inspected.change_logs, 0);
+        assert_eq!(inspected.max_buffer_size, 8);
+        assert_eq!(
+            inspected.tree_config.as_ref().map(|c| c.num_minted),
+            Some(4)
+        );
+        assert_eq!(inspected.leaves_match_root, None);
+        assert!(inspected.leaves.is_none());
+
+        let mut bytes = std::fs::read(&file).unwrap();
+        bytes[20] ^= 1;
+        std::fs::write(&file, bytes).unwrap();
+        assert!(inspect_snapshot(InspectSnapshot {
+            file: file.clone(),
+            leaves: false,
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Fixtures shared by the command tests
+
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+};
+
+use anchor_lang::AnchorSerialize;
+use arborist::{
+    accounts::TreeConfig,
+    chain::{ConfirmedTransaction, InnerInstructions},
+    hash::asset_id,
+    instructions, MetadataArgs, TokenProgramVersion,
+};
+use mpl_bubblegum::state::leaf_schema::LeafSchema;
+use solana_sdk::{
+    account::Account,
+    clock::Slot,
+    instruction::{CompiledInstruction, Instruction},
+    message::{v0::LoadedAddresses, Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::VersionedTransaction,
+};
+use spl_account_compression::{
+    events::{AccountCompressionEvent, ApplicationDataEvent, ApplicationDataEventV1},
+    state::PathNode,
+    ChangeLogEvent,
+};
+
+use crate::{
+    cli::SignerRoleArgs,
+    project::Project,
+    signer::{CommandSigners, SignerArgs, SignerFromPathConfig},
+};
+
+/// Run a future to completion on a single-threaded runtime
+pub fn block_on<F: Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(f)
+}
+
+/// A uniquely named scratch directory, removed when dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(prefix: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "arborist-{prefix}-{}",
+            Keypair::new().pubkey()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path { &self.0 }
+
+    /// Load the project configuration stored in this directory
+    pub fn project(&self) -> Project { Project::load(self.0.join("arborist.toml")).unwrap() }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) { std::fs::remove_dir_all(&self.0).ok(); }
+}
+
+/// Load a keypair as the payer, authority, and leaf owner of a command
+pub fn command_signers(keypair: &Keypair) -> CommandSigners {
+    let json = serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap();
+
+    CommandSigners::load(
+        &SignerArgs::default(),
+        &SignerRoleArgs::default(),
+        &format!("json:{json}"),
+        SignerFromPathConfig::default(),
+    )
+    .unwrap()
+}
+
+/// Metadata for a minimal asset named `Sapling`
+pub fn metadata() -> MetadataArgs {
+    MetadataArgs {
+        name: "Sapling".into(),
+        symbol: String::new(),
+        uri: String::new(),
+        seller_fee_basis_points: 0,
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: None,
+        collection: None,
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators: vec![],
+    }
+}
+
+/// Encode a depth-3 tree with the given state in the on-chain layout
+pub fn tree_account(sequence_number: u64, root: [u8; 32], rightmost_index: u32) -> Account {
+    const DEPTH: usize = 3;
+    const BUFFER: usize = 8;
+    const HEADER: usize = 56;
+    const PREFIX: usize = 24;
+    const CHANGE_LOG: usize = 32 * (DEPTH + 1) + 8;
+
+    let size = arborist::tree::tree_size(3, 8, 0).unwrap();
+    let mut data = vec![0; usize::try_from(size).unwrap()];
+    data[0] = 1;
+    data[2..6].copy_from_slice(&8_u32.to_le_bytes());
+    data[6..10].copy_from_slice(&3_u32.to_le_bytes());
+
+    let body = &mut data[HEADER..];
+    body[..8].copy_from_slice(&sequence_number.to_le_bytes());
+    body[PREFIX..PREFIX + 32].copy_from_slice(&root);
+    let index = PREFIX + BUFFER * CHANGE_LOG + 32 * DEPTH + 32;
+    body[index..index + 4].copy_from_slice(&rightmost_index.to_le_bytes());
+
+    Account {
+        lamports: 1,
+        data,
+        owner: spl_account_compression::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+pub fn config_account(lamports: u64, config: &TreeConfig) -> Account {
+    let mut data = vec![];
+    anchor_lang::AccountSerialize::try_serialize(config, &mut data).unwrap();
+
+    Account {
+        lamports,
+        data,
+        owner: mpl_bubblegum::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// A processed transaction minting an asset into a tree
+pub fn mint_transaction(
+    signature: Signature,
+    merkle_tree: &Pubkey,
+    payer: &Pubkey,
+    slot: Slot,
+) -> ConfirmedTransaction {
+    let owner = Pubkey::new_unique();
+    let mint = instructions::mint_v1(merkle_tree, &owner, &owner, payer, payer, metadata());
+
+    ConfirmedTransaction {
+        slot,
+        block_time: None,
+        transaction: VersionedTransaction {
+            signatures: vec![signature],
+            message: VersionedMessage::Legacy(Message::new(&[mint], Some(payer))),
+        },
+        err: None,
+        logs: vec![],
+        inner_instructions: vec![],
+        loaded_addresses: LoadedAddresses::default(),
+    }
+}
+
+/// A Bubblegum mint into `merkle_tree` with `owner` in every role
+pub fn mint_instruction(merkle_tree: &Pubkey, owner: &Pubkey) -> Instruction {
+    instructions::mint_v1(merkle_tree, owner, owner, owner, owner, metadata())
+}
+
+/// The events Bubblegum and the account compression program log when
+/// leaf `nonce` is written with a new owner, using `[seq; 32]` as the new
+/// root
+pub fn leaf_events(
+    merkle_tree: &Pubkey,
+    nonce: u32,
+    owner: &Pubkey,
+    seq: u8,
+) -> Vec<AccountCompressionEvent> {
+    let schema = LeafSchema::new_v0(
+        asset_id(merkle_tree, nonce.into()),
+        *owner,
+        *owner,
+        nonce.into(),
+        [1; 32],
+        [2; 32],
+    );
+
+    vec![
+        AccountCompressionEvent::ApplicationData(ApplicationDataEvent::V1(
+            ApplicationDataEventV1 {
+                application_data: schema.to_event().try_to_vec().unwrap(),
+            },
+        )),
+        AccountCompressionEvent::ChangeLog(ChangeLogEvent::new(
+            *merkle_tree,
+            vec![
+                PathNode::new(schema.to_node(), 1),
+                PathNode::new([seq; 32], 1),
+            ],
+            seq.into(),
+            nonce,
+        )),
+    ]
+}
+
+/// Build a processed transaction whose only instruction logs the given
+/// events through the noop program
+pub fn confirmed_transaction(
+    signature: Signature,
+    slot: Slot,
+    ix: Instruction,
+    payer: &Pubkey,
+    events: &[AccountCompressionEvent],
+) -> ConfirmedTransaction {
+    let message = Message::new(&[ix], Some(payer));
+    let noop = message
+        .account_keys
+        .iter()
+        .position(|k| *k == spl_noop::ID)
+        .unwrap();
+
+    ConfirmedTransaction {
+        slot,
+        block_time: None,
+        transaction: VersionedTransaction {
+            signatures: vec![signature],
+            message: VersionedMessage::Legacy(message),
+        },
+        err: None,
+        logs: vec![],
+        inner_instructions: vec![InnerInstructions {
+            index: 0,
+            instructions: events
+                .iter()
+                .map(|e| CompiledInstruction {
+                    program_id_index: noop.try_into().unwrap(),
+                    accounts: vec![],
+                    data: e.try_to_vec().unwrap(),
+                })
+                .collect(),
+        }],
+        loaded_addresses: LoadedAddresses::default(),
+    }
+}
@@ -0,0 +1,550 @@
+use anyhow::{anyhow, Context, Result};
+use arborist::{
+    accounts::{decode_merkle_tree, decode_tree_config, MerkleTreeAccount, TreeConfig},
+    chain::ChainClient,
+    client::SolanaClient,
+    decode::{instruction_kinds, InstructionKind},
+    tree::tree_config_address,
+};
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    nonblocking::pubsub_client::PubsubClient, rpc_config::RpcAccountInfoConfig,
+    rpc_response::Response,
+};
+use solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey};
+
+use crate::{
+    cli::WatchTree,
+    output::{ErrorCode, Output, OutputFormat, ResultExt},
+    project::Project,
+};
+
+/// Number of recent signatures for a tree searched for the transactions
+/// that modified it
+const SIGNATURE_LOOKBACK: usize = 25;
+
+/// Snapshot of the tree and its configuration reported with each event
+#[derive(Debug, Serialize)]
+pub struct TreeState {
+    sequence_number: u64,
+    root: String,
+    leaves_used: u32,
+    num_minted: u64,
+    capacity: u64,
+    remaining_capacity: u64,
+}
+
+impl TreeState {
+    fn new(tree: &MerkleTreeAccount, config: &TreeConfig) -> Self {
+        Self {
+            sequence_number: tree.sequence_number,
+            root: bs58::encode(tree.root).into_string(),
+            leaves_used: tree.rightmost_index,
+            num_minted: config.num_minted,
+            capacity: config.total_mint_capacity,
+            remaining_capacity: config.total_mint_capacity.saturating_sub(config.num_minted),
+        }
+    }
+
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        write!(
+            out,
+            "root {}, {}/{} minted ({} remaining)",
+            self.root, self.num_minted, self.capacity, self.remaining_capacity
+        )
+    }
+}
+
+/// A transaction that modified a watched tree
+#[derive(Debug, Serialize)]
+pub struct TreeTransaction {
+    signature: String,
+    instructions: Vec<String>,
+}
+
+/// A change to a watched tree, reported as it is observed
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum WatchEvent {
+    /// The state of the tree when watching started
+    Watching {
+        merkle_tree: String,
+        #[serde(flatten)]
+        state: TreeState,
+    },
+    /// The tree's sequence number was incremented
+    TreeUpdated {
+        slot: Slot,
+        previous_sequence_number: u64,
+        #[serde(flatten)]
+        state: TreeState,
+        transactions: Vec<TreeTransaction>,
+    },
+    /// The tree's configuration changed without the tree being modified
+    ConfigUpdated {
+        slot: Slot,
+        tree_creator: String,
+        tree_delegate: String,
+        is_public: bool,
+    },
+}
+
+impl Output for WatchEvent {
+    fn write_text(&self, out: &mut dyn std::io::Write) -> std::io::Result<()> {
+        match self {
+            Self::Watching { merkle_tree, state } => {
+                write!(
+                    out,
+                    "Watching tree {merkle_tree} at sequence {}: ",
+                    state.sequence_number
+                )?;
+                state.write_text(out)?;
+                writeln!(out)
+            },
+            Self::TreeUpdated {
+                slot,
+                previous_sequence_number,
+                state,
+                transactions,
+            } => {
+                write!(
+                    out,
+                    "[slot {slot}] sequence {previous_sequence_number} -> {}, ",
+                    state.sequence_number
+                )?;
+                state.write_text(out)?;
+                writeln!(out)?;
+
+                if transactions.is_empty() {
+                    writeln!(out, "  (triggering transaction not found)")?;
+                }
+
+                for TreeTransaction {
+                    signature,
+                    instructions,
+                } in transactions
+                {
+                    writeln!(out, "  {}: {signature}", instructions.join(", "))?;
+                }
+
+                Ok(())
+            },
+            Self::ConfigUpdated {
+                slot,
+                tree_creator,
+                tree_delegate,
+                is_public,
+            } => writeln!(
+                out,
+                "[slot {slot}] tree config updated: creator {tree_creator}, delegate \
+                 {tree_delegate}, public {is_public}"
+            ),
+        }
+    }
+}
+
+/// A notification from one of the watched accounts
+enum Update {
+    Tree(Response<UiAccount>),
+    Config(Response<UiAccount>),
+}
+
+fn notification_data(res: &Response<UiAccount>, kind: &str) -> Result<(Slot, Vec<u8>)> {
+    let account = res
+        .value
+        .decode::<Account>()
+        .with_context(|| format!("Unable to decode {kind} notification"))?;
+
+    Ok((res.context.slot, account.data))
+}
+
+/// Current state of a watched tree
+struct Watcher<'a, C> {
+    client: &'a SolanaClient<C>,
+    merkle_tree: Pubkey,
+    tree: MerkleTreeAccount,
+    config: TreeConfig,
+    config_slot: Slot,
+}
+
+impl<C: ChainClient> Watcher<'_, C> {
+    async fn fetch_config(&self) -> Result<TreeConfig> {
+        let address = tree_config_address(&self.merkle_tree);
+        let account = self
+            .client
+            .account(&address)
+            .await
+            .with_context(|| format!("Error getting tree config {address}"))
+            .code(ErrorCode::Rpc)?
+            .with_context(|| format!("Tree config {address} does not exist"))
+            .code(ErrorCode::InvalidArgument)?;
+
+        decode_tree_config(&account.data)
+            .with_context(|| format!("Error decoding tree config {address}"))
+    }
+
+    /// Find the successful transactions that modified the tree in a slot,
+    /// along with the Bubblegum instructions they contained, or their account
+    /// compression instructions if they used none
+    async fn transactions(&self, slot: Slot) -> Vec<TreeTransaction> {
+        let Ok(signatures) = self
+            .client
+            .signatures_for_address(&self.merkle_tree, None, SIGNATURE_LOOKBACK)
+            .await
+        else {
+            return vec![];
+        };
+
+        let mut transactions = vec![];
+        for info in signatures
+            .into_iter()
+            .rev()
+            .filter(|s| s.slot == slot && s.err.is_none())
+        {
+            let Ok(Some(txn)) = self.client.transaction(&info.signature).await else {
+                continue;
+            };
+
+            let kinds = instruction_kinds(&txn);
+            let bubblegum = kinds
+                .iter()
+                .copied()
+                .filter(|k| k.is_bubblegum())
+                .collect::<Vec<_>>();
+            let kinds = if bubblegum.is_empty() {
+                kinds
+            } else {
+                bubblegum
+            };
+
+            transactions.push(TreeTransaction {
+                signature: info.signature.to_string(),
+                instructions: kinds
+                    .iter()
+                    .map(|k| InstructionKind::name(*k).into())
+                    .collect(),
+            });
+        }
+
+        transactions
+    }
+
+    async fn update(&mut self, update: Update) -> Result<Option<WatchEvent>> {
+        match update {
+            Update::Tree(res) => {
+                let (slot, data) = notification_data(&res, "Merkle tree")?;
+                let tree = decode_merkle_tree(&data)
+                    .with_context(|| format!("Error decoding Merkle tree {}", self.merkle_tree))?;
+
+                // Only lamports or other fields changed
+                if tree.sequence_number == self.tree.sequence_number {
+                    return Ok(None);
+                }
+
+                // Mints update the tree config in the same transaction, but
+                // its notification may not have arrived yet
+                if self.config_slot < slot {
+                    self.config = self.fetch_config().await?;
+                    self.config_slot = slot;
+                }
+
+                let previous_sequence_number = self.tree.sequence_number;
+                self.tree = tree;
+
+                Ok(Some(WatchEvent::TreeUpdated {
+                    slot,
+                    previous_sequence_number,
+                    state: TreeState::new(&self.tree, &self.config),
+                    transactions: self.transactions(slot).await,
+                }))
+            },
+            Update::Config(res) => {
+                let (slot, data) = notification_data(&res, "tree config")?;
+
+                // Notifications for different accounts are not ordered, so the
+                // config may already have been refetched at a later slot
+                if slot < self.config_slot {
+                    return Ok(None);
+                }
+
+                let config = decode_tree_config(&data).context("Error decoding tree config")?;
+                let prev = std::mem::replace(&mut self.config, config);
+                self.config_slot = slot;
+
+                // Changes to the mint count are reported with the tree update
+                let config = &self.config;
+                if (prev.tree_creator, prev.tree_delegate, prev.is_public)
+                    == (config.tree_creator, config.tree_delegate, config.is_public)
+                {
+                    return Ok(None);
+                }
+
+                Ok(Some(WatchEvent::ConfigUpdated {
+                    slot,
+                    tree_creator: config.tree_creator.to_string(),
+                    tree_delegate: config.tree_delegate.to_string(),
+                    is_public: config.is_public,
+                }))
+            },
+        }
+    }
+}
+
+/// Subscribe to a tree and its configuration, reporting each change until the
+/// subscriptions end
+async fn watch<C: ChainClient>(
+    client: &SolanaClient<C>,
+    pubsub: &PubsubClient,
+    merkle_tree: Pubkey,
+    mut emit: impl FnMut(&WatchEvent) -> Result<()>,
+) -> Result<()> {
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(client.commitment()),
+        ..RpcAccountInfoConfig::default()
+    };
+
+    // Subscribe before reading the initial state so no changes are missed
+    let (tree_updates, _) = pubsub
+        .account_subscribe(&merkle_tree, Some(config.clone()))
+        .await
+        .with_context(|| format!("Error subscribing to Merkle tree {merkle_tree}"))
+        .code(ErrorCode::Rpc)?;
+    let (config_updates, _) = pubsub
+        .account_subscribe(&tree_config_address(&merkle_tree), Some(config))
+        .await
+        .context("Error subscribing to tree config")
+        .code(ErrorCode::Rpc)?;
+    let mut updates = stream::select(
+        tree_updates.map(Update::Tree),
+        config_updates.map(Update::Config),
+    );
+
+    let tree = client
+        .account(&merkle_tree)
+        .await
+        .with_context(|| format!("Error getting Merkle tree {merkle_tree}"))
+        .code(ErrorCode::Rpc)?
+        .with_context(|| format!("Merkle tree {merkle_tree} does not exist"))
+        .code(ErrorCode::InvalidArgument)?;
+    let tree = decode_merkle_tree(&tree.data)
+        .with_context(|| format!("Error decoding Merkle tree {merkle_tree}"))
+        .code(ErrorCode::InvalidArgument)?;
+    let config_slot = client
+        .slot(client.commitment())
+        .await
+        .context("Error getting current slot")
+        .code(ErrorCode::Rpc)?;
+
+    let mut watcher = Watcher {
+        client,
+        merkle_tree,
+        tree,
+        config: TreeConfig {
+            tree_creator: Pubkey::default(),
+            tree_delegate: Pubkey::default(),
+            total_mint_capacity: 0,
+            num_minted: 0,
+            is_public: false,
+        },
+        config_slot,
+    };
+    watcher.config = watcher.fetch_config().await?;
+
+    emit(&WatchEvent::Watching {
+        merkle_tree: merkle_tree.to_string(),
+        state: TreeState::new(&watcher.tree, &watcher.config),
+    })?;
+
+    while let Some(update) = updates.next().await {
+        if let Some(event) = watcher.update(update).await? {
+            emit(&event)?;
+        }
+    }
+
+    Err(anyhow!("Websocket subscription closed")).code(ErrorCode::Rpc)
+}
+
+pub async fn watch_tree<C: ChainClient>(
+    client: &SolanaClient<C>,
+    project: &Project,
+    ws_url: &str,
+    output: OutputFormat,
+    args: WatchTree,
+) -> Result<()> {
+    let WatchTree { merkle_tree } = args;
+    let merkle_tree = project.tree(&merkle_tree)?;
+
+    let pubsub = PubsubClient::new(ws_url)
+        .await
+        .with_context(|| format!("Error connecting to websocket {ws_url}"))
+        .code(ErrorCode::Rpc)?;
+
+    watch(client, &pubsub, merkle_tree, |e| output.print(e)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use arborist::chain::MockChain;
+    use futures_util::SinkExt;
+    use serde_json::{json, Value};
+    use solana_sdk::{account::Account, signature::Signature};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    use super::*;
+    use crate::test_support::{config_account, mint_transaction, tree_account};
+
+    /// Accept a single websocket connection, acknowledge its account
+    /// subscriptions, and replay a list of account notifications before
+    /// closing it
+    async fn pubsub_stand_in(notifications: Vec<(Pubkey, Slot, Account)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let mut subscriptions = HashMap::new();
+
+            while subscriptions.len() < 2 {
+                let msg = ws.next().await.unwrap().unwrap();
+                let req: Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+                assert_eq!(req["method"], "accountSubscribe");
+
+                let id = subscriptions.len() + 1;
+                subscriptions.insert(req["params"][0].as_str().unwrap().to_owned(), id);
+                ws.send(WsMessage::Text(
+                    json!({ "jsonrpc": "2.0", "result": id, "id": req["id"] }).to_string(),
+                ))
+                .await
+                .unwrap();
+            }
+
+            for (address, slot, account) in notifications {
+                let value =
+                    UiAccount::encode(&address, &account, UiAccountEncoding::Base64, None, None);
+                ws.send(WsMessage::Text(
+                    json!({
+                        "jsonrpc": "2.0",
+                        "method": "accountNotification",
+                        "params": {
+                            "result": { "context": { "slot": slot }, "value": value },
+                            "subscription": subscriptions[&address.to_string()],
+                        },
+                    })
+                    .to_string(),
+                ))
+                .await
+                .unwrap();
+            }
+
+            ws.close(None).await.unwrap();
+        });
+
+        url
+    }
+
+    #[test]
+    fn reports_tree_changes() {
+        let merkle_tree = Pubkey::new_unique();
+        let config_address = tree_config_address(&merkle_tree);
+        let creator = Pubkey::new_unique();
+        let config = TreeConfig {
+            tree_creator: creator,
+            tree_delegate: creator,
+            total_mint_capacity: 8,
+            num_minted: 0,
+            is_public: false,
+        };
+        let minted = TreeConfig {
+            num_minted: 1,
+            ..config
+        };
+        let delegated = TreeConfig {
+            tree_delegate: Pubkey::new_unique(),
+            ..minted
+        };
+
+        let chain = MockChain::new();
+        chain.set_account(merkle_tree, tree_account(0, [0; 32], 0));
+        chain.set_account(config_address, config_account(1, &config));
+
+        // The mint that produced the first update
+        let signature = Signature::new_unique();
+        chain.add_transaction(mint_transaction(signature, &merkle_tree, &creator, 10));
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let client = SolanaClient::new(chain, false);
+        let mut events = vec![];
+
+        let res = runtime.block_on(async {
+            let url = pubsub_stand_in(vec![
+                (config_address, 10, config_account(1, &minted)),
+                (merkle_tree, 10, tree_account(1, [1; 32], 1)),
+                // A lamport change does not modify the tree
+                (merkle_tree, 11, Account {
+                    lamports: 2,
+                    ..tree_account(1, [1; 32], 1)
+                }),
+                (config_address, 12, config_account(1, &delegated)),
+            ])
+            .await;
+            let pubsub = PubsubClient::new(&url).await.unwrap();
+
+            watch(&client, &pubsub, merkle_tree, |e| {
+                // Once watching, refetching the config returns the mint's
+                // result, as it would from an RPC node
+                client.set_account(config_address, config_account(1, &minted));
+                events.push(serde_json::to_value(e)?);
+                Ok(())
+            })
+            .await
+        });
+
+        // Notifications for the tree and its config may be interleaved
+        assert!(res.is_err());
+        events.sort_by_key(|e| e["slot"].as_u64());
+        assert_eq!(events, vec![
+            json!({
+                "event": "watching",
+                "merkle_tree": merkle_tree.to_string(),
+                "sequence_number": 0,
+                "root": bs58::encode([0; 32]).into_string(),
+                "leaves_used": 0,
+                "num_minted": 0,
+                "capacity": 8,
+                "remaining_capacity": 8,
+            }),
+            json!({
+                "event": "tree-updated",
+                "slot": 10,
+                "previous_sequence_number": 0,
+                "sequence_number": 1,
+                "root": bs58::encode([1; 32]).into_string(),
+                "leaves_used": 1,
+                "num_minted": 1,
+                "capacity": 8,
+                "remaining_capacity": 7,
+                "transactions": [{
+                    "signature": signature.to_string(),
+                    "instructions": ["MintV1"],
+                }],
+            }),
+            json!({
+                "event": "config-updated",
+                "slot": 12,
+                "tree_creator": creator.to_string(),
+                "tree_delegate": delegated.tree_delegate.to_string(),
+                "is_public": false,
+            }),
+        ]);
+    }
+}
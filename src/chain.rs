@@ -0,0 +1,468 @@
+//! An abstraction over the cluster calls made by `arborist`
+//!
+//! [`ChainClient`] covers everything needed to read accounts and send
+//! transactions, so that the same code can run against an RPC node, an
+//! in-process bank, or the scripted `MockChain` used in tests.  The latter two
+//! are only available with the `test-utils` feature.
+
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{
+        RpcSendTransactionConfig, RpcSimulateTransactionAccountsConfig,
+        RpcSimulateTransactionConfig, RpcTransactionConfig,
+    },
+    rpc_request::RpcRequest,
+};
+use solana_sdk::{
+    account::Account,
+    clock::{Slot, UnixTimestamp},
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::CompiledInstruction,
+    message::v0::LoadedAddresses,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    TransactionStatus, UiInstruction, UiTransactionEncoding,
+};
+
+#[cfg(any(test, feature = "test-utils"))]
+mod mock;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub use mock::{MockChain, MockSend};
+
+/// Settings for [`ChainClient::simulate`]
+#[derive(Debug, Default, Clone)]
+pub struct SimulationConfig {
+    /// Verify the transaction's signatures
+    pub sig_verify: bool,
+    /// Replace the transaction's blockhash with the latest blockhash
+    pub replace_recent_blockhash: bool,
+    /// Accounts whose post-simulation state should be returned
+    pub accounts: Vec<Pubkey>,
+}
+
+/// Outcome of simulating a transaction
+#[derive(Debug, Default, Clone)]
+pub struct SimulationResult {
+    /// The error the transaction failed with, if any
+    pub err: Option<TransactionError>,
+    /// Program logs produced by the transaction
+    pub logs: Vec<String>,
+    /// State of each requested account after the transaction
+    pub accounts: Vec<Option<Account>>,
+    /// Compute units consumed by the transaction, if reported
+    pub units_consumed: Option<u64>,
+}
+
+/// A transaction signature involving an address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureInfo {
+    /// The transaction signature
+    pub signature: Signature,
+    /// Slot the transaction was processed in
+    pub slot: Slot,
+    /// The error the transaction failed with, if any
+    pub err: Option<TransactionError>,
+    /// Estimated production time of the transaction's block
+    pub block_time: Option<UnixTimestamp>,
+}
+
+/// Instructions invoked by a top-level instruction of a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InnerInstructions {
+    /// Index of the top-level instruction
+    pub index: u8,
+    /// Instructions invoked via CPI, in order
+    pub instructions: Vec<CompiledInstruction>,
+}
+
+/// A processed transaction and its status metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmedTransaction {
+    /// Slot the transaction was processed in
+    pub slot: Slot,
+    /// Estimated production time of the transaction's block
+    pub block_time: Option<UnixTimestamp>,
+    /// The transaction
+    pub transaction: VersionedTransaction,
+    /// The error the transaction failed with, if any
+    pub err: Option<TransactionError>,
+    /// Program logs produced by the transaction
+    pub logs: Vec<String>,
+    /// Instructions invoked via CPI by each top-level instruction
+    pub inner_instructions: Vec<InnerInstructions>,
+    /// Accounts loaded from address lookup tables
+    pub loaded_addresses: LoadedAddresses,
+}
+
+impl ConfirmedTransaction {
+    /// Parse a transaction in the format returned by the `getTransaction` RPC
+    /// method with a binary encoding, such as a saved fixture.  Either the
+    /// full JSON-RPC response or its `result` may be given.
+    ///
+    /// # Errors
+    /// This function fails if the JSON is not a transaction in the expected
+    /// format.
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        let invalid = crate::Error::InvalidTransaction;
+
+        let mut value: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+        if let Some(result) = value.get_mut("result") {
+            value = result.take();
+        }
+
+        serde_json::from_value(value)
+            .map_err(|e| invalid(e.to_string()))
+            .and_then(|t| decode_transaction(t).map_err(invalid))
+    }
+}
+
+/// The cluster calls needed to read tree state and send transactions
+///
+/// Methods return the RPC client's error type so that callers can inspect
+/// transaction and preflight errors the same way regardless of backend.
+#[async_trait]
+pub trait ChainClient: Send + Sync {
+    /// Commitment level used for reads and confirmation
+    fn commitment(&self) -> CommitmentConfig;
+
+    /// Get the latest blockhash and the last block height at which it is
+    /// valid
+    async fn latest_blockhash(&self) -> ClientResult<(Hash, u64)>;
+
+    /// Check whether a blockhash can still be used by new transactions
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> ClientResult<bool>;
+
+    /// Get the current block height
+    async fn block_height(&self) -> ClientResult<u64>;
+
+    /// Get the current slot at the given commitment level
+    async fn slot(&self, commitment: CommitmentConfig) -> ClientResult<Slot>;
+
+    /// Get the genesis hash identifying the cluster
+    async fn genesis_hash(&self) -> ClientResult<Hash>;
+
+    /// Get the minimum balance for an account of the given size to be rent
+    /// exempt
+    async fn minimum_balance_for_rent_exemption(&self, size: usize) -> ClientResult<u64>;
+
+    /// Get the balance of an account, in lamports
+    async fn balance(&self, address: &Pubkey) -> ClientResult<u64>;
+
+    /// Get an account, returning `None` if it does not exist
+    async fn account(&self, address: &Pubkey) -> ClientResult<Option<Account>>;
+
+    /// Get several accounts, returning `None` for any that do not exist
+    async fn multiple_accounts(&self, addresses: &[Pubkey]) -> ClientResult<Vec<Option<Account>>>;
+
+    /// Get the prioritization fees recently paid for transactions writing to
+    /// the given accounts, in micro-lamports per compute unit
+    async fn recent_prioritization_fees(&self, accounts: &[Pubkey]) -> ClientResult<Vec<u64>>;
+
+    /// Submit a signed transaction without waiting for it to be processed
+    async fn send(
+        &self,
+        txn: &VersionedTransaction,
+        skip_preflight: bool,
+    ) -> ClientResult<Signature>;
+
+    /// Simulate a transaction without submitting it
+    async fn simulate(
+        &self,
+        txn: &VersionedTransaction,
+        config: SimulationConfig,
+    ) -> ClientResult<SimulationResult>;
+
+    /// Get the processing status of a set of signatures, returning `None` for
+    /// any that have not been processed
+    async fn signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> ClientResult<Vec<Option<TransactionStatus>>>;
+
+    /// Get up to `limit` signatures for transactions involving an address,
+    /// newest first, starting before the given signature if any
+    async fn signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        limit: usize,
+    ) -> ClientResult<Vec<SignatureInfo>>;
+
+    /// Get a processed transaction, returning `None` if it cannot be found
+    async fn transaction(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<Option<ConfirmedTransaction>>;
+}
+
+fn custom_error(msg: impl Into<String>) -> ClientError {
+    ClientErrorKind::Custom(msg.into()).into()
+}
+
+/// Signature and transaction history is only available at `confirmed` or
+/// higher
+fn history_commitment(commitment: CommitmentConfig) -> CommitmentConfig {
+    if commitment.is_at_least_confirmed() {
+        commitment
+    } else {
+        CommitmentConfig::confirmed()
+    }
+}
+
+fn parse_pubkeys(keys: &[String]) -> Result<Vec<Pubkey>, String> {
+    keys.iter()
+        .map(|k| Pubkey::from_str(k).map_err(|e| format!("Invalid address {k}: {e}")))
+        .collect()
+}
+
+fn decode_transaction(
+    encoded: EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<ConfirmedTransaction, String> {
+    let EncodedConfirmedTransactionWithStatusMeta {
+        slot,
+        transaction,
+        block_time,
+    } = encoded;
+    let meta = transaction.meta;
+    let transaction = transaction
+        .transaction
+        .decode()
+        .ok_or("Unable to decode transaction")?;
+
+    let Some(meta) = meta else {
+        return Ok(ConfirmedTransaction {
+            slot,
+            block_time,
+            transaction,
+            err: None,
+            logs: vec![],
+            inner_instructions: vec![],
+            loaded_addresses: LoadedAddresses::default(),
+        });
+    };
+
+    let inner_instructions = match meta.inner_instructions {
+        OptionSerializer::Some(inner) => inner
+            .into_iter()
+            .map(|i| {
+                let instructions = i
+                    .instructions
+                    .into_iter()
+                    .map(|ix| match ix {
+                        UiInstruction::Compiled(c) => Ok(CompiledInstruction {
+                            program_id_index: c.program_id_index,
+                            accounts: c.accounts,
+                            data: bs58::decode(&c.data)
+                                .into_vec()
+                                .map_err(|e| format!("Invalid inner instruction data: {e}"))?,
+                        }),
+                        UiInstruction::Parsed(_) => {
+                            Err("Unexpected parsed inner instruction".to_owned())
+                        },
+                    })
+                    .collect::<Result<_, String>>()?;
+
+                Ok(InnerInstructions {
+                    index: i.index,
+                    instructions,
+                })
+            })
+            .collect::<Result<_, String>>()?,
+        OptionSerializer::None | OptionSerializer::Skip => vec![],
+    };
+
+    let loaded_addresses = match meta.loaded_addresses {
+        OptionSerializer::Some(l) => LoadedAddresses {
+            writable: parse_pubkeys(&l.writable)?,
+            readonly: parse_pubkeys(&l.readonly)?,
+        },
+        OptionSerializer::None | OptionSerializer::Skip => LoadedAddresses::default(),
+    };
+
+    Ok(ConfirmedTransaction {
+        slot,
+        block_time,
+        transaction,
+        err: meta.err,
+        logs: match meta.log_messages {
+            OptionSerializer::Some(l) => l,
+            OptionSerializer::None | OptionSerializer::Skip => vec![],
+        },
+        inner_instructions,
+        loaded_addresses,
+    })
+}
+
+#[async_trait]
+impl ChainClient for RpcClient {
+    fn commitment(&self) -> CommitmentConfig { RpcClient::commitment(self) }
+
+    async fn latest_blockhash(&self) -> ClientResult<(Hash, u64)> {
+        self.get_latest_blockhash_with_commitment(RpcClient::commitment(self))
+            .await
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> ClientResult<bool> {
+        RpcClient::is_blockhash_valid(self, blockhash, RpcClient::commitment(self)).await
+    }
+
+    async fn block_height(&self) -> ClientResult<u64> { self.get_block_height().await }
+
+    async fn slot(&self, commitment: CommitmentConfig) -> ClientResult<Slot> {
+        self.get_slot_with_commitment(commitment).await
+    }
+
+    async fn genesis_hash(&self) -> ClientResult<Hash> { self.get_genesis_hash().await }
+
+    async fn minimum_balance_for_rent_exemption(&self, size: usize) -> ClientResult<u64> {
+        self.get_minimum_balance_for_rent_exemption(size).await
+    }
+
+    async fn balance(&self, address: &Pubkey) -> ClientResult<u64> {
+        self.get_balance(address).await
+    }
+
+    async fn account(&self, address: &Pubkey) -> ClientResult<Option<Account>> {
+        Ok(self
+            .get_account_with_commitment(address, RpcClient::commitment(self))
+            .await?
+            .value)
+    }
+
+    async fn multiple_accounts(&self, addresses: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        self.get_multiple_accounts(addresses).await
+    }
+
+    async fn recent_prioritization_fees(&self, accounts: &[Pubkey]) -> ClientResult<Vec<u64>> {
+        Ok(self
+            .get_recent_prioritization_fees(accounts)
+            .await?
+            .into_iter()
+            .map(|f| f.prioritization_fee)
+            .collect())
+    }
+
+    async fn send(
+        &self,
+        txn: &VersionedTransaction,
+        skip_preflight: bool,
+    ) -> ClientResult<Signature> {
+        self.send_transaction_with_config(txn, RpcSendTransactionConfig {
+            skip_preflight,
+            max_retries: Some(0),
+            ..RpcSendTransactionConfig::default()
+        })
+        .await
+    }
+
+    async fn simulate(
+        &self,
+        txn: &VersionedTransaction,
+        config: SimulationConfig,
+    ) -> ClientResult<SimulationResult> {
+        let SimulationConfig {
+            sig_verify,
+            replace_recent_blockhash,
+            accounts,
+        } = config;
+
+        let res = self
+            .simulate_transaction_with_config(txn, RpcSimulateTransactionConfig {
+                sig_verify,
+                replace_recent_blockhash,
+                accounts: (!accounts.is_empty()).then(|| RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: accounts.iter().map(ToString::to_string).collect(),
+                }),
+                ..RpcSimulateTransactionConfig::default()
+            })
+            .await?
+            .value;
+
+        Ok(SimulationResult {
+            err: res.err,
+            logs: res.logs.unwrap_or_default(),
+            accounts: res
+                .accounts
+                .unwrap_or_default()
+                .into_iter()
+                .map(|a| a.and_then(|a| a.decode()))
+                .collect(),
+            units_consumed: res.units_consumed,
+        })
+    }
+
+    async fn signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> ClientResult<Vec<Option<TransactionStatus>>> {
+        Ok(self.get_signature_statuses(signatures).await?.value)
+    }
+
+    async fn signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        limit: usize,
+    ) -> ClientResult<Vec<SignatureInfo>> {
+        self.get_signatures_for_address_with_config(
+            address,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: Some(limit),
+                commitment: Some(history_commitment(RpcClient::commitment(self))),
+            },
+        )
+        .await?
+        .into_iter()
+        .map(|s| {
+            Ok(SignatureInfo {
+                signature: s
+                    .signature
+                    .parse()
+                    .map_err(|e| format!("Invalid signature {}: {e}", s.signature))?,
+                slot: s.slot,
+                err: s.err,
+                block_time: s.block_time,
+            })
+        })
+        .collect::<Result<_, String>>()
+        .map_err(custom_error)
+    }
+
+    async fn transaction(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<Option<ConfirmedTransaction>> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(history_commitment(RpcClient::commitment(self))),
+            max_supported_transaction_version: Some(0),
+        };
+
+        // `get_transaction` treats a missing transaction as an error, so the
+        // request is made directly to distinguish the two
+        self.send::<Option<EncodedConfirmedTransactionWithStatusMeta>>(
+            RpcRequest::GetTransaction,
+            serde_json::json!([signature.to_string(), config]),
+        )
+        .await?
+        .map(decode_transaction)
+        .transpose()
+        .map_err(custom_error)
+    }
+}
@@ -0,0 +1,325 @@
+//! A scripted [`ChainClient`] for tests
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Mutex, PoisonError},
+};
+
+use async_trait::async_trait;
+use solana_client::client_error::Result as ClientResult;
+use solana_sdk::{
+    account::Account,
+    clock::Slot,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    message::v0::LoadedAddresses,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+
+use super::{ChainClient, ConfirmedTransaction, SignatureInfo, SimulationConfig, SimulationResult};
+
+/// Scripted behavior of the next transaction sent to a [`MockChain`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockSend {
+    /// The transaction is accepted and confirmed
+    Confirm,
+    /// The transaction is accepted and processed, but fails
+    Fail(TransactionError),
+    /// The transaction is rejected before being processed, as if by a
+    /// preflight check
+    Reject(TransactionError),
+    /// The transaction is accepted but never processed
+    Drop,
+}
+
+#[derive(Debug)]
+struct MockState {
+    block_height: u64,
+    blockhashes: HashMap<Hash, u64>,
+    latest_blockhash: Option<(Hash, u64)>,
+    accounts: HashMap<Pubkey, Account>,
+    prioritization_fees: Vec<u64>,
+    sends: VecDeque<MockSend>,
+    simulations: VecDeque<SimulationResult>,
+    sent: Vec<VersionedTransaction>,
+    statuses: HashMap<Signature, Option<TransactionError>>,
+    transactions: HashMap<Signature, ConfirmedTransaction>,
+    history: HashMap<Pubkey, Vec<Signature>>,
+}
+
+/// A scripted [`ChainClient`] that records every transaction sent to it
+///
+/// Accounts, fees, and the outcome of each sent transaction are configured
+/// up front.  Sent transactions do not modify any accounts.  The block
+/// height, and the slot with it, advances each time either is queried, so
+/// that transactions which are never processed eventually expire.
+#[derive(Debug)]
+pub struct MockChain {
+    state: Mutex<MockState>,
+}
+
+impl Default for MockChain {
+    fn default() -> Self { Self::new() }
+}
+
+impl MockChain {
+    /// Number of blocks for which a blockhash returned by the mock is valid
+    pub const BLOCKHASH_LIFETIME: u64 = 2;
+    /// Slot reported for every processed transaction
+    pub const SLOT: Slot = 1;
+
+    /// Construct an empty mock chain
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(MockState {
+                block_height: 0,
+                blockhashes: HashMap::new(),
+                latest_blockhash: None,
+                accounts: HashMap::new(),
+                prioritization_fees: vec![],
+                sends: VecDeque::new(),
+                simulations: VecDeque::new(),
+                sent: vec![],
+                statuses: HashMap::new(),
+                transactions: HashMap::new(),
+                history: HashMap::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Create or replace an account
+    pub fn set_account(&self, address: Pubkey, account: Account) {
+        self.state().accounts.insert(address, account);
+    }
+
+    /// Set the fees returned by
+    /// [`recent_prioritization_fees`](ChainClient::recent_prioritization_fees)
+    pub fn set_prioritization_fees(&self, fees: Vec<u64>) {
+        self.state().prioritization_fees = fees;
+    }
+
+    /// Script the outcome of the next transaction sent.  Transactions sent
+    /// with no scripted outcome are confirmed.
+    pub fn push_send(&self, send: MockSend) { self.state().sends.push_back(send); }
+
+    /// Script the result of the next simulation.  Simulations with no
+    /// scripted result succeed, returning the current state of the requested
+    /// accounts.
+    pub fn push_simulation(&self, result: SimulationResult) {
+        self.state().simulations.push_back(result);
+    }
+
+    /// Record a processed transaction, making it available from
+    /// [`transaction`](ChainClient::transaction) and
+    /// [`signatures_for_address`](ChainClient::signatures_for_address) for
+    /// each of its accounts
+    pub fn add_transaction(&self, txn: ConfirmedTransaction) {
+        let mut state = self.state();
+        let signature = txn.transaction.signatures[0];
+
+        for key in txn
+            .transaction
+            .message
+            .static_account_keys()
+            .iter()
+            .chain(&txn.loaded_addresses.writable)
+            .chain(&txn.loaded_addresses.readonly)
+        {
+            let history = state.history.entry(*key).or_default();
+            if !history.contains(&signature) {
+                history.push(signature);
+            }
+        }
+
+        state.statuses.insert(signature, txn.err.clone());
+        state.transactions.insert(signature, txn);
+    }
+
+    /// Every transaction sent to the mock, in order, including those that
+    /// were rejected or dropped
+    #[must_use]
+    pub fn sent(&self) -> Vec<VersionedTransaction> { self.state().sent.clone() }
+}
+
+#[async_trait]
+impl ChainClient for MockChain {
+    fn commitment(&self) -> CommitmentConfig { CommitmentConfig::confirmed() }
+
+    async fn latest_blockhash(&self) -> ClientResult<(Hash, u64)> {
+        let mut state = self.state();
+        let height = state.block_height;
+
+        // Issue a new blockhash once per block
+        let latest = match state.latest_blockhash {
+            Some(l) if l.1 == height + Self::BLOCKHASH_LIFETIME => l,
+            _ => (Hash::new_unique(), height + Self::BLOCKHASH_LIFETIME),
+        };
+        state.blockhashes.insert(latest.0, latest.1);
+        state.latest_blockhash = Some(latest);
+
+        Ok(latest)
+    }
+
+    async fn is_blockhash_valid(&self, blockhash: &Hash) -> ClientResult<bool> {
+        let state = self.state();
+
+        Ok(state
+            .blockhashes
+            .get(blockhash)
+            .is_some_and(|&h| h >= state.block_height))
+    }
+
+    async fn block_height(&self) -> ClientResult<u64> {
+        let mut state = self.state();
+        state.block_height += 1;
+
+        Ok(state.block_height)
+    }
+
+    async fn slot(&self, _: CommitmentConfig) -> ClientResult<Slot> {
+        let mut state = self.state();
+        state.block_height += 1;
+
+        Ok(state.block_height + Self::SLOT)
+    }
+
+    async fn genesis_hash(&self) -> ClientResult<Hash> { Ok(Hash::default()) }
+
+    async fn minimum_balance_for_rent_exemption(&self, size: usize) -> ClientResult<u64> {
+        Ok(solana_sdk::rent::Rent::default().minimum_balance(size))
+    }
+
+    async fn balance(&self, address: &Pubkey) -> ClientResult<u64> {
+        Ok(self.state().accounts.get(address).map_or(0, |a| a.lamports))
+    }
+
+    async fn account(&self, address: &Pubkey) -> ClientResult<Option<Account>> {
+        Ok(self.state().accounts.get(address).cloned())
+    }
+
+    async fn multiple_accounts(&self, addresses: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        let state = self.state();
+
+        Ok(addresses
+            .iter()
+            .map(|a| state.accounts.get(a).cloned())
+            .collect())
+    }
+
+    async fn recent_prioritization_fees(&self, _: &[Pubkey]) -> ClientResult<Vec<u64>> {
+        Ok(self.state().prioritization_fees.clone())
+    }
+
+    async fn send(&self, txn: &VersionedTransaction, _: bool) -> ClientResult<Signature> {
+        let signature = txn.signatures[0];
+        let send = {
+            let mut state = self.state();
+            state.sent.push(txn.clone());
+            state.sends.pop_front().unwrap_or(MockSend::Confirm)
+        };
+
+        let err = match send {
+            MockSend::Confirm => None,
+            MockSend::Fail(e) => Some(e),
+            MockSend::Reject(e) => return Err(e.into()),
+            MockSend::Drop => return Ok(signature),
+        };
+
+        self.add_transaction(ConfirmedTransaction {
+            slot: Self::SLOT,
+            block_time: None,
+            transaction: txn.clone(),
+            err,
+            logs: vec![],
+            inner_instructions: vec![],
+            loaded_addresses: LoadedAddresses::default(),
+        });
+
+        Ok(signature)
+    }
+
+    async fn simulate(
+        &self,
+        _: &VersionedTransaction,
+        config: SimulationConfig,
+    ) -> ClientResult<SimulationResult> {
+        let mut state = self.state();
+
+        Ok(state
+            .simulations
+            .pop_front()
+            .unwrap_or_else(|| SimulationResult {
+                accounts: config
+                    .accounts
+                    .iter()
+                    .map(|a| state.accounts.get(a).cloned())
+                    .collect(),
+                ..SimulationResult::default()
+            }))
+    }
+
+    async fn signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> ClientResult<Vec<Option<TransactionStatus>>> {
+        let state = self.state();
+
+        Ok(signatures
+            .iter()
+            .map(|s| {
+                state.statuses.get(s).map(|err| TransactionStatus {
+                    slot: Self::SLOT,
+                    confirmations: None,
+                    status: err.clone().map_or(Ok(()), Err),
+                    err: err.clone(),
+                    confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+                })
+            })
+            .collect())
+    }
+
+    async fn signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<Signature>,
+        limit: usize,
+    ) -> ClientResult<Vec<SignatureInfo>> {
+        let state = self.state();
+        let Some(history) = state.history.get(address) else {
+            return Ok(vec![]);
+        };
+
+        Ok(history
+            .iter()
+            .rev()
+            .skip_while(|&&s| before.is_some_and(|b| b != s))
+            .skip(usize::from(before.is_some()))
+            .take(limit)
+            .map(|s| {
+                let txn = &state.transactions[s];
+
+                SignatureInfo {
+                    signature: *s,
+                    slot: txn.slot,
+                    err: txn.err.clone(),
+                    block_time: txn.block_time,
+                }
+            })
+            .collect())
+    }
+
+    async fn transaction(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<Option<ConfirmedTransaction>> {
+        Ok(self.state().transactions.get(signature).cloned())
+    }
+}
@@ -0,0 +1,1444 @@
+//! An async client for reading tree state and sending transactions
+//!
+//! [`SolanaClient`] wraps a [`ChainClient`] with everything needed to land
+//! transactions reliably: priority fees and compute limits, address lookup
+//! tables, durable nonces, offline signing, simulation, and rebroadcasting and
+//! re-signing transactions until they are confirmed or their blockhash
+//! expires.
+
+use std::{
+    fmt,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    nonblocking::{nonce_utils, rpc_client::RpcClient},
+    rpc_request::{RpcError, RpcResponseErrorData},
+    rpc_response::RpcSimulateTransactionResult,
+};
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, Message, VersionedMessage},
+    nonce,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Signature,
+    signer::Signer,
+    signers::Signers,
+    system_instruction,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use solana_address_lookup_table_program::{
+    instruction::{create_lookup_table, extend_lookup_table},
+    state::LOOKUP_TABLE_MAX_ADDRESSES,
+};
+
+use crate::{
+    accounts::{self, MerkleTreeAccount, TreeConfig},
+    chain::{ChainClient, SimulationConfig, SimulationResult},
+    lut::{self, LookupTable, Registry, MAX_EXTEND_ADDRESSES},
+    tree::tree_config_address,
+    Error, Result,
+};
+
+/// Maximum number of accounts accepted by `getRecentPrioritizationFees`
+const MAX_FEE_ACCOUNTS: usize = 128;
+
+/// Strategy for selecting the priority fee paid by each transaction
+#[derive(Debug, Default, Clone, Copy)]
+pub enum PriorityFee {
+    /// Do not pay a priority fee
+    #[default]
+    None,
+    /// Pay a fixed fee, in micro-lamports per compute unit
+    Fixed(u64),
+    /// Select a fee from the recent prioritization fees paid for the writable
+    /// accounts of each transaction
+    Auto {
+        /// Percentile (0-100) of recent fees to pay
+        percentile: u8,
+        /// Upper bound on the selected fee, in micro-lamports per compute unit
+        max: Option<u64>,
+    },
+}
+
+/// Compute budget settings applied to every transaction sent by the client
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ComputeBudget {
+    /// Priority fee paid by each transaction
+    pub priority_fee: PriorityFee,
+    /// Compute unit limit requested by each transaction, if not the default
+    pub unit_limit: Option<u32>,
+}
+
+/// Settings controlling how transactions are submitted
+#[derive(Debug, Clone, Copy)]
+pub struct SendConfig {
+    /// Simulate transactions instead of sending them
+    pub dry_run: bool,
+    /// Skip the preflight simulation performed by the RPC node
+    pub skip_preflight: bool,
+    /// Number of times to re-sign a transaction with a new blockhash if it
+    /// expires before being processed
+    pub blockhash_retries: usize,
+    /// Interval at which unconfirmed transactions are rebroadcast
+    pub rebroadcast_interval: Duration,
+    /// Sign transactions and report them instead of sending them
+    pub sign_only: bool,
+    /// Blockhash to sign transactions with, instead of fetching the latest
+    /// blockhash from the cluster
+    pub blockhash: Option<Hash>,
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            skip_preflight: false,
+            blockhash_retries: 2,
+            rebroadcast_interval: Duration::from_secs(2),
+            sign_only: false,
+            blockhash: None,
+        }
+    }
+}
+
+/// Status of a set of signatures for the same transaction
+enum SignatureStatus {
+    /// One of the signatures reached the desired commitment level
+    Confirmed(Signature),
+    /// One of the signatures was processed, but has not yet reached the
+    /// desired commitment level
+    Pending,
+    /// None of the signatures have been processed
+    NotFound,
+}
+
+/// Condition after which a signed transaction can no longer be processed
+#[derive(Debug, Clone, Copy)]
+enum Expiry {
+    /// The block height exceeds the last valid block height of the
+    /// transaction's blockhash
+    BlockHeight(u64),
+    /// The transaction's blockhash is no longer valid
+    Blockhash(Hash),
+    /// The durable nonce used as the transaction's blockhash has been advanced
+    Nonce { account: Pubkey, blockhash: Hash },
+}
+
+/// Change to a writable account observed while simulating a transaction
+#[derive(Debug, Serialize)]
+pub struct AccountChange {
+    /// Address of the account
+    pub address: String,
+    /// Balance of the account before the transaction
+    pub lamports_before: u64,
+    /// Balance of the account after the transaction
+    pub lamports_after: u64,
+    /// Data length of the account before the transaction
+    pub data_len_before: usize,
+    /// Data length of the account after the transaction
+    pub data_len_after: usize,
+    /// Owner of the account before the transaction, if it existed
+    pub owner_before: Option<String>,
+    /// Owner of the account after the transaction, if it exists
+    pub owner_after: Option<String>,
+}
+
+/// Report produced by simulating a transaction
+#[derive(Debug, Serialize)]
+pub struct Simulation {
+    /// Compute units consumed by the transaction, if reported
+    pub units_consumed: Option<u64>,
+    /// Program logs produced by the transaction
+    pub logs: Vec<String>,
+    /// Changes to each writable account of the transaction
+    pub account_changes: Vec<AccountChange>,
+}
+
+/// Transaction signed offline, to be completed with any absent signatures and
+/// broadcast later
+#[derive(Debug, Serialize)]
+pub struct SignOnly {
+    /// Blockhash or durable nonce the transaction was signed with
+    pub blockhash: String,
+    /// Signatures present in the transaction, as `PUBKEY=SIGNATURE` pairs
+    pub signers: Vec<String>,
+    /// Public keys of required signers whose signatures are absent
+    pub absent_signers: Vec<String>,
+    /// The serialized transaction, encoded as base58
+    pub transaction_base58: String,
+    /// The serialized transaction, encoded as base64
+    pub transaction_base64: String,
+}
+
+impl SignOnly {
+    fn new(txn: &VersionedTransaction) -> Result<Self> {
+        let bytes = bincode::serialize(txn).map_err(|e| Error::InvalidTransaction(e.to_string()))?;
+        let mut signers = vec![];
+        let mut absent_signers = vec![];
+
+        for (key, sig) in txn
+            .message
+            .static_account_keys()
+            .iter()
+            .zip(&txn.signatures)
+        {
+            if *sig == Signature::default() {
+                absent_signers.push(key.to_string());
+            } else {
+                signers.push(format!("{key}={sig}"));
+            }
+        }
+
+        Ok(Self {
+            blockhash: txn.message.recent_blockhash().to_string(),
+            signers,
+            absent_signers,
+            transaction_base58: bs58::encode(&bytes).into_string(),
+            transaction_base64: base64::encode(&bytes),
+        })
+    }
+}
+
+/// Final state of a transaction submitted by [`SolanaClient::send_transaction`]
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum TransactionOutcome {
+    /// The transaction was sent and confirmed
+    Confirmed {
+        /// The signature of the confirmed transaction
+        signature: String,
+    },
+    /// The transaction was only simulated
+    Simulated(Simulation),
+    /// The transaction was signed but not sent
+    SignOnly(SignOnly),
+}
+
+/// Extract the program logs from a failed preflight check, if present
+fn preflight_logs(err: &ClientError) -> Option<Vec<String>> {
+    match err.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data:
+                RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
+                    logs: Some(logs),
+                    ..
+                }),
+            ..
+        }) if !logs.is_empty() => Some(logs.clone()),
+        _ => None,
+    }
+}
+
+/// Compile a set of instructions into a message, producing a versioned
+/// message if any lookup tables are given and a legacy message otherwise
+fn compile_message(
+    instructions: &[Instruction],
+    payer: Option<&Pubkey>,
+    blockhash: &Hash,
+    tables: &[AddressLookupTableAccount],
+) -> Result<VersionedMessage> {
+    if tables.is_empty() {
+        return Ok(VersionedMessage::Legacy(Message::new_with_blockhash(
+            instructions,
+            payer,
+            blockhash,
+        )));
+    }
+
+    let payer = payer.ok_or_else(|| {
+        Error::InvalidTransaction("a fee payer is required for versioned transactions".into())
+    })?;
+
+    v0::Message::try_compile(payer, instructions, tables, *blockhash)
+        .map(VersionedMessage::V0)
+        .map_err(|e| Error::InvalidTransaction(e.to_string()))
+}
+
+/// Ensure every account required to sign a set of instructions, including
+/// the fee payer, has a signer, so that missing signers are reported before
+/// any network requests are made
+fn check_signers(
+    instructions: &[Instruction],
+    payer: Option<&Pubkey>,
+    signers: &impl Signers,
+) -> Result<()> {
+    let pubkeys = signers.try_pubkeys()?;
+
+    let mut missing = payer
+        .into_iter()
+        .chain(
+            instructions
+                .iter()
+                .flat_map(|i| &i.accounts)
+                .filter(|a| a.is_signer)
+                .map(|a| &a.pubkey),
+        )
+        .filter(|k| !pubkeys.contains(k))
+        .copied()
+        .collect::<Vec<_>>();
+    missing.sort_unstable();
+    missing.dedup();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    Err(Error::MissingSigners(missing))
+}
+
+/// Get the signature identifying a transaction, i.e. that of its fee payer
+fn first_signature(txn: &VersionedTransaction) -> Result<Signature> {
+    txn.signatures
+        .first()
+        .copied()
+        .ok_or_else(|| Error::InvalidTransaction("transaction has no signatures".into()))
+}
+
+/// Sign a message with a set of signers and an optional additional signer,
+/// ignoring any signers not required by the message
+///
+/// # Errors
+/// This function fails if any required signer is missing or signing fails.
+pub fn sign_message(
+    message: VersionedMessage,
+    signers: &impl Signers,
+    extra: Option<&dyn Signer>,
+) -> Result<VersionedTransaction> {
+    let num_signers = usize::from(message.header().num_required_signatures);
+    let data = message.serialize();
+    let mut pubkeys = signers.try_pubkeys()?;
+    let mut sigs = signers.try_sign_message(&data)?;
+
+    if let Some(extra) = extra {
+        pubkeys.push(extra.try_pubkey()?);
+        sigs.push(extra.try_sign_message(&data)?);
+    }
+
+    let signatures = message.static_account_keys()[..num_signers]
+        .iter()
+        .map(|key| {
+            pubkeys
+                .iter()
+                .position(|p| p == key)
+                .map(|i| sigs[i])
+                .ok_or_else(|| Error::MissingSigners(vec![*key]))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(VersionedTransaction {
+        signatures,
+        message,
+    })
+}
+
+fn new_spinner() -> ProgressBar {
+    let bar = ProgressBar::new_spinner();
+    bar.set_style(ProgressStyle::default_spinner().template("{spinner:.green} {wide_msg}"));
+    bar.enable_steady_tick(100);
+    bar
+}
+
+/// Address lookup tables used to compile transactions
+#[derive(Debug, Default, Clone)]
+pub enum LookupTables {
+    /// Send legacy transactions
+    #[default]
+    None,
+    /// Send versioned transactions using the given lookup tables
+    Fixed(Vec<Pubkey>),
+    /// Send versioned transactions using a lookup table owned by the fee payer,
+    /// automatically creating it or adding any Merkle tree and program
+    /// accounts used by each transaction.  The addresses of created tables
+    /// are recorded in the user's configuration directory, unless another
+    /// file is set with
+    /// [`lookup_table_registry`](SolanaClient::lookup_table_registry).
+    Auto,
+}
+
+/// Durable nonce account used in place of a recent blockhash
+pub struct DurableNonce {
+    /// Address of the nonce account
+    pub account: Pubkey,
+    /// Signer authorized to advance the nonce, if not the fee payer
+    pub authority: Option<Box<dyn Signer>>,
+}
+
+impl fmt::Debug for DurableNonce {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DurableNonce")
+            .field("account", &self.account)
+            .field("authority", &self.authority.as_ref().map(|a| a.pubkey()))
+            .finish()
+    }
+}
+
+/// An async client for Bubblegum trees and the transactions that manage them
+#[allow(clippy::module_name_repetitions)]
+pub struct SolanaClient<C = RpcClient> {
+    chain: C,
+    progress: bool,
+    compute_budget: ComputeBudget,
+    send_config: SendConfig,
+    lookup_tables: LookupTables,
+    lookup_table_registry: Option<PathBuf>,
+    nonce: Option<DurableNonce>,
+}
+
+impl<C: ChainClient> fmt::Debug for SolanaClient<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SolanaClient")
+            .field("commitment", &self.chain.commitment())
+            .field("compute_budget", &self.compute_budget)
+            .field("send_config", &self.send_config)
+            .field("lookup_tables", &self.lookup_tables)
+            .field("lookup_table_registry", &self.lookup_table_registry)
+            .field("nonce", &self.nonce)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C: ChainClient> SolanaClient<C> {
+    /// Construct a new client over a chain client, optionally displaying a
+    /// progress spinner while confirming transactions
+    #[inline]
+    #[must_use]
+    pub fn new(chain: C, progress: bool) -> Self {
+        Self {
+            chain,
+            progress,
+            compute_budget: ComputeBudget::default(),
+            send_config: SendConfig::default(),
+            lookup_tables: LookupTables::default(),
+            lookup_table_registry: None,
+            nonce: None,
+        }
+    }
+
+    /// Get the underlying chain client
+    #[inline]
+    #[must_use]
+    pub fn chain(&self) -> &C { &self.chain }
+
+    /// Set the durable nonce used in place of a recent blockhash for every
+    /// transaction sent by the client
+    #[inline]
+    #[must_use]
+    pub fn nonce(self, nonce: Option<DurableNonce>) -> Self { Self { nonce, ..self } }
+
+    /// Load the current state of a durable nonce account
+    ///
+    /// # Errors
+    /// This function fails if the account cannot be fetched or is not an
+    /// initialized nonce account.
+    pub async fn nonce_data(&self, account: &Pubkey) -> Result<nonce::state::Data> {
+        let data = self
+            .chain
+            .account(account)
+            .await?
+            .ok_or(Error::AccountNotFound(*account))?;
+
+        nonce_utils::data_from_account(&data).map_err(|e| Error::InvalidAccountData {
+            kind: "nonce",
+            reason: e.to_string(),
+        })
+    }
+
+    /// Set the address lookup tables used to compile transactions
+    #[inline]
+    #[must_use]
+    pub fn lookup_tables(self, lookup_tables: LookupTables) -> Self {
+        Self {
+            lookup_tables,
+            ..self
+        }
+    }
+
+    /// Set the file recording the lookup tables created in
+    /// [`LookupTables::Auto`] mode
+    #[inline]
+    #[must_use]
+    pub fn lookup_table_registry(self, path: impl Into<PathBuf>) -> Self {
+        Self {
+            lookup_table_registry: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Get the address lookup tables used to compile transactions
+    #[inline]
+    #[must_use]
+    pub fn lookup_table_mode(&self) -> &LookupTables { &self.lookup_tables }
+
+    /// Check whether transactions are simulated instead of sent
+    #[inline]
+    #[must_use]
+    pub fn is_dry_run(&self) -> bool { self.send_config.dry_run }
+
+    /// Check whether transactions are signed and reported instead of sent
+    #[inline]
+    #[must_use]
+    pub fn is_sign_only(&self) -> bool { self.send_config.sign_only }
+
+    /// Get the minimum balance for an account of the given size to be rent
+    /// exempt.  When signing offline with a fixed blockhash this is computed
+    /// locally from the default rent parameters.
+    ///
+    /// # Errors
+    /// This function fails if the balance cannot be fetched.
+    pub async fn rent_exemption(&self, size: usize) -> Result<u64> {
+        let SendConfig {
+            sign_only,
+            blockhash,
+            ..
+        } = self.send_config;
+
+        if sign_only && blockhash.is_some() {
+            return Ok(Rent::default().minimum_balance(size));
+        }
+
+        Ok(self.chain.minimum_balance_for_rent_exemption(size).await?)
+    }
+
+    /// Set how transactions sent by the client are submitted
+    #[inline]
+    #[must_use]
+    pub fn send_config(self, send_config: SendConfig) -> Self {
+        Self {
+            send_config,
+            ..self
+        }
+    }
+
+    /// Set the compute budget applied to every transaction sent by the client
+    #[inline]
+    #[must_use]
+    pub fn compute_budget(self, compute_budget: ComputeBudget) -> Self {
+        Self {
+            compute_budget,
+            ..self
+        }
+    }
+
+    async fn account_data(&self, address: &Pubkey) -> Result<Vec<u8>> {
+        self.chain
+            .account(address)
+            .await?
+            .map(|a| a.data)
+            .ok_or(Error::AccountNotFound(*address))
+    }
+
+    /// Fetch and decode the Bubblegum configuration of a tree
+    ///
+    /// # Errors
+    /// This function fails if the configuration cannot be fetched or decoded.
+    pub async fn tree_config(&self, merkle_tree: &Pubkey) -> Result<TreeConfig> {
+        let data = self.account_data(&tree_config_address(merkle_tree)).await?;
+
+        accounts::decode_tree_config(&data)
+    }
+
+    /// Fetch and decode a Merkle tree account
+    ///
+    /// # Errors
+    /// This function fails if the tree cannot be fetched or decoded.
+    pub async fn merkle_tree(&self, merkle_tree: &Pubkey) -> Result<MerkleTreeAccount> {
+        let data = self.account_data(merkle_tree).await?;
+
+        accounts::decode_merkle_tree(&data)
+    }
+
+    /// Fetch and decode an address lookup table, returning `None` if it does
+    /// not exist
+    ///
+    /// # Errors
+    /// This function fails if the table cannot be fetched or decoded.
+    pub async fn lookup_table(&self, address: &Pubkey) -> Result<Option<LookupTable>> {
+        self.chain
+            .account(address)
+            .await?
+            .map(|a| LookupTable::decode(&a.data))
+            .transpose()
+    }
+
+    /// Select a priority fee from the recent fees paid for the writable
+    /// accounts of a set of instructions
+    async fn auto_priority_fee(
+        &self,
+        instructions: &[Instruction],
+        percentile: u8,
+        max: Option<u64>,
+    ) -> Result<u64> {
+        let mut accounts = instructions
+            .iter()
+            .flat_map(|i| &i.accounts)
+            .filter(|a| a.is_writable)
+            .map(|a| a.pubkey)
+            .collect::<Vec<_>>();
+        accounts.sort_unstable();
+        accounts.dedup();
+        accounts.truncate(MAX_FEE_ACCOUNTS);
+
+        let mut fees = self.chain.recent_prioritization_fees(&accounts).await?;
+        fees.sort_unstable();
+
+        let fee = if fees.is_empty() {
+            0
+        } else {
+            // nearest-rank percentile
+            let rank = (usize::from(percentile.min(100)) * fees.len()).div_ceil(100);
+            fees[rank.saturating_sub(1)]
+        };
+
+        Ok(max.map_or(fee, |m| fee.min(m)))
+    }
+
+    /// Build the compute budget instructions to prepend to a transaction
+    async fn compute_budget_instructions(
+        &self,
+        instructions: &[Instruction],
+    ) -> Result<Vec<Instruction>> {
+        let ComputeBudget {
+            priority_fee,
+            unit_limit,
+        } = self.compute_budget;
+
+        let price = match priority_fee {
+            PriorityFee::None => None,
+            PriorityFee::Fixed(f) => Some(f),
+            PriorityFee::Auto { percentile, max } => Some(
+                self.auto_priority_fee(instructions, percentile, max)
+                    .await?,
+            ),
+        };
+
+        Ok(unit_limit
+            .map(ComputeBudgetInstruction::set_compute_unit_limit)
+            .into_iter()
+            .chain(
+                price
+                    .filter(|&p| p > 0)
+                    .map(ComputeBudgetInstruction::set_compute_unit_price),
+            )
+            .collect())
+    }
+
+    /// Resolve the lookup tables to use for a transaction according to the
+    /// client's lookup table mode
+    async fn resolve_lookup_tables(
+        &self,
+        instructions: &[Instruction],
+        payer: Option<&Pubkey>,
+        signers: &impl Signers,
+    ) -> Result<Vec<AddressLookupTableAccount>> {
+        match &self.lookup_tables {
+            LookupTables::None => Ok(vec![]),
+            LookupTables::Fixed(keys) => {
+                let mut tables = Vec::with_capacity(keys.len());
+
+                for &key in keys {
+                    let LookupTable { addresses, .. } = self
+                        .lookup_table(&key)
+                        .await?
+                        .ok_or(Error::AccountNotFound(key))?;
+
+                    tables.push(AddressLookupTableAccount { key, addresses });
+                }
+
+                Ok(tables)
+            },
+            LookupTables::Auto => {
+                let payer = payer.ok_or_else(|| {
+                    Error::InvalidTransaction("a fee payer is required for lookup tables".into())
+                })?;
+
+                self.auto_lookup_table(instructions, *payer, signers)
+                    .await
+                    .map(|t| t.into_iter().collect())
+            },
+        }
+    }
+
+    /// Find or create the fee payer's automatic lookup table, adding any
+    /// missing addresses used by the given instructions
+    async fn auto_lookup_table(
+        &self,
+        instructions: &[Instruction],
+        payer: Pubkey,
+        signers: &impl Signers,
+    ) -> Result<Option<AddressLookupTableAccount>> {
+        let wanted = lut::lookup_addresses(instructions);
+
+        if wanted.is_empty() {
+            return Ok(None);
+        }
+
+        let genesis = self.chain.genesis_hash().await?;
+        let registry_key = format!("{genesis}/{payer}");
+        let registry_path = match &self.lookup_table_registry {
+            Some(p) => p.clone(),
+            None => Registry::default_path()?,
+        };
+        let mut registry = Registry::load(&registry_path)?;
+
+        let mut existing = None;
+        if let Some(key) = registry.tables.get(&registry_key) {
+            let key: Pubkey = key.parse().map_err(|_| {
+                Error::LookupTableRegistry(format!("invalid lookup table address {key:?}"))
+            })?;
+
+            existing = self
+                .lookup_table(&key)
+                .await?
+                .filter(|t| t.authority == Some(payer) && t.deactivation_slot == u64::MAX)
+                .map(|t| AddressLookupTableAccount {
+                    key,
+                    addresses: t.addresses,
+                });
+        }
+
+        let missing = |t: &AddressLookupTableAccount| {
+            wanted
+                .iter()
+                .filter(|a| !t.addresses.contains(a))
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        // Never send transactions to manage lookup tables during a dry run or
+        // while signing offline
+        if self.is_dry_run() || self.is_sign_only() {
+            return Ok(existing);
+        }
+
+        let mut table = match existing {
+            Some(t) if t.addresses.len() + missing(&t).len() <= LOOKUP_TABLE_MAX_ADDRESSES => t,
+            _ => {
+                let slot = self.chain.slot(CommitmentConfig::finalized()).await?;
+                let (ix, key) = create_lookup_table(payer, payer, slot);
+
+                self.send_message(&[ix], Some(&payer), signers, &[])
+                    .await?;
+
+                registry.tables.insert(registry_key, key.to_string());
+                registry.save(&registry_path)?;
+
+                AddressLookupTableAccount {
+                    key,
+                    addresses: vec![],
+                }
+            },
+        };
+
+        let missing = missing(&table);
+
+        if !missing.is_empty() {
+            for chunk in missing.chunks(MAX_EXTEND_ADDRESSES) {
+                self.send_message(
+                    &[extend_lookup_table(
+                        table.key,
+                        payer,
+                        Some(payer),
+                        chunk.to_vec(),
+                    )],
+                    Some(&payer),
+                    signers,
+                    &[],
+                )
+                .await?;
+            }
+
+            self.wait_for_next_slot().await?;
+            table.addresses.extend(missing);
+        }
+
+        Ok(Some(table))
+    }
+
+    /// Wait until the slot after the current one, so that addresses added to
+    /// a lookup table become usable
+    async fn wait_for_next_slot(&self) -> Result<()> {
+        let slot = self.chain.slot(CommitmentConfig::processed()).await?;
+
+        while self.chain.slot(CommitmentConfig::processed()).await? <= slot {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+
+        Ok(())
+    }
+
+    /// Sign and send a transaction, compiled using the lookup tables selected
+    /// by the client's lookup table mode, and wait for it to be confirmed.
+    /// Depending on the client's configuration the transaction may instead
+    /// be simulated or only signed.
+    ///
+    /// # Errors
+    /// This function fails if a signer is missing, the transaction fails or
+    /// expires, or its status cannot be determined.
+    pub async fn send_transaction(
+        &self,
+        instructions: &[Instruction],
+        payer: Option<&Pubkey>,
+        signers: &impl Signers,
+    ) -> Result<TransactionOutcome> {
+        check_signers(instructions, payer, signers)?;
+        let tables = self
+            .resolve_lookup_tables(instructions, payer, signers)
+            .await?;
+
+        self.send_message(instructions, payer, signers, &tables)
+            .await
+    }
+
+    /// Send a transaction compiled using the given address lookup tables,
+    /// without resolving any lookup tables automatically
+    ///
+    /// # Errors
+    /// This function fails if a signer is missing, the transaction fails or
+    /// expires, or its status cannot be determined.
+    pub async fn send_message(
+        &self,
+        instructions: &[Instruction],
+        payer: Option<&Pubkey>,
+        signers: &impl Signers,
+        tables: &[AddressLookupTableAccount],
+    ) -> Result<TransactionOutcome> {
+        check_signers(instructions, payer, signers)?;
+
+        let SendConfig {
+            dry_run,
+            blockhash_retries,
+            sign_only,
+            blockhash,
+            ..
+        } = self.send_config;
+
+        // A nonce advance must be the first instruction of a transaction
+        let mut budgeted = vec![];
+        let mut nonce_account = None;
+        if let Some(DurableNonce { account, authority }) = &self.nonce {
+            let authority = match authority {
+                Some(a) => a.try_pubkey()?,
+                None => *payer.ok_or_else(|| {
+                    Error::InvalidTransaction("a nonce authority or fee payer is required".into())
+                })?,
+            };
+
+            budgeted.push(system_instruction::advance_nonce_account(
+                account, &authority,
+            ));
+            nonce_account = Some(*account);
+        }
+
+        budgeted.extend(self.compute_budget_instructions(instructions).await?);
+        budgeted.extend_from_slice(instructions);
+
+        let nonce_authority = self.nonce.as_ref().and_then(|n| n.authority.as_deref());
+        let sign_with = |blockhash: &Hash| {
+            sign_message(
+                compile_message(&budgeted, payer, blockhash, tables)?,
+                signers,
+                nonce_authority,
+            )
+        };
+
+        // Transactions signed offline, simulated, or using a durable nonce are
+        // signed exactly once
+        if sign_only || dry_run || nonce_account.is_some() {
+            let blockhash = match (blockhash, nonce_account) {
+                (Some(h), _) => h,
+                (None, Some(a)) => self.nonce_data(&a).await?.blockhash(),
+                (None, None) => self.chain.latest_blockhash().await?.0,
+            };
+            let txn = sign_with(&blockhash)?;
+
+            if sign_only {
+                return SignOnly::new(&txn).map(TransactionOutcome::SignOnly);
+            }
+
+            return self.submit_transaction(&txn).await;
+        }
+
+        let spinner = self.progress.then(new_spinner);
+        let attempts = blockhash_retries.saturating_add(1);
+        let mut sigs = vec![];
+
+        for attempt in 1..=attempts {
+            let (blockhash, last_valid_block_height) = self.chain.latest_blockhash().await?;
+            let txn = sign_with(&blockhash)?;
+            sigs.push(first_signature(&txn)?);
+
+            let res = self
+                .broadcast(
+                    &sigs,
+                    &txn,
+                    Expiry::BlockHeight(last_valid_block_height),
+                    spinner.as_ref(),
+                    &format!(" (attempt {attempt}/{attempts})"),
+                )
+                .await;
+
+            let signature = match res {
+                Ok(Some(s)) => s,
+                Ok(None) => continue,
+                Err(e) => {
+                    if let Some(s) = spinner {
+                        s.finish_and_clear();
+                    }
+
+                    return Err(e);
+                },
+            };
+
+            if let Some(s) = spinner {
+                s.finish_and_clear();
+            }
+
+            return Ok(TransactionOutcome::Confirmed {
+                signature: signature.to_string(),
+            });
+        }
+
+        if let Some(s) = spinner {
+            s.finish_and_clear();
+        }
+
+        Err(Error::Expired { attempts })
+    }
+
+    /// Broadcast a transaction that has already been fully signed, without
+    /// re-signing it if its blockhash or durable nonce expires
+    ///
+    /// # Errors
+    /// This function fails if the transaction fails or expires, or its status
+    /// cannot be determined.
+    pub async fn submit_transaction(
+        &self,
+        txn: &VersionedTransaction,
+    ) -> Result<TransactionOutcome> {
+        if self.send_config.dry_run {
+            return self.dry_run(txn).await.map(TransactionOutcome::Simulated);
+        }
+
+        let blockhash = *txn.message.recent_blockhash();
+        let nonce = txn
+            .uses_durable_nonce()
+            .then(|| {
+                let ix = &txn.message.instructions()[0];
+                let idx = usize::from(*ix.accounts.first()?);
+                txn.message.static_account_keys().get(idx).copied()
+            })
+            .flatten();
+        let expiry = match nonce {
+            Some(account) => Expiry::Nonce { account, blockhash },
+            None => Expiry::Blockhash(blockhash),
+        };
+
+        let spinner = self.progress.then(new_spinner);
+        let res = self
+            .broadcast(&[first_signature(txn)?], txn, expiry, spinner.as_ref(), "")
+            .await;
+
+        if let Some(s) = spinner {
+            s.finish_and_clear();
+        }
+
+        match res? {
+            Some(signature) => Ok(TransactionOutcome::Confirmed {
+                signature: signature.to_string(),
+            }),
+            None => Err(Error::Expired { attempts: 1 }),
+        }
+    }
+
+    /// Send a signed transaction and wait for any of a set of signatures for
+    /// it to be confirmed.
+    ///
+    /// Returns `Ok(None)` if the transaction expired before being processed,
+    /// in which case it is safe to re-sign and retry.
+    async fn broadcast(
+        &self,
+        sigs: &[Signature],
+        txn: &VersionedTransaction,
+        expiry: Expiry,
+        spinner: Option<&ProgressBar>,
+        attempt: &str,
+    ) -> Result<Option<Signature>> {
+        let SendConfig {
+            skip_preflight,
+            rebroadcast_interval,
+            ..
+        } = self.send_config;
+        let sig = first_signature(txn)?;
+
+        if let Some(s) = spinner {
+            s.set_message(format!("Sending transaction {sig}{attempt}"));
+        }
+
+        let sent = self.chain.send(txn, skip_preflight).await;
+
+        if let Err(e) = sent {
+            match e.get_transaction_error() {
+                // The blockhash expired before the node saw the transaction,
+                // so it is safe to re-sign
+                Some(TransactionError::BlockhashNotFound) => return Ok(None),
+                Some(_) => {
+                    let logs = match preflight_logs(&e) {
+                        Some(l) => l,
+                        None => self.failure_logs(txn).await,
+                    };
+
+                    return Err(Error::TransactionFailed {
+                        signature: sig,
+                        source: Box::new(e),
+                        logs,
+                    });
+                },
+                // Any other error leaves the transaction in an unknown state,
+                // so it is rebroadcast below until it expires
+                None => (),
+            }
+        }
+
+        if let Some(s) = spinner {
+            s.set_message(format!("Confirming transaction {sig}{attempt}"));
+        }
+
+        match self
+            .await_confirmation(sigs, sig, txn, expiry, rebroadcast_interval)
+            .await
+        {
+            Err(Error::TransactionFailed {
+                signature, source, ..
+            }) => Err(Error::TransactionFailed {
+                signature,
+                source,
+                logs: self.failure_logs(txn).await,
+            }),
+            res => res,
+        }
+    }
+
+    /// Check whether a signed transaction can no longer be processed
+    async fn is_expired(&self, expiry: Expiry) -> Result<bool, ClientError> {
+        let chain = &self.chain;
+
+        match expiry {
+            Expiry::BlockHeight(last_valid_block_height) => {
+                Ok(chain.block_height().await? > last_valid_block_height)
+            },
+            Expiry::Blockhash(hash) => Ok(!chain.is_blockhash_valid(&hash).await?),
+            Expiry::Nonce { account, blockhash } => {
+                let account = chain.account(&account).await?;
+
+                // A closed or uninitialized nonce account can never be used
+                Ok(account
+                    .and_then(|a| nonce_utils::data_from_account(&a).ok())
+                    .is_none_or(|d| d.blockhash() != blockhash))
+            },
+        }
+    }
+
+    /// Check whether any of a set of signatures for the same transaction have
+    /// been processed
+    async fn signature_status(&self, sigs: &[Signature]) -> Result<SignatureStatus> {
+        let statuses = self
+            .chain
+            .signature_statuses(sigs)
+            .await
+            .map_err(|source| Error::StatusUnknown {
+                signature: sigs.last().copied().unwrap_or_default(),
+                source: Box::new(source),
+            })?;
+
+        let mut pending = false;
+        for (&signature, status) in sigs.iter().zip(statuses) {
+            let Some(status) = status else { continue };
+
+            if let Err(error) = status.status {
+                return Err(Error::TransactionFailed {
+                    signature,
+                    source: Box::new(error.into()),
+                    logs: vec![],
+                });
+            }
+
+            if status.satisfies_commitment(self.chain.commitment()) {
+                return Ok(SignatureStatus::Confirmed(signature));
+            }
+
+            pending = true;
+        }
+
+        Ok(if pending {
+            SignatureStatus::Pending
+        } else {
+            SignatureStatus::NotFound
+        })
+    }
+
+    /// Wait for any of a set of signatures for the same transaction to be
+    /// confirmed, periodically rebroadcasting `txn`, the latest signed copy
+    /// with signature `sig`, until it expires.
+    ///
+    /// Returns `Ok(None)` if the transaction expired before any signature was
+    /// processed, in which case it is safe to re-sign and retry.
+    async fn await_confirmation(
+        &self,
+        sigs: &[Signature],
+        sig: Signature,
+        txn: &VersionedTransaction,
+        expiry: Expiry,
+        rebroadcast_interval: Duration,
+    ) -> Result<Option<Signature>> {
+        /// Maximum time to tolerate consecutive RPC errors before giving up
+        const MAX_RPC_OUTAGE: Duration = Duration::from_secs(30);
+
+        let mut last_send = Instant::now();
+        let mut last_ok = Instant::now();
+
+        loop {
+            let status = self.signature_status(sigs).await;
+
+            match status {
+                Ok(SignatureStatus::Confirmed(sig)) => return Ok(Some(sig)),
+                Ok(SignatureStatus::Pending) => last_ok = Instant::now(),
+                Ok(SignatureStatus::NotFound) => {
+                    match self.is_expired(expiry).await {
+                        Ok(true) => {
+                            // Check one last time to avoid racing the final
+                            // valid block
+                            return match self.signature_status(sigs).await? {
+                                SignatureStatus::Confirmed(sig) => Ok(Some(sig)),
+                                SignatureStatus::Pending => continue,
+                                SignatureStatus::NotFound => Ok(None),
+                            };
+                        },
+                        Ok(false) => last_ok = Instant::now(),
+                        Err(source) if last_ok.elapsed() > MAX_RPC_OUTAGE => {
+                            return Err(Error::StatusUnknown {
+                                signature: sig,
+                                source: Box::new(source),
+                            });
+                        },
+                        Err(_) => (),
+                    }
+                },
+                Err(e @ Error::TransactionFailed { .. }) => return Err(e),
+                Err(e) if last_ok.elapsed() > MAX_RPC_OUTAGE => return Err(e),
+                Err(_) => (),
+            }
+
+            if last_send.elapsed() >= rebroadcast_interval {
+                // Errors here are expected (e.g. if the transaction was
+                // already processed) and the status check above is the
+                // source of truth, so they are ignored
+                self.chain.send(txn, true).await.ok();
+
+                last_send = Instant::now();
+            }
+
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Simulate a transaction, reporting its logs, compute usage, and changes
+    /// to its writable accounts
+    async fn dry_run(&self, txn: &VersionedTransaction) -> Result<Simulation> {
+        let keys = txn.message.static_account_keys();
+        let writable = keys
+            .iter()
+            .enumerate()
+            .filter_map(|(i, k)| txn.message.is_maybe_writable(i).then_some(*k))
+            .collect::<Vec<_>>();
+
+        let before = self.chain.multiple_accounts(&writable).await?;
+
+        let SimulationResult {
+            err,
+            logs,
+            accounts: after,
+            units_consumed,
+        } = self
+            .chain
+            .simulate(txn, SimulationConfig {
+                sig_verify: true,
+                accounts: writable.clone(),
+                ..SimulationConfig::default()
+            })
+            .await?;
+
+        if let Some(err) = err {
+            return Err(Error::TransactionFailed {
+                signature: txn.signatures.first().copied().unwrap_or_default(),
+                source: Box::new(err.into()),
+                logs,
+            });
+        }
+
+        let account_changes = writable
+            .iter()
+            .zip(before)
+            .zip(after)
+            .map(|((address, before), after)| AccountChange {
+                address: address.to_string(),
+                lamports_before: before.as_ref().map_or(0, |a| a.lamports),
+                lamports_after: after.as_ref().map_or(0, |a| a.lamports),
+                data_len_before: before.as_ref().map_or(0, |a| a.data.len()),
+                data_len_after: after.as_ref().map_or(0, |a| a.data.len()),
+                owner_before: before.map(|a| a.owner.to_string()),
+                owner_after: after.map(|a| a.owner.to_string()),
+            })
+            .collect();
+
+        Ok(Simulation {
+            units_consumed,
+            logs,
+            account_changes,
+        })
+    }
+
+    /// Re-simulate a failed transaction to retrieve its program logs
+    async fn failure_logs(&self, txn: &VersionedTransaction) -> Vec<String> {
+        self.chain
+            .simulate(txn, SimulationConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                ..SimulationConfig::default()
+            })
+            .await
+            .map(|r| r.logs)
+            .unwrap_or_default()
+    }
+}
+
+impl<C> std::ops::Deref for SolanaClient<C> {
+    type Target = C;
+
+    #[inline]
+    fn deref(&self) -> &C { &self.chain }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, future::Future};
+
+    use solana_address_lookup_table_program::state::{AddressLookupTable, LookupTableMeta};
+    use solana_sdk::{account::Account, instruction::InstructionError, signature::Keypair};
+
+    use super::*;
+    use crate::{
+        chain::{MockChain, MockSend},
+        compression,
+    };
+
+    fn block_on<F: Future>(f: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(f)
+    }
+
+    fn transfer(payer: &Keypair) -> Instruction {
+        system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1)
+    }
+
+    fn send(client: &SolanaClient<MockChain>, payer: &Keypair) -> Result<TransactionOutcome> {
+        block_on(client.send_transaction(&[transfer(payer)], Some(&payer.pubkey()), &[payer]))
+    }
+
+    #[test]
+    fn confirms_sent_transaction() {
+        let payer = Keypair::new();
+        let client = SolanaClient::new(MockChain::new(), false);
+
+        let TransactionOutcome::Confirmed { signature } = send(&client, &payer).unwrap() else {
+            panic!("Transaction was not confirmed");
+        };
+
+        let sent = client.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(signature, sent[0].signatures[0].to_string());
+        assert!(sent[0].verify_with_results().into_iter().all(|v| v));
+    }
+
+    #[test]
+    fn reports_failed_transaction() {
+        let payer = Keypair::new();
+        let client = SolanaClient::new(MockChain::new(), false);
+        let error = TransactionError::InstructionError(0, InstructionError::Custom(1));
+        client.push_send(MockSend::Fail(error.clone()));
+
+        let err = send(&client, &payer).unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::TransactionFailed { source, .. }
+                if source.get_transaction_error() == Some(error),
+        ));
+        assert_eq!(client.sent().len(), 1);
+    }
+
+    #[test]
+    fn reports_rejected_transaction() {
+        let payer = Keypair::new();
+        let client = SolanaClient::new(MockChain::new(), false);
+        client.push_send(MockSend::Reject(TransactionError::AccountNotFound));
+
+        let err = send(&client, &payer).unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::TransactionFailed { source, .. }
+                if source.get_transaction_error() == Some(TransactionError::AccountNotFound),
+        ));
+    }
+
+    #[test]
+    fn reports_missing_signers() {
+        let payer = Keypair::new();
+        let other = Keypair::new();
+        let client = SolanaClient::new(MockChain::new(), false);
+
+        let err = block_on(client.send_transaction(
+            &[transfer(&other)],
+            Some(&payer.pubkey()),
+            &[&payer],
+        ))
+        .unwrap_err();
+
+        assert!(matches!(err, Error::MissingSigners(k) if k == [other.pubkey()]));
+        assert!(client.sent().is_empty());
+    }
+
+    #[test]
+    fn resigns_expired_transaction() {
+        let payer = Keypair::new();
+        let client = SolanaClient::new(MockChain::new(), false);
+        client.push_send(MockSend::Drop);
+
+        let TransactionOutcome::Confirmed { signature } = send(&client, &payer).unwrap() else {
+            panic!("Transaction was not confirmed");
+        };
+
+        let sent = client.sent();
+        assert_eq!(sent.len(), 2);
+        assert_ne!(
+            sent[0].message.recent_blockhash(),
+            sent[1].message.recent_blockhash()
+        );
+        assert_eq!(signature, sent[1].signatures[0].to_string());
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let payer = Keypair::new();
+        let client = SolanaClient::new(MockChain::new(), false).send_config(SendConfig {
+            blockhash_retries: 0,
+            ..SendConfig::default()
+        });
+        client.push_send(MockSend::Drop);
+
+        let err = send(&client, &payer).unwrap_err();
+
+        assert!(matches!(err, Error::Expired { attempts: 1 }));
+    }
+
+    #[test]
+    fn dry_run_sends_nothing() {
+        let payer = Keypair::new();
+        let client = SolanaClient::new(MockChain::new(), false).send_config(SendConfig {
+            dry_run: true,
+            ..SendConfig::default()
+        });
+
+        let outcome = send(&client, &payer).unwrap();
+
+        assert!(matches!(outcome, TransactionOutcome::Simulated(_)));
+        assert!(client.sent().is_empty());
+    }
+
+    #[test]
+    fn reuses_auto_lookup_table() {
+        let payer = Keypair::new();
+        let registry = std::env::temp_dir().join(format!(
+            "arborist-lookup-tables-{}.json",
+            payer.pubkey()
+        ));
+        let client = SolanaClient::new(MockChain::new(), false)
+            .lookup_tables(LookupTables::Auto)
+            .lookup_table_registry(&registry);
+        let append = [compression::append(&Pubkey::new_unique(), &payer.pubkey(), [1; 32])];
+        let send_append = || {
+            block_on(client.send_transaction(&append, Some(&payer.pubkey()), &[&payer])).unwrap()
+        };
+        let table_key = |txn: &VersionedTransaction| {
+            txn.message.address_table_lookups().unwrap()[0].account_key
+        };
+
+        // The first transaction creates and extends the table before using it
+        send_append();
+        let sent = client.sent();
+        assert_eq!(sent.len(), 3);
+        let key = Registry::load(&registry).unwrap().tables.into_values().next().unwrap();
+        let key: Pubkey = key.parse().unwrap();
+        assert_eq!(table_key(&sent[2]), key);
+
+        // Stand in for the lookup table program, which the mock does not run
+        let data = AddressLookupTable {
+            meta: LookupTableMeta {
+                authority: Some(payer.pubkey()),
+                ..LookupTableMeta::default()
+            },
+            addresses: Cow::Owned(lut::lookup_addresses(&append).into_iter().collect()),
+        }
+        .serialize_for_tests()
+        .unwrap();
+        client.set_account(key, Account {
+            lamports: 1,
+            data,
+            owner: solana_address_lookup_table_program::ID,
+            executable: false,
+            rent_epoch: 0,
+        });
+
+        // Later transactions reuse the table without sending anything else
+        send_append();
+        std::fs::remove_file(&registry).unwrap();
+        let sent = client.sent();
+        assert_eq!(sent.len(), 4);
+        assert_eq!(table_key(&sent[3]), key);
+    }
+
+    #[test]
+    fn rejects_unsigned_transaction() {
+        let payer = Keypair::new();
+        let client = SolanaClient::new(MockChain::new(), false);
+        let message = compile_message(
+            &[transfer(&payer)],
+            Some(&payer.pubkey()),
+            &Hash::default(),
+            &[],
+        )
+        .unwrap();
+        let txn = VersionedTransaction {
+            signatures: vec![],
+            message,
+        };
+
+        let err = block_on(client.submit_transaction(&txn)).unwrap_err();
+
+        assert!(matches!(err, Error::InvalidTransaction(_)));
+        assert!(client.sent().is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+//! Typed builders for account compression program instructions
+//!
+//! These instructions operate on a concurrent Merkle tree directly, without
+//! going through Bubblegum.  Every modifying instruction must be signed by the
+//! tree's authority, and instructions that reference an existing leaf take the
+//! proof nodes not stored in the tree's canopy.
+
+use anchor_lang::InstructionData;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use spl_account_compression::instruction as ix;
+
+/// A 32-byte tree node, such as a leaf hash or root
+pub type Node = [u8; 32];
+
+fn proof_metas(proof: &[Node]) -> impl Iterator<Item = AccountMeta> + '_ {
+    proof
+        .iter()
+        .map(|&n| AccountMeta::new_readonly(Pubkey::new_from_array(n), false))
+}
+
+/// Initialize an empty Merkle tree in an account created with
+/// [`create_tree_account`](crate::instructions::create_tree_account)
+#[must_use]
+pub fn init_empty_merkle_tree(
+    merkle_tree: &Pubkey,
+    authority: &Pubkey,
+    max_depth: u32,
+    max_buffer_size: u32,
+) -> Instruction {
+    Instruction {
+        program_id: spl_account_compression::ID,
+        accounts: vec![
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+        ],
+        data: ix::InitEmptyMerkleTree {
+            max_depth,
+            max_buffer_size,
+        }
+        .data(),
+    }
+}
+
+/// Append a leaf to the rightmost position of a tree
+#[must_use]
+pub fn append(merkle_tree: &Pubkey, authority: &Pubkey, leaf: Node) -> Instruction {
+    Instruction {
+        program_id: spl_account_compression::ID,
+        accounts: vec![
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+        ],
+        data: ix::Append { leaf }.data(),
+    }
+}
+
+/// Replace the leaf at an index, proving its previous value against `root`
+#[must_use]
+pub fn replace_leaf(
+    merkle_tree: &Pubkey,
+    authority: &Pubkey,
+    root: Node,
+    previous_leaf: Node,
+    new_leaf: Node,
+    index: u32,
+    proof: &[Node],
+) -> Instruction {
+    Instruction {
+        program_id: spl_account_compression::ID,
+        accounts: [
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+        ]
+        .into_iter()
+        .chain(proof_metas(proof))
+        .collect(),
+        data: ix::ReplaceLeaf {
+            root,
+            previous_leaf,
+            new_leaf,
+            index,
+        }
+        .data(),
+    }
+}
+
+/// Verify that a leaf is present at an index, failing if the proof is invalid
+#[must_use]
+pub fn verify_leaf(
+    merkle_tree: &Pubkey,
+    root: Node,
+    leaf: Node,
+    index: u32,
+    proof: &[Node],
+) -> Instruction {
+    Instruction {
+        program_id: spl_account_compression::ID,
+        accounts: [AccountMeta::new_readonly(*merkle_tree, false)]
+            .into_iter()
+            .chain(proof_metas(proof))
+            .collect(),
+        data: ix::VerifyLeaf { root, leaf, index }.data(),
+    }
+}
+
+/// Transfer write access to a tree to a new authority
+#[must_use]
+pub fn transfer_authority(
+    merkle_tree: &Pubkey,
+    authority: &Pubkey,
+    new_authority: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: spl_account_compression::ID,
+        accounts: vec![
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: ix::TransferAuthority { new_authority }.data(),
+    }
+}
+
+/// Close a tree with no leaves, sending its rent to `recipient`
+#[must_use]
+pub fn close_empty_tree(
+    merkle_tree: &Pubkey,
+    authority: &Pubkey,
+    recipient: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: spl_account_compression::ID,
+        accounts: vec![
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*recipient, false),
+        ],
+        data: ix::CloseEmptyTree {}.data(),
+    }
+}
@@ -0,0 +1,655 @@
+//! Identification of Bubblegum and account compression instructions in
+//! processed transactions, and of the events they log through the noop
+//! program
+
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use mpl_bubblegum::{instruction as bubblegum, state::leaf_schema::LeafSchema};
+use solana_sdk::pubkey::Pubkey;
+use spl_account_compression::{
+    events::{AccountCompressionEvent, ApplicationDataEvent},
+    instruction as compression,
+};
+
+use crate::{chain::ConfirmedTransaction, instructions::SET_DECOMPRESSIBLE_STATE};
+
+/// A Bubblegum or account compression instruction, identified by its Anchor
+/// discriminator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum InstructionKind {
+    // Bubblegum
+    CreateTree,
+    SetTreeDelegate,
+    SetDecompressibleState,
+    MintV1,
+    MintToCollectionV1,
+    VerifyCreator,
+    UnverifyCreator,
+    VerifyCollection,
+    UnverifyCollection,
+    SetAndVerifyCollection,
+    Transfer,
+    Delegate,
+    Burn,
+    Redeem,
+    CancelRedeem,
+    DecompressV1,
+    Compress,
+
+    // Account compression
+    InitEmptyMerkleTree,
+    ReplaceLeaf,
+    TransferAuthority,
+    VerifyLeaf,
+    Append,
+    InsertOrAppend,
+    CloseEmptyTree,
+}
+
+const BUBBLEGUM: &[(InstructionKind, [u8; 8])] = &[
+    (
+        InstructionKind::CreateTree,
+        bubblegum::CreateTree::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::SetTreeDelegate,
+        bubblegum::SetTreeDelegate::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::SetDecompressibleState,
+        SET_DECOMPRESSIBLE_STATE,
+    ),
+    (InstructionKind::MintV1, bubblegum::MintV1::DISCRIMINATOR),
+    (
+        InstructionKind::MintToCollectionV1,
+        bubblegum::MintToCollectionV1::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::VerifyCreator,
+        bubblegum::VerifyCreator::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::UnverifyCreator,
+        bubblegum::UnverifyCreator::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::VerifyCollection,
+        bubblegum::VerifyCollection::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::UnverifyCollection,
+        bubblegum::UnverifyCollection::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::SetAndVerifyCollection,
+        bubblegum::SetAndVerifyCollection::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::Transfer,
+        bubblegum::Transfer::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::Delegate,
+        bubblegum::Delegate::DISCRIMINATOR,
+    ),
+    (InstructionKind::Burn, bubblegum::Burn::DISCRIMINATOR),
+    (InstructionKind::Redeem, bubblegum::Redeem::DISCRIMINATOR),
+    (
+        InstructionKind::CancelRedeem,
+        bubblegum::CancelRedeem::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::DecompressV1,
+        bubblegum::DecompressV1::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::Compress,
+        bubblegum::Compress::DISCRIMINATOR,
+    ),
+];
+
+const COMPRESSION: &[(InstructionKind, [u8; 8])] = &[
+    (
+        InstructionKind::InitEmptyMerkleTree,
+        compression::InitEmptyMerkleTree::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::ReplaceLeaf,
+        compression::ReplaceLeaf::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::TransferAuthority,
+        compression::TransferAuthority::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::VerifyLeaf,
+        compression::VerifyLeaf::DISCRIMINATOR,
+    ),
+    (InstructionKind::Append, compression::Append::DISCRIMINATOR),
+    (
+        InstructionKind::InsertOrAppend,
+        compression::InsertOrAppend::DISCRIMINATOR,
+    ),
+    (
+        InstructionKind::CloseEmptyTree,
+        compression::CloseEmptyTree::DISCRIMINATOR,
+    ),
+];
+
+impl InstructionKind {
+    /// Identify an instruction from its program ID and data, returning `None`
+    /// if it is not a known Bubblegum or account compression instruction
+    #[must_use]
+    pub fn decode(program_id: &Pubkey, data: &[u8]) -> Option<Self> {
+        let known = if *program_id == mpl_bubblegum::ID {
+            BUBBLEGUM
+        } else if *program_id == spl_account_compression::ID {
+            COMPRESSION
+        } else {
+            return None;
+        };
+        let disc = data.get(..8)?;
+
+        known
+            .iter()
+            .find_map(|(kind, d)| (d.as_slice() == disc).then_some(*kind))
+    }
+
+    /// The instruction's name, as logged by the program
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::CreateTree => "CreateTree",
+            Self::SetTreeDelegate => "SetTreeDelegate",
+            Self::SetDecompressibleState => "SetDecompressibleState",
+            Self::MintV1 => "MintV1",
+            Self::MintToCollectionV1 => "MintToCollectionV1",
+            Self::VerifyCreator => "VerifyCreator",
+            Self::UnverifyCreator => "UnverifyCreator",
+            Self::VerifyCollection => "VerifyCollection",
+            Self::UnverifyCollection => "UnverifyCollection",
+            Self::SetAndVerifyCollection => "SetAndVerifyCollection",
+            Self::Transfer => "Transfer",
+            Self::Delegate => "Delegate",
+            Self::Burn => "Burn",
+            Self::Redeem => "Redeem",
+            Self::CancelRedeem => "CancelRedeem",
+            Self::DecompressV1 => "DecompressV1",
+            Self::Compress => "Compress",
+            Self::InitEmptyMerkleTree => "InitEmptyMerkleTree",
+            Self::ReplaceLeaf => "ReplaceLeaf",
+            Self::TransferAuthority => "TransferAuthority",
+            Self::VerifyLeaf => "VerifyLeaf",
+            Self::Append => "Append",
+            Self::InsertOrAppend => "InsertOrAppend",
+            Self::CloseEmptyTree => "CloseEmptyTree",
+        }
+    }
+
+    /// Whether this is a Bubblegum instruction, rather than an account
+    /// compression instruction
+    #[must_use]
+    pub fn is_bubblegum(self) -> bool { BUBBLEGUM.iter().any(|&(k, _)| k == self) }
+
+    /// Position of the Merkle tree in the instruction's accounts
+    fn tree_account(self) -> Option<usize> {
+        match self {
+            Self::InitEmptyMerkleTree
+            | Self::ReplaceLeaf
+            | Self::TransferAuthority
+            | Self::VerifyLeaf
+            | Self::Append
+            | Self::InsertOrAppend
+            | Self::CloseEmptyTree => Some(0),
+            Self::CreateTree => Some(1),
+            Self::CancelRedeem => Some(2),
+            Self::SetTreeDelegate
+            | Self::MintV1
+            | Self::MintToCollectionV1
+            | Self::VerifyCreator
+            | Self::UnverifyCreator
+            | Self::VerifyCollection
+            | Self::UnverifyCollection
+            | Self::SetAndVerifyCollection
+            | Self::Burn
+            | Self::Redeem
+            | Self::Compress => Some(3),
+            Self::Transfer | Self::Delegate => Some(4),
+            Self::SetDecompressibleState | Self::DecompressV1 => None,
+        }
+    }
+
+    /// Whether the instruction's arguments start with the root, data hash,
+    /// creator hash, nonce and index of an existing Bubblegum leaf, and its
+    /// second account is the leaf's owner
+    fn targets_leaf(self) -> bool {
+        matches!(
+            self,
+            Self::VerifyCreator
+                | Self::UnverifyCreator
+                | Self::VerifyCollection
+                | Self::UnverifyCollection
+                | Self::SetAndVerifyCollection
+                | Self::Transfer
+                | Self::Delegate
+                | Self::Burn
+                | Self::Redeem
+        )
+    }
+
+    /// Offset of the leaf index in the instruction's data, if it has one
+    fn leaf_index_offset(self) -> Option<usize> {
+        match self {
+            _ if self.targets_leaf() => Some(8 + 32 * 3 + 8),
+            Self::ReplaceLeaf => Some(8 + 32 * 3),
+            Self::VerifyLeaf => Some(8 + 32 * 2),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for InstructionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { f.write_str(self.name()) }
+}
+
+/// A change to a concurrent Merkle tree, as logged by the account compression
+/// program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangeLogEvent {
+    /// The tree that was changed
+    pub merkle_tree: Pubkey,
+    /// The sequence number of the change
+    pub sequence_number: u64,
+    /// The index of the changed leaf
+    pub leaf_index: u32,
+    /// The root of the tree after the change
+    pub root: [u8; 32],
+}
+
+/// The new contents of a leaf, as logged by Bubblegum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeafSchemaEvent {
+    /// The ID of the asset stored in the leaf
+    pub asset_id: Pubkey,
+    /// The owner of the asset
+    pub owner: Pubkey,
+    /// The delegate of the asset
+    pub delegate: Pubkey,
+    /// The nonce the asset was minted with
+    pub nonce: u64,
+    /// The hash of the asset's metadata
+    pub data_hash: [u8; 32],
+    /// The hash of the asset's creators
+    pub creator_hash: [u8; 32],
+    /// The hash stored in the leaf
+    pub leaf_hash: [u8; 32],
+}
+
+/// An event logged through the noop program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoopEvent {
+    /// A change to a tree
+    ChangeLog(ChangeLogEvent),
+    /// The new contents of a Bubblegum leaf
+    LeafSchema(LeafSchemaEvent),
+}
+
+impl NoopEvent {
+    /// Decode the data of a noop instruction, returning `None` if it is not
+    /// an account compression change log or Bubblegum leaf schema event
+    #[must_use]
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        match AccountCompressionEvent::try_from_slice(data).ok()? {
+            AccountCompressionEvent::ChangeLog(spl_account_compression::ChangeLogEvent::V1(e)) => {
+                Some(Self::ChangeLog(ChangeLogEvent {
+                    merkle_tree: e.id,
+                    sequence_number: e.seq,
+                    leaf_index: e.index,
+                    root: e.path.last()?.node,
+                }))
+            },
+            AccountCompressionEvent::ApplicationData(ApplicationDataEvent::V1(d)) => {
+                let mpl_bubblegum::state::leaf_schema::LeafSchemaEvent {
+                    schema:
+                        LeafSchema::V1 {
+                            id,
+                            owner,
+                            delegate,
+                            nonce,
+                            data_hash,
+                            creator_hash,
+                        },
+                    leaf_hash,
+                    ..
+                } = AnchorDeserialize::try_from_slice(&d.application_data).ok()?;
+
+                Some(Self::LeafSchema(LeafSchemaEvent {
+                    asset_id: id,
+                    owner,
+                    delegate,
+                    nonce,
+                    data_hash,
+                    creator_hash,
+                    leaf_hash,
+                }))
+            },
+        }
+    }
+}
+
+/// A Bubblegum or account compression instruction in a processed transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    /// The kind of instruction
+    pub kind: InstructionKind,
+    /// The index of the top-level instruction this instruction is, or was
+    /// invoked by
+    pub index: usize,
+    /// Whether the instruction was invoked via CPI
+    pub inner: bool,
+    /// The tree the instruction operates on
+    pub merkle_tree: Option<Pubkey>,
+    /// The owner of the leaf before the instruction, for Bubblegum
+    /// instructions that modify an existing leaf
+    pub leaf_owner: Option<Pubkey>,
+    /// The nonce of the leaf, for Bubblegum instructions that modify an
+    /// existing leaf
+    pub nonce: Option<u64>,
+    /// The index of the leaf given in the instruction's arguments
+    pub leaf_index: Option<u32>,
+    /// Events logged by the instruction and the account compression
+    /// instructions it invoked
+    pub events: Vec<NoopEvent>,
+}
+
+impl DecodedInstruction {
+    /// The first change log event logged by the instruction
+    #[must_use]
+    pub fn change_log(&self) -> Option<&ChangeLogEvent> {
+        self.events.iter().find_map(|e| match e {
+            NoopEvent::ChangeLog(c) => Some(c),
+            NoopEvent::LeafSchema(_) => None,
+        })
+    }
+
+    /// The first leaf schema event logged by the instruction
+    #[must_use]
+    pub fn leaf_schema(&self) -> Option<&LeafSchemaEvent> {
+        self.events.iter().find_map(|e| match e {
+            NoopEvent::LeafSchema(l) => Some(l),
+            NoopEvent::ChangeLog(_) => None,
+        })
+    }
+}
+
+/// Decode every Bubblegum and account compression instruction in a
+/// transaction, including those invoked via CPI, in execution order
+///
+/// Noop events are attached to the most recent Bubblegum instruction invoked
+/// by the same top-level instruction, or to the most recent account
+/// compression instruction if there is none, so that the change log written
+/// by a Bubblegum instruction's CPI is reported alongside its leaf.
+#[must_use]
+pub fn decode_instructions(txn: &ConfirmedTransaction) -> Vec<DecodedInstruction> {
+    let message = &txn.transaction.message;
+    let keys = message
+        .static_account_keys()
+        .iter()
+        .chain(&txn.loaded_addresses.writable)
+        .chain(&txn.loaded_addresses.readonly)
+        .collect::<Vec<_>>();
+    let mut decoded: Vec<DecodedInstruction> = vec![];
+
+    for (index, ix) in message.instructions().iter().enumerate() {
+        let inner = txn
+            .inner_instructions
+            .iter()
+            .filter(|n| usize::from(n.index) == index)
+            .flat_map(|n| &n.instructions);
+        let start = decoded.len();
+        let mut bubblegum = None;
+
+        for (i, ix) in std::iter::once(ix).chain(inner).enumerate() {
+            let Some(&program) = keys.get(usize::from(ix.program_id_index)) else {
+                continue;
+            };
+            let account = |i: usize| {
+                ix.accounts
+                    .get(i)
+                    .and_then(|&a| keys.get(usize::from(a)))
+                    .map(|&&k| k)
+            };
+
+            if *program == spl_noop::ID {
+                let target = bubblegum
+                    .or_else(|| decoded.len().checked_sub(1))
+                    .filter(|&t| t >= start);
+                if let (Some(target), Some(event)) = (target, NoopEvent::decode(&ix.data)) {
+                    decoded[target].events.push(event);
+                }
+
+                continue;
+            }
+
+            let Some(kind) = InstructionKind::decode(program, &ix.data) else {
+                continue;
+            };
+            let targets_leaf = kind.targets_leaf();
+
+            if kind.is_bubblegum() {
+                bubblegum = Some(decoded.len());
+            }
+
+            decoded.push(DecodedInstruction {
+                kind,
+                index,
+                inner: i > 0,
+                merkle_tree: kind.tree_account().and_then(account),
+                leaf_owner: targets_leaf.then(|| account(1)).flatten(),
+                nonce: targets_leaf
+                    .then(|| ix.data.get(8 + 32 * 3..8 + 32 * 3 + 8))
+                    .flatten()
+                    .and_then(|n| n.try_into().ok())
+                    .map(u64::from_le_bytes),
+                leaf_index: kind
+                    .leaf_index_offset()
+                    .and_then(|o| ix.data.get(o..o + 4))
+                    .and_then(|i| i.try_into().ok())
+                    .map(u32::from_le_bytes),
+                events: vec![],
+            });
+        }
+    }
+
+    decoded
+}
+
+/// Identify every Bubblegum and account compression instruction in a
+/// transaction, including those invoked via CPI, in execution order
+#[must_use]
+pub fn instruction_kinds(txn: &ConfirmedTransaction) -> Vec<InstructionKind> {
+    decode_instructions(txn)
+        .into_iter()
+        .map(|i| i.kind)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::AnchorSerialize;
+    use solana_sdk::{
+        instruction::CompiledInstruction,
+        message::{v0::LoadedAddresses, Message, VersionedMessage},
+        signature::Signature,
+        transaction::VersionedTransaction,
+    };
+    use spl_account_compression::{events::ApplicationDataEventV1, state::PathNode};
+
+    use super::*;
+    use crate::{
+        chain::InnerInstructions,
+        compression::{append, replace_leaf},
+        hash::asset_id,
+        instructions::{set_decompressible_state, transfer, Leaf, LeafAuthority},
+    };
+
+    #[test]
+    fn decodes_instructions() {
+        let tree = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let ix = append(&tree, &authority, [0; 32]);
+        assert_eq!(
+            InstructionKind::decode(&ix.program_id, &ix.data),
+            Some(InstructionKind::Append)
+        );
+
+        let ix = set_decompressible_state(
+            &tree,
+            &authority,
+            crate::instructions::DecompressibleState::Enabled,
+        );
+        let kind = InstructionKind::decode(&ix.program_id, &ix.data).unwrap();
+        assert_eq!(kind, InstructionKind::SetDecompressibleState);
+        assert!(kind.is_bubblegum());
+
+        // Discriminators are only matched against their own program
+        assert_eq!(InstructionKind::decode(&spl_noop::ID, &ix.data), None);
+        assert_eq!(InstructionKind::decode(&ix.program_id, &ix.data[..4]), None);
+    }
+
+    /// Build a transfer of leaf 7 and the inner instructions it invokes
+    fn transfer_transaction(
+        tree: Pubkey,
+        owner: Pubkey,
+        new_owner: Pubkey,
+    ) -> (ConfirmedTransaction, LeafSchema) {
+        let leaf = Leaf {
+            root: [1; 32],
+            data_hash: [2; 32],
+            creator_hash: [3; 32],
+            nonce: 7,
+            index: 7,
+        };
+
+        let ix = transfer(
+            &tree,
+            &owner,
+            &owner,
+            LeafAuthority::Owner,
+            &new_owner,
+            &leaf,
+            &[],
+        );
+        let message = Message::new(&[ix], Some(&owner));
+        let keys = &message.account_keys;
+        let position =
+            |key: &Pubkey| u8::try_from(keys.iter().position(|k| k == key).unwrap()).unwrap();
+
+        let schema = LeafSchema::new_v0(
+            asset_id(&tree, 7),
+            new_owner,
+            new_owner,
+            7,
+            [2; 32],
+            [3; 32],
+        );
+        let leaf_event = AccountCompressionEvent::ApplicationData(ApplicationDataEvent::V1(
+            ApplicationDataEventV1 {
+                application_data: schema.to_event().try_to_vec().unwrap(),
+            },
+        ));
+        let change_log =
+            AccountCompressionEvent::ChangeLog(spl_account_compression::ChangeLogEvent::new(
+                tree,
+                vec![PathNode::new([4; 32], 15), PathNode::new([5; 32], 1)],
+                12,
+                7,
+            ));
+        let replace = replace_leaf(
+            &tree,
+            &Pubkey::new_unique(),
+            [1; 32],
+            [0; 32],
+            [6; 32],
+            7,
+            &[],
+        );
+        let noop = |data: Vec<u8>| CompiledInstruction {
+            program_id_index: position(&spl_noop::ID),
+            accounts: vec![],
+            data,
+        };
+
+        let txn = ConfirmedTransaction {
+            slot: 1,
+            block_time: None,
+            transaction: VersionedTransaction {
+                signatures: vec![Signature::default()],
+                message: VersionedMessage::Legacy(message.clone()),
+            },
+            err: None,
+            logs: vec![],
+            inner_instructions: vec![InnerInstructions {
+                index: 0,
+                instructions: vec![
+                    noop(leaf_event.try_to_vec().unwrap()),
+                    CompiledInstruction {
+                        program_id_index: position(&spl_account_compression::ID),
+                        accounts: vec![position(&tree)],
+                        data: replace.data,
+                    },
+                    noop(change_log.try_to_vec().unwrap()),
+                ],
+            }],
+            loaded_addresses: LoadedAddresses::default(),
+        };
+
+        (txn, schema)
+    }
+
+    #[test]
+    fn decodes_transfer_events() {
+        let tree = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let new_owner = Pubkey::new_unique();
+        let (txn, schema) = transfer_transaction(tree, owner, new_owner);
+
+        let decoded = decode_instructions(&txn);
+        assert_eq!(instruction_kinds(&txn), [
+            InstructionKind::Transfer,
+            InstructionKind::ReplaceLeaf
+        ]);
+
+        let transfer = &decoded[0];
+        assert!(!transfer.inner);
+        assert_eq!(transfer.merkle_tree, Some(tree));
+        assert_eq!(transfer.leaf_owner, Some(owner));
+        assert_eq!(transfer.nonce, Some(7));
+        assert_eq!(transfer.leaf_index, Some(7));
+        assert_eq!(transfer.events.len(), 2);
+        assert_eq!(
+            transfer
+                .leaf_schema()
+                .map(|l| (l.asset_id, l.owner, l.leaf_hash)),
+            Some((asset_id(&tree, 7), new_owner, schema.to_node()))
+        );
+        assert_eq!(
+            transfer.change_log(),
+            Some(&ChangeLogEvent {
+                merkle_tree: tree,
+                sequence_number: 12,
+                leaf_index: 7,
+                root: [5; 32],
+            })
+        );
+
+        let replace = &decoded[1];
+        assert!(replace.inner);
+        assert_eq!(replace.merkle_tree, Some(tree));
+        assert_eq!(replace.leaf_index, Some(7));
+        assert!(replace.events.is_empty());
+
+        assert_eq!(NoopEvent::decode(&[9; 4]), None);
+    }
+}
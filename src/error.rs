@@ -0,0 +1,136 @@
+//! Error type for the `arborist` library
+
+use std::fmt;
+
+use solana_client::client_error::ClientError;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, signer::SignerError};
+
+/// Result type returned by the `arborist` library
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error produced by the `arborist` library
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// A tree depth not supported by the account compression program
+    #[error("Invalid tree depth {depth} - closest valid value(s) are {closest}")]
+    InvalidDepth {
+        /// The requested depth
+        depth: u8,
+        /// The closest supported depths
+        closest: String,
+    },
+
+    /// A buffer size not supported for a tree depth
+    #[error("Invalid buffer size {buffer_size} - valid size(s) are {valid}")]
+    InvalidBufferSize {
+        /// The requested buffer size
+        buffer_size: u16,
+        /// The buffer sizes supported for the requested depth
+        valid: String,
+    },
+
+    /// An account that was expected to exist does not
+    #[error("Account {0} does not exist")]
+    AccountNotFound(Pubkey),
+
+    /// Account data could not be decoded as the expected type
+    #[error("Invalid {kind} account data: {reason}")]
+    InvalidAccountData {
+        /// The type of account being decoded
+        kind: &'static str,
+        /// Why decoding failed
+        reason: String,
+    },
+
+    /// Asset metadata could not be hashed
+    #[error("Invalid asset metadata: {0}")]
+    InvalidMetadata(String),
+
+    /// A snapshot file could not be read
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+
+    /// A transaction could not be decoded
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+
+    /// An RPC request failed
+    #[error("RPC request failed")]
+    Rpc(#[source] Box<ClientError>),
+
+    /// A transaction was rejected by its preflight simulation, or processed
+    /// by the cluster but failed
+    #[error("Transaction {signature} failed{}", ProgramLogs(logs))]
+    TransactionFailed {
+        /// The signature of the failed transaction
+        signature: Signature,
+        /// The error the transaction failed with
+        #[source]
+        source: Box<ClientError>,
+        /// Program logs produced by the transaction, if available
+        logs: Vec<String>,
+    },
+
+    /// No signed copy of a transaction was processed before its blockhash
+    /// expired, so it can never be processed
+    #[error("Transaction was not processed before its blockhash expired ({attempts} attempt(s))")]
+    Expired {
+        /// The number of times the transaction was signed and sent
+        attempts: usize,
+    },
+
+    /// The status of a transaction could not be determined, and it may or may
+    /// not have been processed
+    #[error("Unable to determine the status of transaction {signature}")]
+    StatusUnknown {
+        /// The signature of the transaction
+        signature: Signature,
+        /// The error encountered while checking the transaction's status
+        #[source]
+        source: Box<ClientError>,
+    },
+
+    /// Accounts required to sign a transaction have no signer
+    #[error("Missing signer(s) for {}", join(.0))]
+    MissingSigners(Vec<Pubkey>),
+
+    /// A signer failed to sign a transaction
+    #[error("Error signing transaction")]
+    Signer(#[from] SignerError),
+
+    /// The registry of automatically created lookup tables could not be read
+    /// or written
+    #[error("Invalid lookup table registry: {0}")]
+    LookupTableRegistry(String),
+}
+
+/// Program logs appended to the message of a failed transaction
+struct ProgramLogs<'a>(&'a [String]);
+
+impl fmt::Display for ProgramLogs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+
+        f.write_str("\nProgram logs:")?;
+
+        for log in self.0 {
+            write!(f, "\n  {log}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn join(keys: &[Pubkey]) -> String {
+    keys.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl From<ClientError> for Error {
+    fn from(err: ClientError) -> Self { Self::Rpc(Box::new(err)) }
+}
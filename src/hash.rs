@@ -0,0 +1,49 @@
+//! Hashing used to identify compressed assets and construct their leaves
+
+use mpl_bubblegum::state::{leaf_schema::LeafSchema, metaplex_adapter::MetadataArgs};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{Creator, Error, Result};
+
+/// Compute the data hash of an asset's metadata
+///
+/// # Errors
+/// This function fails if the metadata cannot be serialized.
+pub fn hash_metadata(metadata: &MetadataArgs) -> Result<[u8; 32]> {
+    mpl_bubblegum::hash_metadata(metadata).map_err(|e| Error::InvalidMetadata(e.to_string()))
+}
+
+/// Compute the creator hash of an asset's creators
+#[must_use]
+pub fn hash_creators(creators: &[Creator]) -> [u8; 32] {
+    // This never actually fails, it just returns a Result for consistency
+    mpl_bubblegum::hash_creators(creators).unwrap_or_else(|_| unreachable!())
+}
+
+/// Derive the ID of the asset minted into a tree with the given nonce
+#[inline]
+#[must_use]
+pub fn asset_id(merkle_tree: &Pubkey, nonce: u64) -> Pubkey {
+    mpl_bubblegum::utils::get_asset_id(merkle_tree, nonce)
+}
+
+/// Compute the hash of the leaf storing a compressed asset
+#[must_use]
+pub fn leaf_hash(
+    merkle_tree: &Pubkey,
+    owner: Pubkey,
+    delegate: Pubkey,
+    nonce: u64,
+    data_hash: [u8; 32],
+    creator_hash: [u8; 32],
+) -> [u8; 32] {
+    LeafSchema::new_v0(
+        asset_id(merkle_tree, nonce),
+        owner,
+        delegate,
+        nonce,
+        data_hash,
+        creator_hash,
+    )
+    .to_node()
+}
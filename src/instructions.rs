@@ -0,0 +1,309 @@
+//! Typed builders for Bubblegum program instructions
+//!
+//! Each builder returns a single [`Instruction`] with its accounts in the
+//! order expected by the program.  Builders that modify an existing leaf take
+//! a [`Leaf`] describing its current state and the proof nodes required to
+//! verify it against the tree's root.
+
+use anchor_lang::InstructionData;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_instruction, system_program,
+};
+
+use crate::{tree::tree_config_address, MetadataArgs};
+
+/// Discriminator of `set_decompressible_state`, which was added to Bubblegum
+/// after the version this crate depends on
+pub(crate) const SET_DECOMPRESSIBLE_STATE: [u8; 8] = [82, 104, 152, 6, 149, 111, 100, 13];
+
+/// The current state of a leaf, as required to modify it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Leaf {
+    /// The root of the tree the proof was generated against
+    pub root: [u8; 32],
+    /// The data hash of the asset's metadata
+    pub data_hash: [u8; 32],
+    /// The hash of the asset's creators
+    pub creator_hash: [u8; 32],
+    /// The nonce the asset was minted with
+    pub nonce: u64,
+    /// The index of the leaf in the tree
+    pub index: u32,
+}
+
+/// The party signing for a change to a leaf
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeafAuthority {
+    /// The leaf owner is signing
+    Owner,
+    /// The leaf delegate is signing
+    Delegate,
+}
+
+/// Whether compressed assets in a tree may be decompressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecompressibleState {
+    /// Assets may be decompressed
+    Enabled,
+    /// Assets may not be decompressed
+    Disabled,
+}
+
+fn proof_metas(proof: &[Pubkey]) -> impl Iterator<Item = AccountMeta> + '_ {
+    proof.iter().map(|&p| AccountMeta::new_readonly(p, false))
+}
+
+/// Create the account for a new Merkle tree, owned by the account compression
+/// program
+///
+/// `lamports` should be the rent exemption for `space`, which can be computed
+/// with [`tree_size`](crate::tree::tree_size).
+#[must_use]
+pub fn create_tree_account(
+    payer: &Pubkey,
+    merkle_tree: &Pubkey,
+    lamports: u64,
+    space: u64,
+) -> Instruction {
+    system_instruction::create_account(
+        payer,
+        merkle_tree,
+        lamports,
+        space,
+        &spl_account_compression::ID,
+    )
+}
+
+/// Initialize a Merkle tree account and its Bubblegum tree configuration
+#[must_use]
+pub fn create_tree(
+    merkle_tree: &Pubkey,
+    payer: &Pubkey,
+    tree_creator: &Pubkey,
+    max_depth: u32,
+    max_buffer_size: u32,
+    public: Option<bool>,
+) -> Instruction {
+    Instruction {
+        program_id: mpl_bubblegum::ID,
+        accounts: vec![
+            AccountMeta::new(tree_config_address(merkle_tree), false),
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*tree_creator, true),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+            AccountMeta::new_readonly(spl_account_compression::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: mpl_bubblegum::instruction::CreateTree {
+            max_depth,
+            max_buffer_size,
+            public,
+        }
+        .data(),
+    }
+}
+
+/// Change the delegate allowed to mint into a tree
+#[must_use]
+pub fn set_tree_delegate(
+    merkle_tree: &Pubkey,
+    tree_creator: &Pubkey,
+    new_tree_delegate: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: mpl_bubblegum::ID,
+        accounts: vec![
+            AccountMeta::new(tree_config_address(merkle_tree), false),
+            AccountMeta::new_readonly(*tree_creator, true),
+            AccountMeta::new_readonly(*new_tree_delegate, false),
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: mpl_bubblegum::instruction::SetTreeDelegate {}.data(),
+    }
+}
+
+/// Enable or disable decompression of the assets in a tree
+#[must_use]
+pub fn set_decompressible_state(
+    merkle_tree: &Pubkey,
+    tree_creator: &Pubkey,
+    state: DecompressibleState,
+) -> Instruction {
+    let mut data = SET_DECOMPRESSIBLE_STATE.to_vec();
+    data.push(match state {
+        DecompressibleState::Enabled => 0,
+        DecompressibleState::Disabled => 1,
+    });
+
+    Instruction {
+        program_id: mpl_bubblegum::ID,
+        accounts: vec![
+            AccountMeta::new(tree_config_address(merkle_tree), false),
+            AccountMeta::new_readonly(*tree_creator, true),
+        ],
+        data,
+    }
+}
+
+/// Mint a compressed asset into a tree
+#[must_use]
+pub fn mint_v1(
+    merkle_tree: &Pubkey,
+    leaf_owner: &Pubkey,
+    leaf_delegate: &Pubkey,
+    payer: &Pubkey,
+    tree_delegate: &Pubkey,
+    metadata: MetadataArgs,
+) -> Instruction {
+    Instruction {
+        program_id: mpl_bubblegum::ID,
+        accounts: vec![
+            AccountMeta::new(tree_config_address(merkle_tree), false),
+            AccountMeta::new_readonly(*leaf_owner, false),
+            AccountMeta::new_readonly(*leaf_delegate, false),
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(*payer, true),
+            AccountMeta::new_readonly(*tree_delegate, true),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+            AccountMeta::new_readonly(spl_account_compression::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: mpl_bubblegum::instruction::MintV1 { message: metadata }.data(),
+    }
+}
+
+/// Transfer a compressed asset to a new owner
+#[must_use]
+pub fn transfer(
+    merkle_tree: &Pubkey,
+    leaf_owner: &Pubkey,
+    leaf_delegate: &Pubkey,
+    signer: LeafAuthority,
+    new_leaf_owner: &Pubkey,
+    leaf: &Leaf,
+    proof: &[Pubkey],
+) -> Instruction {
+    let Leaf {
+        root,
+        data_hash,
+        creator_hash,
+        nonce,
+        index,
+    } = *leaf;
+
+    Instruction {
+        program_id: mpl_bubblegum::ID,
+        accounts: [
+            AccountMeta::new_readonly(tree_config_address(merkle_tree), false),
+            AccountMeta::new_readonly(*leaf_owner, signer == LeafAuthority::Owner),
+            AccountMeta::new_readonly(*leaf_delegate, signer == LeafAuthority::Delegate),
+            AccountMeta::new_readonly(*new_leaf_owner, false),
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+            AccountMeta::new_readonly(spl_account_compression::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+        .into_iter()
+        .chain(proof_metas(proof))
+        .collect(),
+        data: mpl_bubblegum::instruction::Transfer {
+            root,
+            data_hash,
+            creator_hash,
+            nonce,
+            index,
+        }
+        .data(),
+    }
+}
+
+/// Burn a compressed asset
+#[must_use]
+pub fn burn(
+    merkle_tree: &Pubkey,
+    leaf_owner: &Pubkey,
+    leaf_delegate: &Pubkey,
+    signer: LeafAuthority,
+    leaf: &Leaf,
+    proof: &[Pubkey],
+) -> Instruction {
+    let Leaf {
+        root,
+        data_hash,
+        creator_hash,
+        nonce,
+        index,
+    } = *leaf;
+
+    Instruction {
+        program_id: mpl_bubblegum::ID,
+        accounts: [
+            AccountMeta::new_readonly(tree_config_address(merkle_tree), false),
+            AccountMeta::new_readonly(*leaf_owner, signer == LeafAuthority::Owner),
+            AccountMeta::new_readonly(*leaf_delegate, signer == LeafAuthority::Delegate),
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+            AccountMeta::new_readonly(spl_account_compression::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+        .into_iter()
+        .chain(proof_metas(proof))
+        .collect(),
+        data: mpl_bubblegum::instruction::Burn {
+            root,
+            data_hash,
+            creator_hash,
+            nonce,
+            index,
+        }
+        .data(),
+    }
+}
+
+/// Set the delegate of a compressed asset, signed by its owner
+#[must_use]
+pub fn delegate(
+    merkle_tree: &Pubkey,
+    leaf_owner: &Pubkey,
+    previous_leaf_delegate: &Pubkey,
+    new_leaf_delegate: &Pubkey,
+    leaf: &Leaf,
+    proof: &[Pubkey],
+) -> Instruction {
+    let Leaf {
+        root,
+        data_hash,
+        creator_hash,
+        nonce,
+        index,
+    } = *leaf;
+
+    Instruction {
+        program_id: mpl_bubblegum::ID,
+        accounts: [
+            AccountMeta::new_readonly(tree_config_address(merkle_tree), false),
+            AccountMeta::new_readonly(*leaf_owner, true),
+            AccountMeta::new_readonly(*previous_leaf_delegate, false),
+            AccountMeta::new_readonly(*new_leaf_delegate, false),
+            AccountMeta::new(*merkle_tree, false),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+            AccountMeta::new_readonly(spl_account_compression::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+        .into_iter()
+        .chain(proof_metas(proof))
+        .collect(),
+        data: mpl_bubblegum::instruction::Delegate {
+            root,
+            data_hash,
+            creator_hash,
+            nonce,
+            index,
+        }
+        .data(),
+    }
+}
@@ -0,0 +1,37 @@
+//! Building blocks for managing Metaplex Bubblegum compressed NFTs and the
+//! concurrent Merkle trees that store them
+//!
+//! This crate provides typed instruction builders for the Bubblegum program,
+//! decoders for tree accounts, the hashing used to construct leaves, and an
+//! async client for landing transactions built on the
+//! [`ChainClient`](chain::ChainClient) abstraction over the cluster.  The
+//! `arborist` command-line utility is built on top of it.
+
+#![deny(
+    clippy::disallowed_methods,
+    clippy::suspicious,
+    clippy::style,
+    clippy::clone_on_ref_ptr,
+    missing_debug_implementations,
+    missing_copy_implementations
+)]
+#![warn(clippy::pedantic, missing_docs)]
+
+pub mod accounts;
+#[cfg(feature = "test-utils")]
+pub mod bank;
+pub mod chain;
+pub mod client;
+pub mod compression;
+pub mod decode;
+pub mod error;
+pub mod hash;
+pub mod instructions;
+pub mod lut;
+pub mod snapshot;
+pub mod tree;
+
+pub use error::{Error, Result};
+pub use mpl_bubblegum::state::metaplex_adapter::{
+    Collection, Creator, MetadataArgs, TokenProgramVersion, TokenStandard, UseMethod, Uses,
+};
@@ -0,0 +1,190 @@
+//! Address lookup tables holding the accounts used by Bubblegum instructions
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use solana_address_lookup_table_program::state::AddressLookupTable;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, system_program};
+
+use crate::{tree::tree_config_address, Error, Result};
+
+/// Maximum number of addresses added by a single extend instruction, chosen to
+/// keep transactions under the packet size limit
+pub const MAX_EXTEND_ADDRESSES: usize = 20;
+
+/// The accounts shared by every Bubblegum instruction for a given tree
+#[must_use]
+pub fn tree_addresses(merkle_tree: Pubkey) -> [Pubkey; 6] {
+    [
+        merkle_tree,
+        tree_config_address(&merkle_tree),
+        mpl_bubblegum::ID,
+        spl_account_compression::ID,
+        spl_noop::ID,
+        system_program::ID,
+    ]
+}
+
+/// Collect the accounts worth storing in a lookup table for a set of
+/// instructions, i.e. the tree accounts and programs used by any Bubblegum or
+/// account compression instruction
+#[must_use]
+pub fn lookup_addresses(instructions: &[Instruction]) -> BTreeSet<Pubkey> {
+    /// Number of leading accounts searched for the Merkle tree, which always
+    /// precedes any proof accounts
+    const MAX_TREE_INDEX: usize = 8;
+
+    let mut addrs = BTreeSet::new();
+
+    for ix in instructions {
+        // The Merkle tree is the first account of every account compression
+        // instruction
+        if ix.program_id == spl_account_compression::ID {
+            if let Some(tree) = ix.accounts.first() {
+                addrs.extend([tree.pubkey, spl_account_compression::ID, spl_noop::ID]);
+            }
+
+            continue;
+        }
+
+        if ix.program_id != mpl_bubblegum::ID {
+            continue;
+        }
+
+        // The tree configuration PDA is the first account of every Bubblegum
+        // instruction, and is derived from the tree address
+        let Some(config) = ix.accounts.first() else {
+            continue;
+        };
+
+        let tree = ix
+            .accounts
+            .iter()
+            .skip(1)
+            .take(MAX_TREE_INDEX)
+            .find(|a| tree_config_address(&a.pubkey) == config.pubkey);
+
+        if let Some(tree) = tree {
+            addrs.extend(tree_addresses(tree.pubkey));
+        }
+    }
+
+    addrs
+}
+
+/// Decoded state of an address lookup table
+#[derive(Debug, Clone)]
+pub struct LookupTable {
+    /// Authority permitted to extend, deactivate, and close the table, or
+    /// `None` if it is frozen
+    pub authority: Option<Pubkey>,
+    /// Slot the table was deactivated in, or [`u64::MAX`] if it is active
+    pub deactivation_slot: u64,
+    /// Slot the table was last extended in
+    pub last_extended_slot: u64,
+    /// Addresses stored in the table
+    pub addresses: Vec<Pubkey>,
+}
+
+impl LookupTable {
+    /// Decode the data of a lookup table account
+    ///
+    /// # Errors
+    /// This function fails if the data is not a valid lookup table.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let table =
+            AddressLookupTable::deserialize(data).map_err(|e| Error::InvalidAccountData {
+                kind: "lookup table",
+                reason: e.to_string(),
+            })?;
+
+        Ok(Self {
+            authority: table.meta.authority,
+            deactivation_slot: table.meta.deactivation_slot,
+            last_extended_slot: table.meta.last_extended_slot,
+            addresses: table.addresses.into_owned(),
+        })
+    }
+}
+
+/// Record of the lookup tables created automatically by the client, keyed by
+/// cluster and authority
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Registry {
+    #[serde(default)]
+    pub tables: BTreeMap<String, String>,
+}
+
+impl Registry {
+    /// `arborist/lookup-tables.json` in the user's configuration directory
+    pub fn default_path() -> Result<PathBuf> {
+        dirs_next::config_dir()
+            .map(|d| d.join("arborist").join("lookup-tables.json"))
+            .ok_or_else(|| {
+                Error::LookupTableRegistry("unable to locate user configuration directory".into())
+            })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let err = |e: &dyn std::fmt::Display| {
+            Error::LookupTableRegistry(format!("error reading {}: {e}", path.display()))
+        };
+
+        match std::fs::read(path) {
+            Ok(b) => serde_json::from_slice(&b).map_err(|e| err(&e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(err(&e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let err = |e: &dyn std::fmt::Display| {
+            Error::LookupTableRegistry(format!("error writing {}: {e}", path.display()))
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| err(&e))?;
+        }
+
+        std::fs::write(
+            path,
+            serde_json::to_vec_pretty(self).map_err(|e| err(&e))?,
+        )
+        .map_err(|e| err(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::system_instruction;
+
+    use super::*;
+    use crate::{compression, instructions};
+
+    #[test]
+    fn collects_tree_and_program_addresses() {
+        let bubblegum_tree = Pubkey::new_unique();
+        let compression_tree = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let addrs = lookup_addresses(&[
+            system_instruction::transfer(&authority, &Pubkey::new_unique(), 1),
+            instructions::set_tree_delegate(&bubblegum_tree, &authority, &Pubkey::new_unique()),
+            compression::append(&compression_tree, &authority, [1; 32]),
+        ]);
+
+        let mut expected = BTreeSet::from(tree_addresses(bubblegum_tree));
+        expected.insert(compression_tree);
+        assert_eq!(addrs, expected);
+
+        assert!(lookup_addresses(&[system_instruction::transfer(
+            &authority,
+            &Pubkey::new_unique(),
+            1,
+        )])
+        .is_empty());
+    }
+}
@@ -0,0 +1,499 @@
+//! Portable point-in-time snapshots of compressed trees
+//!
+//! A snapshot file consists of the magic bytes [`MAGIC`], the format version
+//! as a little-endian `u16`, the length of the payload as a little-endian
+//! `u64`, the CBOR-encoded [`TreeSnapshot`] and finally the SHA-256 hash of
+//! everything preceding it.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{hash, keccak, pubkey::Pubkey};
+use spl_account_compression::state::CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1;
+
+use crate::{
+    accounts::{
+        decode_merkle_tree, read, tree_body_size, TreeConfig, CHANGE_LOG_SIZE, TREE_PREFIX_SIZE,
+    },
+    hash::{asset_id, leaf_hash},
+    tree::NODE_SIZE,
+    Error, Result,
+};
+
+/// Magic bytes identifying a snapshot file
+pub const MAGIC: [u8; 8] = *b"ARBSNAP\0";
+
+/// The current version of the snapshot file format
+pub const VERSION: u16 = 1;
+
+/// Size of the magic bytes, version and payload length preceding the payload
+const PREAMBLE_SIZE: usize = MAGIC.len() + 2 + 8;
+
+/// Size of the trailing checksum
+const CHECKSUM_SIZE: usize = 32;
+
+/// A single entry in the change log buffer of a concurrent Merkle tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeLog {
+    /// The root of the tree after the change
+    pub root: [u8; 32],
+    /// The nodes along the path from the changed leaf to the root
+    pub path: Vec<[u8; 32]>,
+    /// The index of the changed leaf
+    pub index: u32,
+}
+
+/// The proof of the rightmost leaf appended to a concurrent Merkle tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RightmostProof {
+    /// The sibling nodes along the path from the leaf to the root
+    pub proof: Vec<[u8; 32]>,
+    /// The rightmost leaf
+    pub leaf: [u8; 32],
+    /// The index of the next leaf to be appended
+    pub index: u32,
+}
+
+/// The full decoded state of a concurrent Merkle tree account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleTreeState {
+    /// The depth of the tree
+    pub max_depth: u32,
+    /// The number of concurrent changes the tree can buffer
+    pub max_buffer_size: u32,
+    /// The authority allowed to modify the tree
+    pub authority: Pubkey,
+    /// The slot the tree was initialized in
+    pub creation_slot: u64,
+    /// The number of changes applied to the tree
+    pub sequence_number: u64,
+    /// The index of the most recent entry in the change log buffer
+    pub active_index: u64,
+    /// The number of valid entries in the change log buffer
+    pub buffer_size: u64,
+    /// The change log buffer
+    pub change_logs: Vec<ChangeLog>,
+    /// The proof of the rightmost leaf
+    pub rightmost_proof: RightmostProof,
+    /// The cached upper levels of the tree, in the order stored on-chain
+    pub canopy: Vec<[u8; 32]>,
+}
+
+fn read_node(data: &[u8], offset: usize) -> Result<[u8; 32]> { read(data, offset) }
+
+fn read_path(data: &[u8], offset: usize, depth: usize) -> Result<Vec<[u8; 32]>> {
+    (0..depth)
+        .map(|i| read_node(data, offset + i * NODE_SIZE))
+        .collect()
+}
+
+impl MerkleTreeState {
+    /// Decode the full state of a concurrent Merkle tree account, including
+    /// its change log buffer and canopy
+    ///
+    /// # Errors
+    /// This function fails if the data is not an initialized Merkle tree.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let tree = decode_merkle_tree(data)?;
+        let depth = tree.max_depth as usize;
+        let buffer = tree.max_buffer_size as usize;
+        let invalid = |reason: &str| Error::InvalidAccountData {
+            kind: "Merkle tree",
+            reason: reason.into(),
+        };
+        let body = data
+            .get(CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1..)
+            .ok_or_else(|| invalid("Account data is too short"))?;
+        let path = NODE_SIZE * depth;
+
+        let active_index = u64::from_le_bytes(read(body, 8)?);
+        let buffer_size = u64::from_le_bytes(read(body, 16)?);
+
+        let change_logs = (0..buffer)
+            .map(|i| {
+                let offset = TREE_PREFIX_SIZE + i * (CHANGE_LOG_SIZE + path);
+
+                Ok(ChangeLog {
+                    root: read_node(body, offset)?,
+                    path: read_path(body, offset + NODE_SIZE, depth)?,
+                    index: u32::from_le_bytes(read(body, offset + NODE_SIZE + path)?),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let rightmost = TREE_PREFIX_SIZE + buffer * (CHANGE_LOG_SIZE + path);
+        let rightmost_proof = RightmostProof {
+            proof: read_path(body, rightmost, depth)?,
+            leaf: read_node(body, rightmost + path)?,
+            index: tree.rightmost_index,
+        };
+
+        let canopy_start =
+            tree_body_size(depth, buffer).ok_or_else(|| invalid("Tree dimensions out of range"))?;
+        let canopy = (0..(2 << tree.canopy_depth) - 2)
+            .map(|i| read_node(body, canopy_start + i * NODE_SIZE))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            max_depth: tree.max_depth,
+            max_buffer_size: tree.max_buffer_size,
+            authority: tree.authority,
+            creation_slot: tree.creation_slot,
+            sequence_number: tree.sequence_number,
+            active_index,
+            buffer_size,
+            change_logs,
+            rightmost_proof,
+            canopy,
+        })
+    }
+
+    /// The current root of the tree, if the active change log exists
+    #[must_use]
+    pub fn root(&self) -> Option<[u8; 32]> {
+        usize::try_from(self.active_index)
+            .ok()
+            .and_then(|i| self.change_logs.get(i))
+            .map(|c| c.root)
+    }
+
+    /// The number of cached upper levels stored after the tree
+    #[must_use]
+    pub fn canopy_depth(&self) -> u32 { (self.canopy.len() + 2).trailing_zeros() - 1 }
+
+    fn check(&self) -> Result<()> {
+        let depth = self.max_depth as usize;
+        let canopy_nodes = self.canopy.len();
+
+        if self.change_logs.len() != self.max_buffer_size as usize {
+            return Err(Error::InvalidSnapshot(
+                "Change log buffer does not match the tree's buffer size".into(),
+            ));
+        }
+        if self.change_logs.iter().any(|c| c.path.len() != depth)
+            || self.rightmost_proof.proof.len() != depth
+        {
+            return Err(Error::InvalidSnapshot(
+                "Path length does not match the tree's depth".into(),
+            ));
+        }
+        if self.root().is_none() {
+            return Err(Error::InvalidSnapshot("Active index out of range".into()));
+        }
+        if (canopy_nodes + 2).count_ones() != 1 {
+            return Err(Error::InvalidSnapshot("Invalid canopy size".into()));
+        }
+
+        Ok(())
+    }
+}
+
+/// The Bubblegum configuration of a tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeConfigState {
+    /// The creator of the tree
+    pub tree_creator: Pubkey,
+    /// The delegate allowed to mint into the tree
+    pub tree_delegate: Pubkey,
+    /// The total number of assets that can be minted into the tree
+    pub total_mint_capacity: u64,
+    /// The number of assets minted into the tree
+    pub num_minted: u64,
+    /// Whether anyone can mint into the tree
+    pub is_public: bool,
+}
+
+impl From<TreeConfig> for TreeConfigState {
+    fn from(config: TreeConfig) -> Self {
+        let TreeConfig {
+            tree_creator,
+            tree_delegate,
+            total_mint_capacity,
+            num_minted,
+            is_public,
+        } = config;
+
+        Self {
+            tree_creator,
+            tree_delegate,
+            total_mint_capacity,
+            num_minted,
+            is_public,
+        }
+    }
+}
+
+/// A compressed asset stored in a tree leaf
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafState {
+    /// The index of the leaf
+    pub index: u32,
+    /// The nonce the asset was minted with
+    pub nonce: u64,
+    /// The ID of the asset
+    pub asset_id: Pubkey,
+    /// The owner of the asset
+    pub owner: Pubkey,
+    /// The delegate of the asset
+    pub delegate: Pubkey,
+    /// The hash of the asset's metadata
+    pub data_hash: [u8; 32],
+    /// The hash of the asset's creators
+    pub creator_hash: [u8; 32],
+    /// The hash stored in the leaf
+    pub leaf_hash: [u8; 32],
+}
+
+impl LeafState {
+    /// Construct the state of a Bubblegum leaf, deriving its asset ID and
+    /// leaf hash
+    #[must_use]
+    pub fn new(
+        merkle_tree: &Pubkey,
+        index: u32,
+        nonce: u64,
+        owner: Pubkey,
+        delegate: Pubkey,
+        data_hash: [u8; 32],
+        creator_hash: [u8; 32],
+    ) -> Self {
+        Self {
+            index,
+            nonce,
+            asset_id: asset_id(merkle_tree, nonce),
+            owner,
+            delegate,
+            data_hash,
+            creator_hash,
+            leaf_hash: leaf_hash(merkle_tree, owner, delegate, nonce, data_hash, creator_hash),
+        }
+    }
+}
+
+/// A point-in-time snapshot of a compressed tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeSnapshot {
+    /// The address of the tree
+    pub merkle_tree: Pubkey,
+    /// The most recent slot when the tree account was read
+    pub slot: u64,
+    /// When the snapshot was taken, as a Unix timestamp
+    pub exported_at: i64,
+    /// The state of the tree account
+    pub tree: MerkleTreeState,
+    /// The Bubblegum configuration of the tree, if it is managed by Bubblegum
+    pub tree_config: Option<TreeConfigState>,
+    /// Every non-empty leaf in the tree, if they were exported
+    pub leaves: Option<Vec<LeafState>>,
+}
+
+impl TreeSnapshot {
+    /// Encode the snapshot as a versioned, checksummed snapshot file
+    ///
+    /// # Errors
+    /// This function fails if the snapshot cannot be serialized.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut payload = vec![];
+        ciborium::ser::into_writer(self, &mut payload)
+            .map_err(|e| Error::InvalidSnapshot(e.to_string()))?;
+
+        let mut bytes = Vec::with_capacity(PREAMBLE_SIZE + payload.len() + CHECKSUM_SIZE);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        let checksum = hash::hash(&bytes);
+        bytes.extend_from_slice(checksum.as_ref());
+
+        Ok(bytes)
+    }
+
+    /// Decode a snapshot file, verifying its checksum
+    ///
+    /// # Errors
+    /// This function fails if the file is not a snapshot, was written by an
+    /// unsupported version of the format, is corrupt, or contains a tree
+    /// whose fields are inconsistent with each other.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = |reason: &str| Error::InvalidSnapshot(reason.into());
+
+        if bytes.get(..MAGIC.len()) != Some(&MAGIC[..]) {
+            return Err(invalid("Not an arborist snapshot file"));
+        }
+
+        let version = bytes
+            .get(MAGIC.len()..MAGIC.len() + 2)
+            .and_then(|v| v.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or_else(|| invalid("File is truncated"))?;
+        if version != VERSION {
+            return Err(Error::InvalidSnapshot(format!(
+                "Unsupported snapshot version {version} (expected {VERSION})"
+            )));
+        }
+
+        let len = bytes
+            .get(MAGIC.len() + 2..PREAMBLE_SIZE)
+            .and_then(|l| l.try_into().ok())
+            .map(u64::from_le_bytes)
+            .and_then(|l| usize::try_from(l).ok())
+            .ok_or_else(|| invalid("File is truncated"))?;
+        let end = PREAMBLE_SIZE
+            .checked_add(len)
+            .ok_or_else(|| invalid("Invalid payload length"))?;
+        if bytes.len() != end + CHECKSUM_SIZE {
+            return Err(invalid("Payload length does not match file size"));
+        }
+
+        if hash::hash(&bytes[..end]).as_ref() != &bytes[end..] {
+            return Err(invalid("Checksum mismatch - the file is corrupt"));
+        }
+
+        let snapshot: Self = ciborium::de::from_reader(&bytes[PREAMBLE_SIZE..end])
+            .map_err(|e| Error::InvalidSnapshot(e.to_string()))?;
+        snapshot.tree.check()?;
+
+        Ok(snapshot)
+    }
+
+    /// Recompute the root of the tree from the exported leaves, for
+    /// comparison against the root stored in the tree account
+    ///
+    /// Returns `None` if the snapshot does not contain leaves.
+    #[must_use]
+    pub fn leaf_root(&self) -> Option<[u8; 32]> {
+        let leaves = self.leaves.as_ref()?;
+
+        let mut level = leaves
+            .iter()
+            .map(|l| (u64::from(l.index), l.leaf_hash))
+            .collect::<BTreeMap<_, _>>();
+        let mut empty = [0; 32];
+
+        for _ in 0..self.tree.max_depth {
+            let mut parents = BTreeMap::new();
+
+            for (&index, node) in &level {
+                let sibling = level.get(&(index ^ 1)).unwrap_or(&empty);
+                let (left, right) = if index % 2 == 0 {
+                    (node, sibling)
+                } else {
+                    (sibling, node)
+                };
+
+                parents
+                    .entry(index / 2)
+                    .or_insert_with(|| keccak::hashv(&[left, right]).to_bytes());
+            }
+
+            empty = keccak::hashv(&[&empty, &empty]).to_bytes();
+            level = parents;
+        }
+
+        Some(level.get(&0).copied().unwrap_or(empty))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{empty_root, tree_size};
+
+    fn tree_data(authority: &Pubkey) -> Vec<u8> {
+        let size = tree_size(3, 8, 2).unwrap();
+        let mut data = vec![0_u8; usize::try_from(size).unwrap()];
+
+        data[0] = 1;
+        data[2..6].copy_from_slice(&8_u32.to_le_bytes());
+        data[6..10].copy_from_slice(&3_u32.to_le_bytes());
+        data[10..42].copy_from_slice(authority.as_ref());
+
+        let body = CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1;
+        data[body..body + 8].copy_from_slice(&2_u64.to_le_bytes());
+        data[body + 8..body + 16].copy_from_slice(&1_u64.to_le_bytes());
+        data[body + 16..body + 24].copy_from_slice(&2_u64.to_le_bytes());
+        let root = body + TREE_PREFIX_SIZE + CHANGE_LOG_SIZE + 32 * 3;
+        data[root..root + 32].copy_from_slice(&[7; 32]);
+        let len = data.len();
+        data[len - 32..].copy_from_slice(&[9; 32]);
+
+        data
+    }
+
+    #[test]
+    fn roundtrips_snapshot() {
+        let merkle_tree = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let tree = MerkleTreeState::decode(&tree_data(&authority)).unwrap();
+
+        assert_eq!(tree.authority, authority);
+        assert_eq!(tree.active_index, 1);
+        assert_eq!(tree.buffer_size, 2);
+        assert_eq!(tree.change_logs.len(), 8);
+        assert_eq!(tree.root(), Some([7; 32]));
+        assert_eq!(tree.canopy.len(), 6);
+        assert_eq!(tree.canopy[5], [9; 32]);
+        assert_eq!(tree.canopy_depth(), 2);
+
+        let owner = Pubkey::new_unique();
+        let leaf = LeafState::new(&merkle_tree, 0, 0, owner, owner, [1; 32], [2; 32]);
+        let snapshot = TreeSnapshot {
+            merkle_tree,
+            slot: 100,
+            exported_at: 1_700_000_000,
+            tree,
+            tree_config: None,
+            leaves: Some(vec![leaf]),
+        };
+
+        let mut bytes = snapshot.to_bytes().unwrap();
+        assert_eq!(TreeSnapshot::from_bytes(&bytes).unwrap(), snapshot);
+
+        let last = bytes.len() - 40;
+        bytes[last] ^= 1;
+        assert!(TreeSnapshot::from_bytes(&bytes).is_err());
+        assert!(TreeSnapshot::from_bytes(&bytes[..20]).is_err());
+        assert!(TreeSnapshot::from_bytes(b"not a snapshot").is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_tree() {
+        let data = tree_data(&Pubkey::new_unique());
+        let header = CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1;
+
+        assert!(MerkleTreeState::decode(&[]).is_err());
+        assert!(MerkleTreeState::decode(&data[..header - 1]).is_err());
+        assert!(MerkleTreeState::decode(&data[..header]).is_err());
+        assert!(MerkleTreeState::decode(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn computes_leaf_root() {
+        let merkle_tree = Pubkey::new_unique();
+        let tree = MerkleTreeState::decode(&tree_data(&Pubkey::new_unique())).unwrap();
+        let mut snapshot = TreeSnapshot {
+            merkle_tree,
+            slot: 0,
+            exported_at: 0,
+            tree,
+            tree_config: None,
+            leaves: None,
+        };
+        assert_eq!(snapshot.leaf_root(), None);
+
+        snapshot.leaves = Some(vec![]);
+        assert_eq!(snapshot.leaf_root(), Some(empty_root(3)));
+
+        let owner = Pubkey::new_unique();
+        let leaf = LeafState::new(&merkle_tree, 5, 5, owner, owner, [1; 32], [2; 32]);
+        snapshot.leaves = Some(vec![leaf]);
+
+        let empty = [0; 32];
+        let level1 = keccak::hashv(&[&empty, &empty]).to_bytes();
+        let level2 = keccak::hashv(&[&level1, &level1]).to_bytes();
+        let parent = keccak::hashv(&[&empty, &leaf.leaf_hash]).to_bytes();
+        let parent = keccak::hashv(&[&parent, &level1]).to_bytes();
+        let root = keccak::hashv(&[&level2, &parent]).to_bytes();
+        assert_eq!(snapshot.leaf_root(), Some(root));
+    }
+}
@@ -0,0 +1,131 @@
+//! Sizing and address derivation for concurrent Merkle trees
+
+use std::{collections::BTreeMap, mem::size_of};
+
+use solana_sdk::{keccak, pubkey::Pubkey};
+use spl_account_compression::{state::CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1, ConcurrentMerkleTree};
+
+use crate::{Error, Result};
+
+/// Size of a single tree node, in bytes
+pub const NODE_SIZE: usize = 32;
+
+/// Size of the canopy (the cached upper levels of a tree), in bytes
+#[inline]
+#[must_use]
+pub const fn canopy_size(canopy_depth: u8) -> u64 {
+    ((2 << canopy_depth) - 2) * NODE_SIZE as u64
+}
+
+/// Compute the size of the account needed to store a concurrent Merkle tree
+/// with the given parameters
+///
+/// # Errors
+/// This function fails if the depth and buffer size are not a combination
+/// supported by the account compression program.
+pub fn tree_size(depth: u8, buffer_size: u16, canopy_depth: u8) -> Result<u64> {
+    // TODO: if someone exports a function for doing this nicely i'm all ears
+
+    // copied from spl-account-compression because it's mired in a labyrinth of
+    // private fields and missing default impls
+    #[allow(clippy::non_std_lazy_statics)]
+    fn merkle_tree_get_size(depth: u8, buffer_size: u16) -> Result<usize> {
+        macro_rules! tree_size {
+            ($depth:expr, $buf:expr) => {
+                (
+                    $depth,
+                    $buf,
+                    size_of::<ConcurrentMerkleTree<$depth, $buf>>(),
+                )
+            };
+        }
+
+        lazy_static::lazy_static! {
+            static ref SIZES: BTreeMap<u8, BTreeMap<u16, usize>> = {
+                let mut map: BTreeMap<u8, BTreeMap<u16, usize>> = BTreeMap::new();
+
+                for (depth, buf, size) in [
+                    tree_size!(3, 8),
+                    tree_size!(5, 8),
+                    tree_size!(14, 64),
+                    tree_size!(14, 256),
+                    tree_size!(14, 1024),
+                    tree_size!(14, 2048),
+                    tree_size!(15, 64),
+                    tree_size!(16, 64),
+                    tree_size!(17, 64),
+                    tree_size!(18, 64),
+                    tree_size!(19, 64),
+                    tree_size!(20, 64),
+                    tree_size!(20, 256),
+                    tree_size!(20, 1024),
+                    tree_size!(20, 2048),
+                    tree_size!(24, 64),
+                    tree_size!(24, 256),
+                    tree_size!(24, 512),
+                    tree_size!(24, 1024),
+                    tree_size!(24, 2048),
+                    tree_size!(26, 512),
+                    tree_size!(26, 1024),
+                    tree_size!(26, 2048),
+                    tree_size!(30, 512),
+                    tree_size!(30, 1024),
+                    tree_size!(30, 2048),
+                ] {
+                    map.entry(depth).or_default().insert(buf, size);
+                }
+
+                map
+            };
+        }
+
+        let Some(map) = SIZES.get(&depth) else {
+            use std::ops::Bound;
+
+            let closest = SIZES
+                .range((Bound::Unbounded, Bound::Included(depth)))
+                .next()
+                .into_iter()
+                .chain(
+                    SIZES
+                        .range((Bound::Included(depth), Bound::Unbounded))
+                        .next(),
+                )
+                .map(|(k, _)| k.to_string())
+                .collect::<Vec<_>>()
+                .join(" and ");
+
+            return Err(Error::InvalidDepth { depth, closest });
+        };
+
+        let Some(&size) = map.get(&buffer_size) else {
+            let valid = map
+                .keys()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            return Err(Error::InvalidBufferSize { buffer_size, valid });
+        };
+
+        Ok(size)
+    }
+
+    let size = CONCURRENT_MERKLE_TREE_HEADER_SIZE_V1 + merkle_tree_get_size(depth, buffer_size)?;
+
+    Ok(u64::try_from(size).unwrap_or_else(|_| unreachable!()) + canopy_size(canopy_depth))
+}
+
+/// Derive the address of the Bubblegum tree configuration PDA for a Merkle
+/// tree
+#[inline]
+#[must_use]
+pub fn tree_config_address(merkle_tree: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[merkle_tree.as_ref()], &mpl_bubblegum::ID).0
+}
+
+/// Compute the root of a tree of the given depth containing only empty leaves
+#[must_use]
+pub fn empty_root(depth: u32) -> [u8; 32] {
+    (0..depth).fold([0; 32], |node, _| keccak::hashv(&[&node, &node]).to_bytes())
+}
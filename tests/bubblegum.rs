@@ -0,0 +1,365 @@
+//! End-to-end tests of the Bubblegum instruction builders
+
+mod common;
+
+use arborist::{
+    accounts::{decode_merkle_tree, decode_tree_config},
+    client::SolanaClient,
+    hash::{hash_creators, hash_metadata, leaf_hash},
+    instructions::{self, Leaf, LeafAuthority},
+    tree::{empty_root, tree_config_address, tree_size},
+    MetadataArgs, TokenProgramVersion, TokenStandard,
+};
+use common::{root_and_proof, BankChain, TestBank, SLOT, SOL};
+use solana_sdk::{pubkey::Pubkey, rent::Rent, signature::Keypair, signer::Signer};
+
+const DEPTH: u32 = 3;
+
+fn metadata() -> MetadataArgs {
+    MetadataArgs {
+        name: "Seedling".into(),
+        symbol: "TREE".into(),
+        uri: "https://example.com/seedling.json".into(),
+        seller_fee_basis_points: 500,
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: Some(TokenStandard::NonFungible),
+        collection: None,
+        uses: None,
+        token_program_version: TokenProgramVersion::Original,
+        creators: vec![],
+    }
+}
+
+fn create_tree(bank: &mut TestBank, payer: &Keypair) -> Pubkey {
+    let tree = Keypair::new();
+    let size = tree_size(3, 8, 0).unwrap();
+    let rent = Rent::default().minimum_balance(size.try_into().unwrap());
+
+    bank.process(
+        &[
+            instructions::create_tree_account(&payer.pubkey(), &tree.pubkey(), rent, size),
+            instructions::create_tree(
+                &tree.pubkey(),
+                &payer.pubkey(),
+                &payer.pubkey(),
+                DEPTH,
+                8,
+                None,
+            ),
+        ],
+        &[payer, &tree],
+    )
+    .unwrap();
+
+    tree.pubkey()
+}
+
+fn proof_accounts(proof: &[[u8; 32]]) -> Vec<Pubkey> {
+    proof.iter().map(|&n| Pubkey::new_from_array(n)).collect()
+}
+
+#[test]
+fn create_and_delegate_tree() {
+    let mut bank = TestBank::new();
+    let payer = Keypair::new();
+    bank.fund(&payer.pubkey(), 10 * SOL);
+
+    let tree = create_tree(&mut bank, &payer);
+
+    let config =
+        decode_tree_config(&bank.account(&tree_config_address(&tree)).unwrap().data).unwrap();
+    assert_eq!(config.tree_creator, payer.pubkey());
+    assert_eq!(config.tree_delegate, payer.pubkey());
+    assert_eq!(config.total_mint_capacity, 1 << DEPTH);
+    assert_eq!(config.num_minted, 0);
+
+    let account = bank.account(&tree).unwrap();
+    assert_eq!(account.owner, spl_account_compression::ID);
+    let merkle_tree = decode_merkle_tree(&account.data).unwrap();
+    assert_eq!(merkle_tree.max_depth, DEPTH);
+    assert_eq!(merkle_tree.max_buffer_size, 8);
+    assert_eq!(merkle_tree.authority, tree_config_address(&tree));
+    assert_eq!(merkle_tree.creation_slot, SLOT);
+    assert_eq!(merkle_tree.canopy_depth, 0);
+    assert_eq!(merkle_tree.root, empty_root(DEPTH));
+
+    let delegate = Pubkey::new_unique();
+    bank.process(
+        &[instructions::set_tree_delegate(
+            &tree,
+            &payer.pubkey(),
+            &delegate,
+        )],
+        &[&payer],
+    )
+    .unwrap();
+
+    let config =
+        decode_tree_config(&bank.account(&tree_config_address(&tree)).unwrap().data).unwrap();
+    assert_eq!(config.tree_delegate, delegate);
+
+    // Only the tree creator may delegate the tree
+    let other = Keypair::new();
+    bank.process(
+        &[instructions::set_tree_delegate(
+            &tree,
+            &other.pubkey(),
+            &other.pubkey(),
+        )],
+        &[&other],
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn mint_transfer_and_burn() {
+    let mut bank = TestBank::new();
+    let payer = Keypair::new();
+    let owner = Keypair::new();
+    let new_owner = Keypair::new();
+    bank.fund(&payer.pubkey(), 10 * SOL);
+
+    let tree = create_tree(&mut bank, &payer);
+    let metadata = metadata();
+    let data_hash = hash_metadata(&metadata).unwrap();
+    let creator_hash = hash_creators(&metadata.creators);
+
+    bank.process(
+        &[instructions::mint_v1(
+            &tree,
+            &owner.pubkey(),
+            &owner.pubkey(),
+            &payer.pubkey(),
+            &payer.pubkey(),
+            metadata,
+        )],
+        &[&payer],
+    )
+    .unwrap();
+
+    let config =
+        decode_tree_config(&bank.account(&tree_config_address(&tree)).unwrap().data).unwrap();
+    assert_eq!(config.num_minted, 1);
+
+    let leaf = leaf_hash(
+        &tree,
+        owner.pubkey(),
+        owner.pubkey(),
+        0,
+        data_hash,
+        creator_hash,
+    );
+    let (root, proof) = root_and_proof(&[leaf], DEPTH, 0);
+    let merkle_tree = decode_merkle_tree(&bank.account(&tree).unwrap().data).unwrap();
+    assert_eq!(merkle_tree.root, root);
+    assert_eq!(merkle_tree.rightmost_index, 1);
+
+    let current = Leaf {
+        root,
+        data_hash,
+        creator_hash,
+        nonce: 0,
+        index: 0,
+    };
+
+    // Transfers must be signed by the owner or delegate
+    bank.process(
+        &[instructions::transfer(
+            &tree,
+            &owner.pubkey(),
+            &owner.pubkey(),
+            LeafAuthority::Owner,
+            &new_owner.pubkey(),
+            &current,
+            &proof_accounts(&proof),
+        )],
+        &[&new_owner],
+    )
+    .unwrap_err();
+
+    bank.process(
+        &[instructions::transfer(
+            &tree,
+            &owner.pubkey(),
+            &owner.pubkey(),
+            LeafAuthority::Owner,
+            &new_owner.pubkey(),
+            &current,
+            &proof_accounts(&proof),
+        )],
+        &[&owner],
+    )
+    .unwrap();
+
+    let leaf = leaf_hash(
+        &tree,
+        new_owner.pubkey(),
+        new_owner.pubkey(),
+        0,
+        data_hash,
+        creator_hash,
+    );
+    let (root, proof) = root_and_proof(&[leaf], DEPTH, 0);
+    let merkle_tree = decode_merkle_tree(&bank.account(&tree).unwrap().data).unwrap();
+    assert_eq!(merkle_tree.root, root);
+
+    bank.process(
+        &[instructions::burn(
+            &tree,
+            &new_owner.pubkey(),
+            &new_owner.pubkey(),
+            LeafAuthority::Owner,
+            &Leaf { root, ..current },
+            &proof_accounts(&proof),
+        )],
+        &[&new_owner],
+    )
+    .unwrap();
+
+    let merkle_tree = decode_merkle_tree(&bank.account(&tree).unwrap().data).unwrap();
+    assert!(merkle_tree.is_empty());
+}
+
+#[test]
+fn delegate_leaf() {
+    let mut bank = TestBank::new();
+    let payer = Keypair::new();
+    let owner = Keypair::new();
+    let delegate = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    bank.fund(&payer.pubkey(), 10 * SOL);
+
+    let tree = create_tree(&mut bank, &payer);
+    let metadata = metadata();
+    let data_hash = hash_metadata(&metadata).unwrap();
+    let creator_hash = hash_creators(&metadata.creators);
+
+    bank.process(
+        &[instructions::mint_v1(
+            &tree,
+            &owner.pubkey(),
+            &owner.pubkey(),
+            &payer.pubkey(),
+            &payer.pubkey(),
+            metadata,
+        )],
+        &[&payer],
+    )
+    .unwrap();
+
+    let leaf = leaf_hash(
+        &tree,
+        owner.pubkey(),
+        owner.pubkey(),
+        0,
+        data_hash,
+        creator_hash,
+    );
+    let (root, proof) = root_and_proof(&[leaf], DEPTH, 0);
+    let current = Leaf {
+        root,
+        data_hash,
+        creator_hash,
+        nonce: 0,
+        index: 0,
+    };
+
+    bank.process(
+        &[instructions::delegate(
+            &tree,
+            &owner.pubkey(),
+            &owner.pubkey(),
+            &delegate.pubkey(),
+            &current,
+            &proof_accounts(&proof),
+        )],
+        &[&owner],
+    )
+    .unwrap();
+
+    // The delegate can now transfer the asset on the owner's behalf
+    let leaf = leaf_hash(
+        &tree,
+        owner.pubkey(),
+        delegate.pubkey(),
+        0,
+        data_hash,
+        creator_hash,
+    );
+    let (root, proof) = root_and_proof(&[leaf], DEPTH, 0);
+
+    bank.process(
+        &[instructions::transfer(
+            &tree,
+            &owner.pubkey(),
+            &delegate.pubkey(),
+            LeafAuthority::Delegate,
+            &recipient,
+            &Leaf { root, ..current },
+            &proof_accounts(&proof),
+        )],
+        &[&delegate],
+    )
+    .unwrap();
+
+    let leaf = leaf_hash(&tree, recipient, recipient, 0, data_hash, creator_hash);
+    let (root, _) = root_and_proof(&[leaf], DEPTH, 0);
+    let merkle_tree = decode_merkle_tree(&bank.account(&tree).unwrap().data).unwrap();
+    assert_eq!(merkle_tree.root, root);
+}
+
+#[test]
+fn client_sends_and_reads_trees() {
+    let mut bank = TestBank::new();
+    let payer = Keypair::new();
+    bank.fund(&payer.pubkey(), 10 * SOL);
+    let tree = create_tree(&mut bank, &payer);
+
+    let client = SolanaClient::new(BankChain::new(bank), false);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let owner = Pubkey::new_unique();
+        let mint = instructions::mint_v1(
+            &tree,
+            &owner,
+            &owner,
+            &payer.pubkey(),
+            &payer.pubkey(),
+            metadata(),
+        );
+        client
+            .send_transaction(&[mint], Some(&payer.pubkey()), &[&payer])
+            .await
+            .unwrap();
+
+        let config = client.tree_config(&tree).await.unwrap();
+        assert_eq!(config.num_minted, 1);
+        let merkle_tree = client.merkle_tree(&tree).await.unwrap();
+        assert_eq!(merkle_tree.rightmost_index, 1);
+
+        // Only the tree delegate may mint
+        let other = Keypair::new();
+        client.chain().with_bank(|b| b.fund(&other.pubkey(), SOL));
+        let mint = instructions::mint_v1(
+            &tree,
+            &owner,
+            &owner,
+            &other.pubkey(),
+            &other.pubkey(),
+            metadata(),
+        );
+        client
+            .send_transaction(&[mint], Some(&other.pubkey()), &[&other])
+            .await
+            .unwrap_err();
+        assert_eq!(client.tree_config(&tree).await.unwrap().num_minted, 1);
+
+        client.merkle_tree(&owner).await.unwrap_err();
+    });
+}
@@ -0,0 +1,29 @@
+//! Shared helpers for tests run against the in-process
+//! [`TestBank`](arborist::bank::TestBank)
+
+#![allow(dead_code, unused_imports)]
+
+pub use arborist::bank::{BankChain, TestBank, SLOT};
+
+/// Lamports in one SOL
+pub const SOL: u64 = 1_000_000_000;
+
+/// Compute the root of a tree and the proof for one of its leaves
+pub fn root_and_proof(leaves: &[[u8; 32]], depth: u32, index: usize) -> ([u8; 32], Vec<[u8; 32]>) {
+    let mut level = leaves.to_vec();
+    level.resize(1 << depth, [0; 32]);
+
+    let mut proof = vec![];
+    let mut index = index;
+
+    while level.len() > 1 {
+        proof.push(level[index ^ 1]);
+        level = level
+            .chunks(2)
+            .map(|p| solana_sdk::keccak::hashv(&[&p[0], &p[1]]).to_bytes())
+            .collect();
+        index /= 2;
+    }
+
+    (level[0], proof)
+}
@@ -0,0 +1,159 @@
+//! End-to-end tests of the account compression instruction builders
+
+mod common;
+
+use arborist::{
+    accounts::decode_merkle_tree, compression, instructions::create_tree_account, tree::tree_size,
+};
+use common::{root_and_proof, TestBank, SLOT, SOL};
+use solana_sdk::{pubkey::Pubkey, rent::Rent, signature::Keypair, signer::Signer};
+
+const DEPTH: u32 = 3;
+
+fn create_tree(bank: &mut TestBank, payer: &Keypair, authority: &Keypair) -> Pubkey {
+    let tree = Keypair::new();
+    let size = tree_size(3, 8, 0).unwrap();
+    let rent = Rent::default().minimum_balance(size.try_into().unwrap());
+
+    bank.process(
+        &[
+            create_tree_account(&payer.pubkey(), &tree.pubkey(), rent, size),
+            compression::init_empty_merkle_tree(&tree.pubkey(), &authority.pubkey(), DEPTH, 8),
+        ],
+        &[payer, authority, &tree],
+    )
+    .unwrap();
+
+    tree.pubkey()
+}
+
+#[test]
+fn append_replace_and_verify() {
+    let mut bank = TestBank::new();
+    let payer = Keypair::new();
+    let authority = Keypair::new();
+    bank.fund(&payer.pubkey(), 10 * SOL);
+
+    let tree = create_tree(&mut bank, &payer, &authority);
+    let merkle_tree = decode_merkle_tree(&bank.account(&tree).unwrap().data).unwrap();
+    assert_eq!(merkle_tree.authority, authority.pubkey());
+    assert_eq!(merkle_tree.creation_slot, SLOT);
+    assert!(merkle_tree.is_empty());
+
+    let mut leaves = vec![[1; 32], [2; 32], [3; 32]];
+    bank.process(
+        &leaves
+            .iter()
+            .map(|&l| compression::append(&tree, &authority.pubkey(), l))
+            .collect::<Vec<_>>(),
+        &[&authority],
+    )
+    .unwrap();
+
+    let (root, proof) = root_and_proof(&leaves, DEPTH, 1);
+    let merkle_tree = decode_merkle_tree(&bank.account(&tree).unwrap().data).unwrap();
+    assert_eq!(merkle_tree.root, root);
+    assert_eq!(merkle_tree.rightmost_index, 3);
+    assert_eq!(merkle_tree.sequence_number, 3);
+
+    // Only the authority can modify the tree
+    let other = Keypair::new();
+    bank.process(&[compression::append(&tree, &other.pubkey(), [4; 32])], &[
+        &other,
+    ])
+    .unwrap_err();
+
+    bank.process(
+        &[compression::replace_leaf(
+            &tree,
+            &authority.pubkey(),
+            root,
+            [2; 32],
+            [9; 32],
+            1,
+            &proof,
+        )],
+        &[&authority],
+    )
+    .unwrap();
+    leaves[1] = [9; 32];
+
+    let (root, proof) = root_and_proof(&leaves, DEPTH, 1);
+    let merkle_tree = decode_merkle_tree(&bank.account(&tree).unwrap().data).unwrap();
+    assert_eq!(merkle_tree.root, root);
+
+    bank.process(
+        &[compression::verify_leaf(&tree, root, [9; 32], 1, &proof)],
+        &[],
+    )
+    .unwrap();
+    bank.process(
+        &[compression::verify_leaf(&tree, root, [2; 32], 1, &proof)],
+        &[],
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn transfer_authority_and_close() {
+    let mut bank = TestBank::new();
+    let payer = Keypair::new();
+    let authority = Keypair::new();
+    let new_authority = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    bank.fund(&payer.pubkey(), 10 * SOL);
+
+    let tree = create_tree(&mut bank, &payer, &authority);
+    let rent = bank.balance(&tree);
+
+    bank.process(
+        &[compression::append(&tree, &authority.pubkey(), [1; 32])],
+        &[&authority],
+    )
+    .unwrap();
+
+    bank.process(
+        &[compression::transfer_authority(
+            &tree,
+            &authority.pubkey(),
+            new_authority.pubkey(),
+        )],
+        &[&authority],
+    )
+    .unwrap();
+
+    let merkle_tree = decode_merkle_tree(&bank.account(&tree).unwrap().data).unwrap();
+    assert_eq!(merkle_tree.authority, new_authority.pubkey());
+
+    // Trees containing leaves cannot be closed
+    bank.process(
+        &[compression::close_empty_tree(
+            &tree,
+            &new_authority.pubkey(),
+            &recipient,
+        )],
+        &[&new_authority],
+    )
+    .unwrap_err();
+
+    let (root, proof) = root_and_proof(&[[1; 32]], DEPTH, 0);
+    bank.process(
+        &[
+            compression::replace_leaf(
+                &tree,
+                &new_authority.pubkey(),
+                root,
+                [1; 32],
+                [0; 32],
+                0,
+                &proof,
+            ),
+            compression::close_empty_tree(&tree, &new_authority.pubkey(), &recipient),
+        ],
+        &[&new_authority],
+    )
+    .unwrap();
+
+    assert!(bank.account(&tree).is_none());
+    assert_eq!(bank.balance(&recipient), rent);
+}